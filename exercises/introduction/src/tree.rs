@@ -1,5 +1,8 @@
+pub mod policy;
 pub mod visitor;
 
+use policy::{ArithmeticError, ArithmeticPolicy};
+
 pub struct Tree {
     root: Expression,
 }
@@ -11,6 +14,11 @@ impl Tree {
     pub fn eval(&self) -> i64 {
         self.root.eval()
     }
+
+    /// Recursively evaluate the tree, with arithmetic following the given policy.
+    pub fn eval_with_policy(&self, policy: ArithmeticPolicy) -> Result<i64, ArithmeticError> {
+        self.root.eval_with_policy(policy)
+    }
 }
 
 /// Types of expressions in our tree.
@@ -44,6 +52,62 @@ impl Expression {
             Expression::Multiplication { left, right } => left.eval() * right.eval(),
         }
     }
+
+    /// Evaluate the value of the expression, with arithmetic following the given policy.
+    pub fn eval_with_policy(&self, policy: ArithmeticPolicy) -> Result<i64, ArithmeticError> {
+        match self {
+            Expression::IntLiteral(i) => policy.literal(*i),
+            Expression::Addition { left, right } => policy.add(
+                left.eval_with_policy(policy)?,
+                right.eval_with_policy(policy)?,
+            ),
+            Expression::Subtraction { left, right } => policy.sub(
+                left.eval_with_policy(policy)?,
+                right.eval_with_policy(policy)?,
+            ),
+            Expression::Multiplication { left, right } => policy.mul(
+                left.eval_with_policy(policy)?,
+                right.eval_with_policy(policy)?,
+            ),
+        }
+    }
+}
+
+impl Expression {
+    // Move the contents of all non-leaf children into the worklist, leaving literals in their
+    // place.
+    fn detach_children(&mut self, worklist: &mut Vec<Expression>) {
+        match self {
+            Expression::IntLiteral(_) => {}
+            Expression::Addition { left, right }
+            | Expression::Subtraction { left, right }
+            | Expression::Multiplication { left, right } => {
+                for child in [left, right] {
+                    if !matches!(**child, Expression::IntLiteral(_)) {
+                        worklist.push(std::mem::replace(&mut **child, Expression::IntLiteral(0)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Dropping an expression the default way recurses once per level of nesting, which overflows the
+/// stack for deep trees. Instead, we detach children into a worklist, so that each node is
+/// dropped while only having literals left as children.
+impl Drop for Expression {
+    fn drop(&mut self) {
+        #[cfg(test)]
+        tests::record_drop(self);
+
+        let mut worklist = Vec::new();
+        self.detach_children(&mut worklist);
+
+        while let Some(mut expr) = worklist.pop() {
+            expr.detach_children(&mut worklist);
+            // `expr` is dropped here, with its own drop having nothing left to detach.
+        }
+    }
 }
 
 /// Evaluate the arithmetic expression encoded in the tree.
@@ -63,8 +127,40 @@ fn eval_recursive(expr: &Expression) -> i64 {
     }
 }
 
+/// Evaluate the arithmetic expression encoded in the tree, with arithmetic following the given
+/// policy.
+pub fn eval_with_policy(tree: &Tree, policy: ArithmeticPolicy) -> Result<i64, ArithmeticError> {
+    eval_recursive_with_policy(&tree.root, policy)
+}
+
+// Recursive evaluation of the tree in a procedural approach, honouring an arithmetic policy.
+fn eval_recursive_with_policy(
+    expr: &Expression,
+    policy: ArithmeticPolicy,
+) -> Result<i64, ArithmeticError> {
+    match expr {
+        Expression::IntLiteral(i) => policy.literal(*i),
+        Expression::Addition { left, right } => policy.add(
+            eval_recursive_with_policy(left, policy)?,
+            eval_recursive_with_policy(right, policy)?,
+        ),
+        Expression::Subtraction { left, right } => policy.sub(
+            eval_recursive_with_policy(left, policy)?,
+            eval_recursive_with_policy(right, policy)?,
+        ),
+        Expression::Multiplication { left, right } => policy.mul(
+            eval_recursive_with_policy(left, policy)?,
+            eval_recursive_with_policy(right, policy)?,
+        ),
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::cell::RefCell;
+
+    use super::policy::{Overflow, Width};
+    use super::visitor::EvalVisitor;
     use super::*;
 
     fn sample_tree() -> Tree {
@@ -117,4 +213,259 @@ mod tests {
         let tree = sample_tree();
         assert_eq!(tree.eval(), 60);
     }
+
+    // (2^15 * 2^15 - 1) + 2^15 * 2^15 = i32::MAX, so adding one more overflows i32 but not i64.
+    fn overflowing_i32_tree() -> Tree {
+        let square = || {
+            Box::new(Expression::Multiplication {
+                left: Box::new(Expression::IntLiteral(1 << 15)),
+                right: Box::new(Expression::IntLiteral(1 << 15)),
+            })
+        };
+
+        Tree {
+            root: Expression::Addition {
+                left: Box::new(Expression::Addition {
+                    left: Box::new(Expression::Subtraction {
+                        left: square(),
+                        right: Box::new(Expression::IntLiteral(1)),
+                    }),
+                    right: square(),
+                }),
+                right: Box::new(Expression::IntLiteral(1)),
+            },
+        }
+    }
+
+    // Evaluate with all three evaluators, asserting that they agree.
+    fn eval_all(tree: &Tree, policy: ArithmeticPolicy) -> Result<i64, ArithmeticError> {
+        let oop = tree.eval_with_policy(policy);
+        let procedural = eval_with_policy(tree, policy);
+
+        let mut visitor = EvalVisitor::with_policy(policy);
+        tree.root.accept(&mut visitor);
+        let visited = visitor.try_result();
+
+        assert_eq!(oop, procedural);
+        assert_eq!(oop, visited);
+
+        oop
+    }
+
+    #[test]
+    fn test_policy_default() {
+        let tree = sample_tree();
+        assert_eq!(
+            eval_all(&tree, ArithmeticPolicy::default()),
+            Ok(tree.eval())
+        );
+
+        let tree = overflowing_i32_tree();
+        assert_eq!(eval_all(&tree, ArithmeticPolicy::default()), Ok(1 << 31));
+        assert_eq!(
+            ArithmeticPolicy::default(),
+            ArithmeticPolicy::new(Overflow::Native, Width::I64)
+        );
+    }
+
+    // Like the plain `eval`, which overflows the same way.
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "overflow")]
+    fn test_policy_default_overflow() {
+        let tree = Tree {
+            root: Expression::Addition {
+                left: Box::new(Expression::IntLiteral(i64::MAX)),
+                right: Box::new(Expression::IntLiteral(1)),
+            },
+        };
+
+        let _ = tree.eval_with_policy(ArithmeticPolicy::default());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "overflow")]
+    fn test_policy_native_i32_overflow() {
+        let _ = overflowing_i32_tree()
+            .eval_with_policy(ArithmeticPolicy::new(Overflow::Native, Width::I32));
+    }
+
+    #[test]
+    fn test_policy_i32() {
+        let tree = overflowing_i32_tree();
+
+        assert_eq!(
+            eval_all(&tree, ArithmeticPolicy::new(Overflow::Wrapping, Width::I32)),
+            Ok(i32::MIN as i64)
+        );
+        assert_eq!(
+            eval_all(
+                &tree,
+                ArithmeticPolicy::new(Overflow::Saturating, Width::I32)
+            ),
+            Ok(i32::MAX as i64)
+        );
+        assert_eq!(
+            eval_all(&tree, ArithmeticPolicy::new(Overflow::Checked, Width::I32)),
+            Err(ArithmeticError::Overflow)
+        );
+
+        // Same tree, but no overflow in i64 mode.
+        for overflow in [Overflow::Wrapping, Overflow::Saturating, Overflow::Checked] {
+            assert_eq!(
+                eval_all(&tree, ArithmeticPolicy::new(overflow, Width::I64)),
+                Ok(1 << 31)
+            );
+        }
+    }
+
+    #[test]
+    fn test_policy_i64() {
+        let tree = Tree {
+            root: Expression::Subtraction {
+                left: Box::new(Expression::IntLiteral(i64::MIN)),
+                right: Box::new(Expression::IntLiteral(1)),
+            },
+        };
+
+        assert_eq!(
+            eval_all(&tree, ArithmeticPolicy::new(Overflow::Wrapping, Width::I64)),
+            Ok(i64::MAX)
+        );
+        assert_eq!(
+            eval_all(
+                &tree,
+                ArithmeticPolicy::new(Overflow::Saturating, Width::I64)
+            ),
+            Ok(i64::MIN)
+        );
+        assert_eq!(
+            eval_all(&tree, ArithmeticPolicy::new(Overflow::Checked, Width::I64)),
+            Err(ArithmeticError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_policy_i32_literal() {
+        let tree = Tree {
+            root: Expression::IntLiteral(i32::MAX as i64 + 1),
+        };
+
+        assert_eq!(
+            eval_all(&tree, ArithmeticPolicy::new(Overflow::Wrapping, Width::I32)),
+            Ok(i32::MIN as i64)
+        );
+        assert_eq!(
+            eval_all(
+                &tree,
+                ArithmeticPolicy::new(Overflow::Saturating, Width::I32)
+            ),
+            Ok(i32::MAX as i64)
+        );
+        assert_eq!(
+            eval_all(&tree, ArithmeticPolicy::new(Overflow::Checked, Width::I32)),
+            Err(ArithmeticError::Overflow)
+        );
+    }
+
+    type Constructor = fn(Box<Expression>, Box<Expression>) -> Expression;
+
+    // Build a chain of the given depth, nesting via either the left or the right operand. Its
+    // leaves are numbered from 1, in the order they are added.
+    fn deep_chain(depth: usize, nest_left: bool, node: Constructor) -> Expression {
+        let mut expr = Expression::IntLiteral(1);
+
+        for i in 0..depth {
+            let leaf = Box::new(Expression::IntLiteral(i as i64 + 2));
+            expr = if nest_left {
+                node(Box::new(expr), leaf)
+            } else {
+                node(leaf, Box::new(expr))
+            };
+        }
+
+        expr
+    }
+
+    #[test]
+    fn test_drop_deep_chains() {
+        let constructors: [Constructor; 3] = [
+            |left, right| Expression::Addition { left, right },
+            |left, right| Expression::Subtraction { left, right },
+            |left, right| Expression::Multiplication { left, right },
+        ];
+
+        for node in constructors {
+            for nest_left in [true, false] {
+                // Would overflow the stack with the default, recursive, drop.
+                drop(deep_chain(200_000, nest_left, node));
+            }
+        }
+    }
+
+    #[test]
+    fn test_drop_small_tree() {
+        let tree = sample_tree();
+        assert_eq!(tree.eval(), 60);
+        drop(tree);
+
+        let mut worklist = Vec::new();
+        let mut expr = deep_chain(2, true, |left, right| Expression::Addition { left, right });
+        expr.detach_children(&mut worklist);
+
+        // Only the nested addition is detached, the literal stays in place.
+        assert_eq!(worklist.len(), 1);
+        assert_eq!(worklist[0].eval(), 1 + 2);
+        assert_eq!(expr.eval(), 3);
+    }
+
+    thread_local! {
+        // Values of the leaves dropped by the current thread.
+        static DROPPED_LEAVES: RefCell<Vec<i64>> = const { RefCell::new(Vec::new()) };
+    }
+
+    // Called by every drop of an expression. The literals `detach_children` leaves in place of
+    // detached children are 0, so tests number their leaves from 1 to tell them apart.
+    pub(super) fn record_drop(expr: &Expression) {
+        if let Expression::IntLiteral(i @ 1..) = expr {
+            DROPPED_LEAVES.with(|leaves| leaves.borrow_mut().push(*i));
+        }
+    }
+
+    // Drop `expr` on a thread with a stack far too small to recurse once per level, returning the
+    // values of the leaves dropped, sorted.
+    fn drop_on_small_stack(expr: Expression) -> Vec<i64> {
+        std::thread::Builder::new()
+            .stack_size(64 << 10)
+            .spawn(move || {
+                drop(expr);
+                let mut dropped = DROPPED_LEAVES.take();
+                dropped.sort_unstable();
+                dropped
+            })
+            .unwrap()
+            .join()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_drop_counts() {
+        // Every leaf is dropped exactly once: none is leaked along with its parents, and none is
+        // dropped twice.
+        let Tree { root } = sample_tree();
+        assert_eq!(drop_on_small_stack(root), vec![2, 3, 5, 7, 11, 12]);
+
+        let constructors: [Constructor; 3] = [
+            |left, right| Expression::Addition { left, right },
+            |left, right| Expression::Subtraction { left, right },
+            |left, right| Expression::Multiplication { left, right },
+        ];
+        for node in constructors {
+            for nest_left in [true, false] {
+                let dropped = drop_on_small_stack(deep_chain(200_000, nest_left, node));
+                assert!(dropped.iter().copied().eq(1..=200_001));
+            }
+        }
+    }
 }
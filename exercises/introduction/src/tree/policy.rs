@@ -0,0 +1,132 @@
+use std::{
+    fmt::Display,
+    ops::{Add, Mul, Sub},
+};
+
+/// Behaviour of arithmetic operations whose result does not fit into the selected integer width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Plain Rust arithmetic in the selected width, as done by the `eval` functions: overflows
+    /// behave as in the current build, so they panic in debug builds and wrap around in release
+    /// builds.
+    Native,
+    /// Results wrap around using two's complement arithmetic.
+    Wrapping,
+    /// Results are clamped to the minimum or maximum representable value.
+    Saturating,
+    /// Overflows are reported as an error.
+    Checked,
+}
+
+/// Width of the integers the evaluation is carried out in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Width {
+    I32,
+    I64,
+}
+
+/// Error returned when evaluation under a checked policy overflows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithmeticError {
+    Overflow,
+}
+
+impl Display for ArithmeticError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArithmeticError::Overflow => write!(f, "Arithmetic overflow"),
+        }
+    }
+}
+
+/// Policy describing how the arithmetic of the tree evaluators behaves.
+///
+/// Evaluation happens in i64, except for native arithmetic, which is carried out in the selected
+/// width. In i32 mode, results are then wrapped, clamped or checked against the i32 range,
+/// depending on the overflow behaviour, which gives the same outcome as evaluating in i32 directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArithmeticPolicy {
+    pub overflow: Overflow,
+    pub width: Width,
+}
+
+impl Default for ArithmeticPolicy {
+    /// Native i64 arithmetic, which is what the plain `eval` functions do.
+    fn default() -> Self {
+        ArithmeticPolicy {
+            overflow: Overflow::Native,
+            width: Width::I64,
+        }
+    }
+}
+
+impl ArithmeticPolicy {
+    pub fn new(overflow: Overflow, width: Width) -> ArithmeticPolicy {
+        ArithmeticPolicy { overflow, width }
+    }
+
+    /// Bring an integer literal into the selected width.
+    pub fn literal(&self, i: i64) -> Result<i64, ArithmeticError> {
+        self.narrow(Some(i))
+    }
+
+    pub fn add(&self, left: i64, right: i64) -> Result<i64, ArithmeticError> {
+        match self.overflow {
+            Overflow::Native => Ok(self.native(left, right, i64::add, i32::add)),
+            Overflow::Wrapping => self.narrow(Some(left.wrapping_add(right))),
+            Overflow::Saturating => self.narrow(Some(left.saturating_add(right))),
+            Overflow::Checked => self.narrow(left.checked_add(right)),
+        }
+    }
+
+    pub fn sub(&self, left: i64, right: i64) -> Result<i64, ArithmeticError> {
+        match self.overflow {
+            Overflow::Native => Ok(self.native(left, right, i64::sub, i32::sub)),
+            Overflow::Wrapping => self.narrow(Some(left.wrapping_sub(right))),
+            Overflow::Saturating => self.narrow(Some(left.saturating_sub(right))),
+            Overflow::Checked => self.narrow(left.checked_sub(right)),
+        }
+    }
+
+    pub fn mul(&self, left: i64, right: i64) -> Result<i64, ArithmeticError> {
+        match self.overflow {
+            Overflow::Native => Ok(self.native(left, right, i64::mul, i32::mul)),
+            Overflow::Wrapping => self.narrow(Some(left.wrapping_mul(right))),
+            Overflow::Saturating => self.narrow(Some(left.saturating_mul(right))),
+            Overflow::Checked => self.narrow(left.checked_mul(right)),
+        }
+    }
+
+    // Carry out an operation in the selected width, with the overflow behaviour of the build.
+    fn native(
+        &self,
+        left: i64,
+        right: i64,
+        op64: fn(i64, i64) -> i64,
+        op32: fn(i32, i32) -> i32,
+    ) -> i64 {
+        match self.width {
+            Width::I64 => op64(left, right),
+            // The operands were brought into the i32 range already.
+            Width::I32 => op32(left as i32, right as i32) as i64,
+        }
+    }
+
+    // Apply the selected width to the i64 result of an operation. A result of None signals that
+    // the operation already overflowed i64, which can only happen in checked mode.
+    fn narrow(&self, result: Option<i64>) -> Result<i64, ArithmeticError> {
+        let result = result.ok_or(ArithmeticError::Overflow)?;
+
+        match self.width {
+            Width::I64 => Ok(result),
+            Width::I32 => match self.overflow {
+                // Only literals are narrowed natively, which `as` truncates.
+                Overflow::Native | Overflow::Wrapping => Ok(result as i32 as i64),
+                Overflow::Saturating => Ok(result.clamp(i32::MIN as i64, i32::MAX as i64)),
+                Overflow::Checked => i32::try_from(result)
+                    .map(|i| i as i64)
+                    .map_err(|_| ArithmeticError::Overflow),
+            },
+        }
+    }
+}
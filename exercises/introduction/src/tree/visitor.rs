@@ -1,5 +1,6 @@
 use crate::stack::Stack;
 
+use super::policy::{ArithmeticError, ArithmeticPolicy};
 use super::Expression;
 
 pub trait Visitor {
@@ -8,42 +9,81 @@ pub trait Visitor {
 
 pub struct EvalVisitor {
     stack: Stack<i64>,
+    policy: ArithmeticPolicy,
+    // First error encountered during evaluation, if any.
+    error: Option<ArithmeticError>,
 }
 
 impl EvalVisitor {
     pub fn new() -> EvalVisitor {
+        EvalVisitor::with_policy(ArithmeticPolicy::default())
+    }
+
+    /// Create a visitor whose arithmetic follows the given policy.
+    pub fn with_policy(policy: ArithmeticPolicy) -> EvalVisitor {
         EvalVisitor {
             stack: Stack::new(),
+            policy,
+            error: None,
         }
     }
 
+    /// Return the result of the evaluation.
+    ///
+    /// Panics if the evaluation failed, see `try_result` for a non-panicking alternative.
     pub fn result(&mut self) -> i64 {
-        self.stack.pop().unwrap()
+        self.try_result().unwrap()
     }
-}
 
-impl Visitor for EvalVisitor {
-    fn visit_expression(&mut self, expr: &Expression) {
-        match expr {
-            Expression::IntLiteral(i) => self.stack.push(*i),
-            Expression::Addition { left: _, right: _ } => {
-                let left = self.stack.pop().unwrap();
-                let right = self.stack.pop().unwrap();
+    /// Return the result of the evaluation, or the first error encountered during it.
+    ///
+    /// Only the checked policy reports errors, others never fail.
+    pub fn try_result(&mut self) -> Result<i64, ArithmeticError> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.stack.pop().unwrap()),
+        }
+    }
 
-                self.stack.push(left + right);
+    // Pop both operands, apply the operation, and push its result. Errors are recorded, with a
+    // placeholder being pushed to keep the stack balanced for the remaining operations.
+    fn apply<F>(&mut self, op: F)
+    where
+        F: Fn(&ArithmeticPolicy, i64, i64) -> Result<i64, ArithmeticError>,
+    {
+        // The right operand was visited last, so is on top of the stack.
+        let right = self.stack.pop().unwrap();
+        let left = self.stack.pop().unwrap();
+
+        match op(&self.policy, left, right) {
+            Ok(i) => self.stack.push(i),
+            Err(e) => {
+                self.error.get_or_insert(e);
+                self.stack.push(0);
             }
-            Expression::Subtraction { left: _, right: _ } => {
-                let left = self.stack.pop().unwrap();
-                let right = self.stack.pop().unwrap();
+        }
+    }
+}
 
-                self.stack.push(left - right);
-            }
-            Expression::Multiplication { left: _, right: _ } => {
-                let left = self.stack.pop().unwrap();
-                let right = self.stack.pop().unwrap();
+impl Default for EvalVisitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-                self.stack.push(left * right);
-            }
+impl Visitor for EvalVisitor {
+    fn visit_expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::IntLiteral(i) => match self.policy.literal(*i) {
+                Ok(i) => self.stack.push(i),
+                Err(e) => {
+                    self.error.get_or_insert(e);
+                    self.stack.push(0);
+                }
+            },
+            Expression::Addition { left: _, right: _ } => self.apply(ArithmeticPolicy::add),
+            Expression::Subtraction { left: _, right: _ } => self.apply(ArithmeticPolicy::sub),
+            Expression::Multiplication { left: _, right: _ } => self.apply(ArithmeticPolicy::mul),
         };
     }
 }
@@ -112,4 +152,16 @@ mod tests {
         tree.root.accept(&mut visitor);
         assert_eq!(visitor.result(), 60);
     }
+
+    #[test]
+    fn test_visit_subtraction() {
+        let expr = Expression::Subtraction {
+            left: Box::new(Expression::IntLiteral(10)),
+            right: Box::new(Expression::IntLiteral(3)),
+        };
+        let mut visitor = EvalVisitor::new();
+
+        expr.accept(&mut visitor);
+        assert_eq!(visitor.result(), 7);
+    }
 }
@@ -0,0 +1,1204 @@
+//! Abstract syntax tree of SPL programs, as produced by the parser.
+//!
+//! Nodes keep the tokens they originate from, as well as the span of source they cover, so that
+//! later phases can report errors at the right position in the source.
+
+use std::{borrow::Cow, mem};
+
+use crate::{
+    position::{Position, Span},
+    token::{Literal, Token, TokenType},
+    visitor::{ExprVisitor, StmtVisitor},
+};
+
+/// Expressions, which evaluate to a value.
+///
+/// Each node has a span, which covers all of its source - parentheses included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    /// A number, string, char, boolean, or `nil`, whose value is the literal of the token. The
+    /// parser sets it also for tokens which the lexer didn't produce.
+    Literal { token: Token<'static>, span: Span },
+    /// A reference to a variable.
+    Variable { name: Token<'static>, span: Span },
+    /// An expression enclosed in parentheses, kept so that the source can be reproduced.
+    Grouping { expr: Box<Expr>, span: Span },
+    /// A prefix operator, `!` or `-`, applied to its operand.
+    Unary {
+        operator: Token<'static>,
+        operand: Box<Expr>,
+        span: Span,
+    },
+    /// Assignment of a value to a variable, which evaluates to the value.
+    Assign {
+        name: Token<'static>,
+        value: Box<Expr>,
+        span: Span,
+    },
+    /// A binary operator, including the logical `and` and `or`, applied to its operands.
+    Binary {
+        left: Box<Expr>,
+        operator: Token<'static>,
+        right: Box<Expr>,
+        span: Span,
+    },
+    /// A call of `callee` with arguments. The closing parenthesis is kept to report errors of the
+    /// call itself at.
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+        closing_paren: Token<'static>,
+        span: Span,
+    },
+}
+
+impl Expr {
+    /// Source of the expression, from its first token to its last one.
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Literal { span, .. }
+            | Expr::Variable { span, .. }
+            | Expr::Grouping { span, .. }
+            | Expr::Unary { span, .. }
+            | Expr::Assign { span, .. }
+            | Expr::Binary { span, .. }
+            | Expr::Call { span, .. } => *span,
+        }
+    }
+}
+
+/// Statements, which are executed for their effect.
+///
+/// Each node has a span, which covers all of its source - the terminating `;` included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stmt {
+    /// An expression evaluated for its side effects, such as `a + b;`.
+    Expression { expr: Expr, span: Span },
+    /// `print expr;`
+    Print {
+        keyword: Token<'static>,
+        expr: Expr,
+        span: Span,
+    },
+    /// `var name = initializer;`, where the initializer is optional.
+    Var {
+        name: Token<'static>,
+        initializer: Option<Expr>,
+        span: Span,
+    },
+    /// `if (condition) then_branch else else_branch`, where the else branch is optional.
+    If {
+        keyword: Token<'static>,
+        condition: Expr,
+        then_branch: Box<Stmt>,
+        else_branch: Option<Box<Stmt>>,
+        span: Span,
+    },
+    /// `while (condition) body`
+    While {
+        keyword: Token<'static>,
+        condition: Expr,
+        body: Box<Stmt>,
+        span: Span,
+    },
+    /// Statements enclosed in braces, which form a scope of their own.
+    Block { statements: Vec<Stmt>, span: Span },
+    /// `fun name(params) { body }`
+    Function {
+        name: Token<'static>,
+        params: Vec<Token<'static>>,
+        body: Vec<Stmt>,
+        span: Span,
+    },
+    /// `return value;`, where the value is optional. `return;` has none, unlike `return nil;`.
+    Return {
+        keyword: Token<'static>,
+        value: Option<Expr>,
+        span: Span,
+    },
+    /// `break;`
+    Break { keyword: Token<'static>, span: Span },
+    /// `continue;`
+    Continue { keyword: Token<'static>, span: Span },
+}
+
+// Pins the sizes of syntax tree nodes, which mostly consist of tokens, see the size of `Token`.
+// The largest variants hold a token next to an expression.
+#[cfg(target_pointer_width = "64")]
+const _: () = assert!(mem::size_of::<Expr>() <= 160 && mem::size_of::<Stmt>() <= 304);
+
+impl Stmt {
+    /// Source of the statement, from its first token to its last one.
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Expression { span, .. }
+            | Stmt::Print { span, .. }
+            | Stmt::Var { span, .. }
+            | Stmt::If { span, .. }
+            | Stmt::While { span, .. }
+            | Stmt::Block { span, .. }
+            | Stmt::Function { span, .. }
+            | Stmt::Return { span, .. }
+            | Stmt::Break { span, .. }
+            | Stmt::Continue { span, .. } => *span,
+        }
+    }
+}
+
+/// Nodes detached from their parents while dropping a syntax tree.
+#[derive(Default)]
+struct Worklist {
+    exprs: Vec<Expr>,
+    stmts: Vec<Stmt>,
+}
+
+impl Worklist {
+    // Drop all nodes, detaching their children first.
+    fn drop_all(&mut self) {
+        loop {
+            if let Some(mut expr) = self.exprs.pop() {
+                expr.detach_children(self);
+            } else if let Some(mut stmt) = self.stmts.pop() {
+                stmt.detach_children(self);
+            } else {
+                break;
+            }
+            // The node is dropped here, with its own drop having nothing left to detach.
+        }
+    }
+}
+
+impl Expr {
+    // Leaf which detached children are replaced with.
+    fn placeholder() -> Expr {
+        let span = Span { start: 0, end: 0 };
+        Expr::Literal {
+            token: Token {
+                token_type: TokenType::Nil,
+                lexeme: Cow::Borrowed("nil"),
+                start: Position::default(),
+                end: Position::default(),
+                span,
+                symbol: None,
+                literal: Some(Literal::None),
+            },
+            span,
+        }
+    }
+
+    // Move all children which have children of their own into the worklist, leaving placeholders
+    // in their place.
+    fn detach_children(&mut self, worklist: &mut Worklist) {
+        match self {
+            Expr::Literal { .. } | Expr::Variable { .. } => {}
+            Expr::Grouping { expr, .. }
+            | Expr::Unary { operand: expr, .. }
+            | Expr::Assign { value: expr, .. } => expr.detach(worklist),
+            Expr::Binary { left, right, .. } => {
+                left.detach(worklist);
+                right.detach(worklist);
+            }
+            Expr::Call { callee, args, .. } => {
+                callee.detach(worklist);
+                worklist.exprs.append(args);
+            }
+        }
+    }
+
+    // Move this expression into the worklist, unless it is a leaf.
+    fn detach(&mut self, worklist: &mut Worklist) {
+        if !matches!(self, Expr::Literal { .. } | Expr::Variable { .. }) {
+            worklist.exprs.push(mem::replace(self, Expr::placeholder()));
+        }
+    }
+}
+
+impl Stmt {
+    // Move all children which have children of their own into the worklist, leaving placeholders
+    // in their place. Expressions of the statement itself stay, with their children detached.
+    fn detach_children(&mut self, worklist: &mut Worklist) {
+        match self {
+            Stmt::Expression { expr, .. } | Stmt::Print { expr, .. } => {
+                expr.detach_children(worklist)
+            }
+            Stmt::Var {
+                initializer: expr, ..
+            }
+            | Stmt::Return { value: expr, .. } => {
+                if let Some(expr) = expr {
+                    expr.detach_children(worklist);
+                }
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                condition.detach_children(worklist);
+                then_branch.detach(worklist);
+                if let Some(else_branch) = else_branch.take() {
+                    worklist.stmts.push(*else_branch);
+                }
+            }
+            Stmt::While {
+                condition, body, ..
+            } => {
+                condition.detach_children(worklist);
+                body.detach(worklist);
+            }
+            Stmt::Block {
+                statements: body, ..
+            }
+            | Stmt::Function { body, .. } => {
+                worklist.stmts.append(body);
+            }
+            Stmt::Break { .. } | Stmt::Continue { .. } => {}
+        }
+    }
+
+    // Move this statement into the worklist, leaving an empty block in its place.
+    fn detach(&mut self, worklist: &mut Worklist) {
+        let empty = Stmt::Block {
+            statements: Vec::new(),
+            span: self.span(),
+        };
+        worklist.stmts.push(mem::replace(self, empty));
+    }
+}
+
+/// Dropping a syntax tree the default way recurses once per level of nesting, which overflows the
+/// stack for deep trees - such as long chains of operators. Instead, we detach children into a
+/// worklist, so that each node is dropped while only having leaves left as children.
+impl Drop for Expr {
+    fn drop(&mut self) {
+        let mut worklist = Worklist::default();
+        self.detach_children(&mut worklist);
+        worklist.drop_all();
+    }
+}
+
+impl Drop for Stmt {
+    fn drop(&mut self) {
+        let mut worklist = Worklist::default();
+        self.detach_children(&mut worklist);
+        worklist.drop_all();
+    }
+}
+
+/// Printer of the canonical textual form of syntax trees, as Lisp-style S-expressions such as
+/// `(+ 1 (* 2 3))`, `(if (== a 1) (block (print a)) (block))` or `(fun f (a b) (print (call g a)))`.
+///
+/// The output is deterministic and contains no line breaks, except between the statements of a
+/// program, so it is suited for comparing syntax trees in tests.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AstPrinter;
+
+impl AstPrinter {
+    pub fn print_expr(&mut self, expr: &Expr) -> String {
+        expr.accept(self)
+    }
+
+    pub fn print_stmt(&mut self, stmt: &Stmt) -> String {
+        stmt.accept(self)
+    }
+
+    /// Print the statements of a program, one per line.
+    pub fn print_program(&mut self, program: &[Stmt]) -> String {
+        program
+            .iter()
+            .map(|statement| statement.accept(self))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl ExprVisitor<String> for AstPrinter {
+    fn visit_literal(&mut self, value: &Literal, token: &Token<'static>) -> String {
+        match value {
+            Literal::Number(n) => n.to_string(),
+            Literal::Str(s) if token.token_type == TokenType::Char => quote(s, '\''),
+            Literal::Str(s) => quote(s, '"'),
+            Literal::Bool(b) => b.to_string(),
+            Literal::None => "nil".to_string(),
+        }
+    }
+
+    fn visit_variable(&mut self, name: &Token<'static>) -> String {
+        name.lexeme.to_string()
+    }
+
+    fn visit_assign(&mut self, name: &Token<'static>, value: &Expr) -> String {
+        format!("(= {} {})", name.lexeme, value.accept(self))
+    }
+
+    fn visit_grouping(&mut self, expr: &Expr) -> String {
+        // Transparent, as the output is fully parenthesized anyway.
+        expr.accept(self)
+    }
+
+    fn visit_unary(&mut self, operator: &Token<'static>, operand: &Expr) -> String {
+        format!("({} {})", operator.lexeme, operand.accept(self))
+    }
+
+    fn visit_binary(&mut self, left: &Expr, operator: &Token<'static>, right: &Expr) -> String {
+        format!(
+            "({} {} {})",
+            operator.lexeme,
+            left.accept(self),
+            right.accept(self)
+        )
+    }
+
+    fn visit_call(
+        &mut self,
+        callee: &Expr,
+        args: &[Expr],
+        _closing_paren: &Token<'static>,
+    ) -> String {
+        let mut out = format!("(call {}", callee.accept(self));
+        for arg in args {
+            out.push(' ');
+            out.push_str(&arg.accept(self));
+        }
+        out.push(')');
+        out
+    }
+}
+
+impl StmtVisitor<String> for AstPrinter {
+    fn visit_expression_stmt(&mut self, expr: &Expr) -> String {
+        format!("(expr {})", expr.accept(self))
+    }
+
+    fn visit_print(&mut self, _keyword: &Token<'static>, expr: &Expr) -> String {
+        format!("(print {})", expr.accept(self))
+    }
+
+    fn visit_var(&mut self, name: &Token<'static>, initializer: Option<&Expr>) -> String {
+        match initializer {
+            Some(initializer) => format!("(var {} {})", name.lexeme, initializer.accept(self)),
+            None => format!("(var {})", name.lexeme),
+        }
+    }
+
+    fn visit_if(
+        &mut self,
+        _keyword: &Token<'static>,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> String {
+        let condition = condition.accept(self);
+        let then_branch = then_branch.accept(self);
+
+        match else_branch {
+            Some(else_branch) => format!(
+                "(if {} {} {})",
+                condition,
+                then_branch,
+                else_branch.accept(self)
+            ),
+            None => format!("(if {} {})", condition, then_branch),
+        }
+    }
+
+    fn visit_while(&mut self, _keyword: &Token<'static>, condition: &Expr, body: &Stmt) -> String {
+        format!("(while {} {})", condition.accept(self), body.accept(self))
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt]) -> String {
+        let mut out = String::from("(block");
+        for statement in statements {
+            out.push(' ');
+            out.push_str(&statement.accept(self));
+        }
+        out.push(')');
+        out
+    }
+
+    fn visit_function(
+        &mut self,
+        name: &Token<'static>,
+        params: &[Token<'static>],
+        body: &[Stmt],
+    ) -> String {
+        let params: Vec<&str> = params.iter().map(|param| param.lexeme.as_ref()).collect();
+        let mut out = format!("(fun {} ({})", name.lexeme, params.join(" "));
+        for statement in body {
+            out.push(' ');
+            out.push_str(&statement.accept(self));
+        }
+        out.push(')');
+        out
+    }
+
+    fn visit_return(&mut self, _keyword: &Token<'static>, value: Option<&Expr>) -> String {
+        match value {
+            Some(value) => format!("(return {})", value.accept(self)),
+            None => "(return)".to_string(),
+        }
+    }
+
+    fn visit_break(&mut self, _keyword: &Token<'static>) -> String {
+        "(break)".to_string()
+    }
+
+    fn visit_continue(&mut self, _keyword: &Token<'static>) -> String {
+        "(continue)".to_string()
+    }
+}
+
+/// Printer of syntax trees as SPL source, such as `(1 + 2) * 3;`.
+///
+/// Unlike `AstPrinter`, parentheses are only printed where the source had them, so that the output
+/// reads like the original. Whitespace and comments are not preserved, though: Statements are
+/// printed one per line, with the contents of blocks indented by four spaces.
+#[derive(Debug, Clone, Default)]
+pub struct SourcePrinter {
+    // Depth of the blocks the statement being printed is nested in.
+    depth: usize,
+}
+
+impl SourcePrinter {
+    pub fn print_expr(&mut self, expr: &Expr) -> String {
+        expr.accept(self)
+    }
+
+    pub fn print_stmt(&mut self, stmt: &Stmt) -> String {
+        stmt.accept(self)
+    }
+
+    /// Print the statements of a program, one per line.
+    pub fn print_program(&mut self, program: &[Stmt]) -> String {
+        program
+            .iter()
+            .map(|statement| statement.accept(self))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl ExprVisitor<String> for SourcePrinter {
+    fn visit_literal(&mut self, value: &Literal, token: &Token<'static>) -> String {
+        match (value, token.token_type) {
+            (Literal::Str(s), TokenType::Char) => quote(s, '\''),
+            (Literal::Str(s), _) => quote(s, '"'),
+            _ => token.lexeme.to_string(),
+        }
+    }
+
+    fn visit_variable(&mut self, name: &Token<'static>) -> String {
+        name.lexeme.to_string()
+    }
+
+    fn visit_assign(&mut self, name: &Token<'static>, value: &Expr) -> String {
+        format!("{} = {}", name.lexeme, value.accept(self))
+    }
+
+    fn visit_grouping(&mut self, expr: &Expr) -> String {
+        format!("({})", expr.accept(self))
+    }
+
+    fn visit_unary(&mut self, operator: &Token<'static>, operand: &Expr) -> String {
+        format!("{}{}", operator.lexeme, operand.accept(self))
+    }
+
+    fn visit_binary(&mut self, left: &Expr, operator: &Token<'static>, right: &Expr) -> String {
+        format!(
+            "{} {} {}",
+            left.accept(self),
+            operator.lexeme,
+            right.accept(self)
+        )
+    }
+
+    fn visit_call(
+        &mut self,
+        callee: &Expr,
+        args: &[Expr],
+        _closing_paren: &Token<'static>,
+    ) -> String {
+        let args: Vec<String> = args.iter().map(|arg| arg.accept(self)).collect();
+        format!("{}({})", callee.accept(self), args.join(", "))
+    }
+}
+
+impl StmtVisitor<String> for SourcePrinter {
+    fn visit_expression_stmt(&mut self, expr: &Expr) -> String {
+        format!("{};", expr.accept(self))
+    }
+
+    fn visit_print(&mut self, _keyword: &Token<'static>, expr: &Expr) -> String {
+        format!("print {};", expr.accept(self))
+    }
+
+    fn visit_var(&mut self, name: &Token<'static>, initializer: Option<&Expr>) -> String {
+        match initializer {
+            Some(initializer) => format!("var {} = {};", name.lexeme, initializer.accept(self)),
+            None => format!("var {};", name.lexeme),
+        }
+    }
+
+    fn visit_if(
+        &mut self,
+        _keyword: &Token<'static>,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> String {
+        let mut out = format!(
+            "if ({}) {}",
+            condition.accept(self),
+            then_branch.accept(self)
+        );
+        if let Some(else_branch) = else_branch {
+            out.push_str(" else ");
+            out.push_str(&else_branch.accept(self));
+        }
+
+        out
+    }
+
+    fn visit_while(&mut self, _keyword: &Token<'static>, condition: &Expr, body: &Stmt) -> String {
+        format!("while ({}) {}", condition.accept(self), body.accept(self))
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt]) -> String {
+        if statements.is_empty() {
+            return "{}".to_string();
+        }
+
+        self.depth += 1;
+        let mut out = String::from("{\n");
+        for statement in statements {
+            out.push_str(&"    ".repeat(self.depth));
+            out.push_str(&statement.accept(self));
+            out.push('\n');
+        }
+        self.depth -= 1;
+
+        out.push_str(&"    ".repeat(self.depth));
+        out.push('}');
+        out
+    }
+
+    fn visit_function(
+        &mut self,
+        name: &Token<'static>,
+        params: &[Token<'static>],
+        body: &[Stmt],
+    ) -> String {
+        let params: Vec<&str> = params.iter().map(|param| param.lexeme.as_ref()).collect();
+        format!(
+            "fun {}({}) {}",
+            name.lexeme,
+            params.join(", "),
+            self.visit_block(body)
+        )
+    }
+
+    fn visit_return(&mut self, _keyword: &Token<'static>, value: Option<&Expr>) -> String {
+        match value {
+            Some(value) => format!("return {};", value.accept(self)),
+            None => "return;".to_string(),
+        }
+    }
+
+    fn visit_break(&mut self, _keyword: &Token<'static>) -> String {
+        "break;".to_string()
+    }
+
+    fn visit_continue(&mut self, _keyword: &Token<'static>) -> String {
+        "continue;".to_string()
+    }
+}
+
+/// Export `program` as a Graphviz DOT digraph, to be rendered with e.g. `dot -Tsvg`.
+///
+/// Each node of the syntax tree becomes a node labelled with its kind, and its lexeme for literals
+/// and names. The statements hang off a `Program` root node. Edges lead from parents to their
+/// children, in source order. Nodes are numbered in pre-order, so the output is stable for a given
+/// program.
+pub fn to_dot(program: &[Stmt]) -> String {
+    let mut writer = DotWriter {
+        out: String::from("digraph ast {\n"),
+        nodes: 0,
+    };
+
+    let root = writer.node("Program");
+    for statement in program {
+        let child = statement.accept(&mut writer);
+        writer.edge(root, child);
+    }
+
+    writer.out.push_str("}\n");
+    writer.out
+}
+
+/// Writes syntax trees as DOT, see `to_dot`. Visiting a node returns its ID.
+struct DotWriter {
+    out: String,
+    // Number of nodes written so far, which is the ID of the next one.
+    nodes: usize,
+}
+
+impl DotWriter {
+    fn node(&mut self, label: &str) -> usize {
+        let id = self.nodes;
+        self.nodes += 1;
+
+        // Within DOT strings, only quotes and backslashes need escaping. Labels contain no line
+        // breaks, as those of string literals are escaped by `quote` already.
+        let label = label.replace('\\', "\\\\").replace('"', "\\\"");
+        self.out
+            .push_str(&format!("    n{} [label=\"{}\"];\n", id, label));
+
+        id
+    }
+
+    fn edge(&mut self, from: usize, to: usize) {
+        self.out.push_str(&format!("    n{} -> n{};\n", from, to));
+    }
+
+    /// Write a node with `children`, which are visited in order.
+    fn parent(&mut self, label: &str, children: &[&dyn Node]) -> usize {
+        let id = self.node(label);
+        for child in children {
+            let child = child.accept_dot(self);
+            self.edge(id, child);
+        }
+
+        id
+    }
+}
+
+/// Expressions and statements alike, so that `DotWriter::parent` can take a mix of both.
+trait Node {
+    fn accept_dot(&self, writer: &mut DotWriter) -> usize;
+}
+
+impl Node for Expr {
+    fn accept_dot(&self, writer: &mut DotWriter) -> usize {
+        self.accept(writer)
+    }
+}
+
+impl Node for Stmt {
+    fn accept_dot(&self, writer: &mut DotWriter) -> usize {
+        self.accept(writer)
+    }
+}
+
+impl ExprVisitor<usize> for DotWriter {
+    fn visit_literal(&mut self, value: &Literal, token: &Token<'static>) -> usize {
+        let text = match (value, token.token_type) {
+            (Literal::Str(s), TokenType::Char) => quote(s, '\''),
+            (Literal::Str(s), _) => quote(s, '"'),
+            _ => token.lexeme.to_string(),
+        };
+        self.node(&format!("Literal {}", text))
+    }
+
+    fn visit_variable(&mut self, name: &Token<'static>) -> usize {
+        self.node(&format!("Variable {}", name.lexeme))
+    }
+
+    fn visit_assign(&mut self, name: &Token<'static>, value: &Expr) -> usize {
+        self.parent(&format!("Assign {}", name.lexeme), &[value])
+    }
+
+    fn visit_grouping(&mut self, expr: &Expr) -> usize {
+        self.parent("Grouping", &[expr])
+    }
+
+    fn visit_unary(&mut self, operator: &Token<'static>, operand: &Expr) -> usize {
+        self.parent(&format!("Unary {}", operator.lexeme), &[operand])
+    }
+
+    fn visit_binary(&mut self, left: &Expr, operator: &Token<'static>, right: &Expr) -> usize {
+        self.parent(&format!("Binary {}", operator.lexeme), &[left, right])
+    }
+
+    fn visit_call(
+        &mut self,
+        callee: &Expr,
+        args: &[Expr],
+        _closing_paren: &Token<'static>,
+    ) -> usize {
+        let mut children: Vec<&dyn Node> = vec![callee];
+        children.extend(args.iter().map(|arg| arg as &dyn Node));
+        self.parent("Call", &children)
+    }
+}
+
+impl StmtVisitor<usize> for DotWriter {
+    fn visit_expression_stmt(&mut self, expr: &Expr) -> usize {
+        self.parent("Expression", &[expr])
+    }
+
+    fn visit_print(&mut self, _keyword: &Token<'static>, expr: &Expr) -> usize {
+        self.parent("Print", &[expr])
+    }
+
+    fn visit_var(&mut self, name: &Token<'static>, initializer: Option<&Expr>) -> usize {
+        let label = format!("Var {}", name.lexeme);
+        match initializer {
+            Some(initializer) => self.parent(&label, &[initializer]),
+            None => self.node(&label),
+        }
+    }
+
+    fn visit_if(
+        &mut self,
+        _keyword: &Token<'static>,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> usize {
+        match else_branch {
+            Some(else_branch) => self.parent("If", &[condition, then_branch, else_branch]),
+            None => self.parent("If", &[condition, then_branch]),
+        }
+    }
+
+    fn visit_while(&mut self, _keyword: &Token<'static>, condition: &Expr, body: &Stmt) -> usize {
+        self.parent("While", &[condition, body])
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt]) -> usize {
+        let children: Vec<&dyn Node> = statements.iter().map(|s| s as &dyn Node).collect();
+        self.parent("Block", &children)
+    }
+
+    fn visit_function(
+        &mut self,
+        name: &Token<'static>,
+        params: &[Token<'static>],
+        body: &[Stmt],
+    ) -> usize {
+        let params: Vec<&str> = params.iter().map(|param| param.lexeme.as_ref()).collect();
+        let children: Vec<&dyn Node> = body.iter().map(|s| s as &dyn Node).collect();
+        self.parent(
+            &format!("Function {}({})", name.lexeme, params.join(", ")),
+            &children,
+        )
+    }
+
+    fn visit_return(&mut self, _keyword: &Token<'static>, value: Option<&Expr>) -> usize {
+        match value {
+            Some(value) => self.parent("Return", &[value]),
+            None => self.node("Return"),
+        }
+    }
+
+    fn visit_break(&mut self, _keyword: &Token<'static>) -> usize {
+        self.node("Break")
+    }
+
+    fn visit_continue(&mut self, _keyword: &Token<'static>) -> usize {
+        self.node("Continue")
+    }
+}
+
+/// `s` enclosed in `quote`s, with escape sequences for the quote, backslashes and control
+/// characters, so that it can't be confused with the surrounding output.
+fn quote(s: &str, quote: char) -> String {
+    let mut out = String::from(quote);
+
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push(quote);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::PROGRAM, lexer::Lexer, parser::Parser};
+
+    fn token(token_type: TokenType, lexeme: &'static str) -> Token<'static> {
+        Token {
+            token_type,
+            lexeme: Cow::Borrowed(lexeme),
+            start: Position::default(),
+            end: Position::default(),
+            span: Span { start: 0, end: 0 },
+            symbol: None,
+            literal: None,
+        }
+    }
+
+    #[test]
+    fn test_drop_deep_trees() {
+        // Would overflow the stack with the default, recursive, drop.
+        const DEPTH: usize = 200_000;
+        let span = Span { start: 0, end: 0 };
+
+        let mut expr = Expr::placeholder();
+        for i in 0..DEPTH {
+            expr = match i % 4 {
+                0 => Expr::Grouping {
+                    expr: Box::new(expr),
+                    span,
+                },
+                1 => Expr::Binary {
+                    left: Box::new(expr),
+                    operator: token(TokenType::Plus, "+"),
+                    right: Box::new(Expr::placeholder()),
+                    span,
+                },
+                2 => Expr::Unary {
+                    operator: token(TokenType::Minus, "-"),
+                    operand: Box::new(expr),
+                    span,
+                },
+                _ => Expr::Call {
+                    callee: Box::new(Expr::placeholder()),
+                    args: vec![Expr::placeholder(), expr],
+                    closing_paren: token(TokenType::ClosingParentheses, ")"),
+                    span,
+                },
+            };
+        }
+
+        let mut stmt = Stmt::Print {
+            keyword: token(TokenType::Print, "print"),
+            expr,
+            span,
+        };
+        for i in 0..DEPTH {
+            stmt = match i % 3 {
+                0 => Stmt::Block {
+                    statements: vec![stmt],
+                    span,
+                },
+                1 => Stmt::If {
+                    keyword: token(TokenType::If, "if"),
+                    condition: Expr::placeholder(),
+                    then_branch: Box::new(Stmt::Break {
+                        keyword: token(TokenType::Break, "break"),
+                        span,
+                    }),
+                    else_branch: Some(Box::new(stmt)),
+                    span,
+                },
+                _ => Stmt::While {
+                    keyword: token(TokenType::While, "while"),
+                    condition: Expr::placeholder(),
+                    body: Box::new(stmt),
+                    span,
+                },
+            };
+        }
+        drop(stmt);
+    }
+
+    #[test]
+    fn test_detach_children() {
+        let mut worklist = Worklist::default();
+        let mut expr = Expr::Binary {
+            left: Box::new(Expr::Grouping {
+                expr: Box::new(Expr::placeholder()),
+                span: Span { start: 0, end: 3 },
+            }),
+            operator: token(TokenType::Plus, "+"),
+            right: Box::new(Expr::placeholder()),
+            span: Span { start: 0, end: 7 },
+        };
+        expr.detach_children(&mut worklist);
+
+        // Only the grouping is detached, the literal stays in place.
+        assert_eq!(worklist.exprs.len(), 1);
+        assert!(worklist.stmts.is_empty());
+        assert!(matches!(
+            &worklist.exprs[0],
+            Expr::Grouping { span, .. } if span.end == 3
+        ));
+        assert_eq!(AstPrinter.print_expr(&expr), "(+ nil nil)");
+    }
+
+    fn print(source: &str) -> String {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        AstPrinter.print_program(&Parser::new(tokens).parse_program().unwrap())
+    }
+
+    #[test]
+    fn test_print_expressions() {
+        let cases = [
+            ("1 + 2 * 3;", "(expr (+ 1 (* 2 3)))"),
+            ("(1 + 2) * 3;", "(expr (* (+ 1 2) 3))"),
+            ("a = b = -c;", "(expr (= a (= b (- c))))"),
+            (
+                "!true or nil and false;",
+                "(expr (or (! true) (and nil false)))",
+            ),
+            ("0x1f + 2.50 + 1_000;", "(expr (+ (+ 31 2.5) 1000))"),
+            ("a  <=\n b;", "(expr (<= a b))"),
+            ("f(a, g())(1);", "(expr (call (call f a (call g)) 1))"),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(print(source), expected, "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_print_unary() {
+        let cases = [
+            ("-5;", "(expr (- 5))", "-5;"),
+            ("!done;", "(expr (! done))", "!done;"),
+            ("-a * b;", "(expr (* (- a) b))", "-a * b;"),
+            ("!a == b;", "(expr (== (! a) b))", "!a == b;"),
+            ("--a;", "(expr (- (- a)))", "--a;"),
+            ("- -1;", "(expr (- (- 1)))", "--1;"),
+            ("-(a + b);", "(expr (- (+ a b)))", "-(a + b);"),
+        ];
+
+        for (source, expected, expected_source) in cases {
+            assert_eq!(print(source), expected, "{:?}", source);
+            assert_eq!(print_source(source), expected_source, "{:?}", source);
+            assert_eq!(print(expected_source), expected, "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_print_statements() {
+        let cases = [
+            ("var x = 1 + 2;", "(var x (+ 1 2))"),
+            ("var x;", "(var x)"),
+            ("print x;", "(print x)"),
+            ("{}", "(block)"),
+            (
+                "{ print 1; { var a; } }",
+                "(block (print 1) (block (var a)))",
+            ),
+            ("if (a) print 1;", "(if a (print 1))"),
+            (
+                "if (a == 1) { print a; } else { }",
+                "(if (== a 1) (block (print a)) (block))",
+            ),
+            (
+                "while (true) x = x + 1;",
+                "(while true (expr (= x (+ x 1))))",
+            ),
+            ("fun f() {}", "(fun f ())"),
+            (
+                "fun f(a, b) { print a; print b; }",
+                "(fun f (a b) (print a) (print b))",
+            ),
+            (
+                "fun f() { return; return nil; }",
+                "(fun f () (return) (return nil))",
+            ),
+            (
+                "while (a) { break; continue; }",
+                "(while a (block (break) (continue)))",
+            ),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(print(source), expected, "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_print_nested_program() {
+        let source = "var i = 0;
+while (i < 10) {
+    if (i % 2 == 0) print \"even\"; else if (i == 5) { print 'f'; }
+    i = i + 1;
+}";
+        assert_eq!(
+            print(source),
+            concat!(
+                "(var i 0)\n",
+                "(while (< i 10) (block ",
+                "(if (== (% i 2) 0) (print \"even\") (if (== i 5) (block (print 'f')))) ",
+                "(expr (= i (+ i 1)))))",
+            )
+        );
+
+        // Stable against changes of whitespace and comments.
+        let reformatted = source.replace('\n', " /* */ ").replace("    ", "\t\t");
+        assert_eq!(print(&reformatted), print(source));
+
+        assert_eq!(print(PROGRAM).lines().count(), 13);
+    }
+
+    #[test]
+    fn test_print_strings() {
+        assert_eq!(
+            print(r#"print "say \"hi\"\n\\";"#),
+            r#"(print "say \"hi\"\n\\")"#
+        );
+        assert_eq!(print(r#"print r"a\tb";"#), r#"(print "a\\tb")"#);
+        assert_eq!(print(r"print '\'';"), r"(print '\'')");
+        assert_eq!(print(r#"print '"';"#), r#"(print '"')"#);
+        assert_eq!(print("print \"\u{7}\";"), r#"(print "\u{7}")"#);
+    }
+
+    fn dot(source: &str) -> String {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        to_dot(&Parser::new(tokens).parse_program().unwrap())
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let dot = dot("var x = (1 + 2) * 3;");
+        assert_eq!(
+            dot,
+            concat!(
+                "digraph ast {\n",
+                "    n0 [label=\"Program\"];\n",
+                "    n1 [label=\"Var x\"];\n",
+                "    n2 [label=\"Binary *\"];\n",
+                "    n3 [label=\"Grouping\"];\n",
+                "    n4 [label=\"Binary +\"];\n",
+                "    n5 [label=\"Literal 1\"];\n",
+                "    n4 -> n5;\n",
+                "    n6 [label=\"Literal 2\"];\n",
+                "    n4 -> n6;\n",
+                "    n3 -> n4;\n",
+                "    n2 -> n3;\n",
+                "    n7 [label=\"Literal 3\"];\n",
+                "    n2 -> n7;\n",
+                "    n1 -> n2;\n",
+                "    n0 -> n1;\n",
+                "}\n",
+            )
+        );
+        assert_eq!(dot.matches(" -> ").count(), 7);
+    }
+
+    #[test]
+    fn test_to_dot_functions() {
+        let dot = dot("fun f(a, b) { g(a); }");
+        assert_eq!(
+            dot,
+            concat!(
+                "digraph ast {\n",
+                "    n0 [label=\"Program\"];\n",
+                "    n1 [label=\"Function f(a, b)\"];\n",
+                "    n2 [label=\"Expression\"];\n",
+                "    n3 [label=\"Call\"];\n",
+                "    n4 [label=\"Variable g\"];\n",
+                "    n3 -> n4;\n",
+                "    n5 [label=\"Variable a\"];\n",
+                "    n3 -> n5;\n",
+                "    n2 -> n3;\n",
+                "    n1 -> n2;\n",
+                "    n0 -> n1;\n",
+                "}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_dot_escaping() {
+        let dot = dot(r#"print "say \"hi\" \\ bye\n"; print '"';"#);
+        assert!(dot.contains(r#"[label="Literal \"say \\\"hi\\\" \\\\ bye\\n\""];"#));
+        assert!(dot.contains(r#"[label="Literal '\"'"];"#));
+    }
+
+    #[test]
+    fn test_to_dot_structure() {
+        let dot = dot(PROGRAM);
+        let lines: Vec<&str> = dot.lines().collect();
+
+        assert_eq!(lines[0], "digraph ast {");
+        assert_eq!(lines[lines.len() - 1], "}");
+        let body = &lines[1..lines.len() - 1];
+        assert!(body.iter().all(|line| !line.contains(['{', '}'])));
+
+        // Every node is declared once, before any edge leads to it, and is the target of exactly
+        // one edge - except for the root.
+        let mut declared = Vec::new();
+        let mut targets = Vec::new();
+        for line in body {
+            let line = line.trim().strip_suffix(';').unwrap();
+            match line.split_once(" -> ") {
+                Some((from, to)) => {
+                    assert!(declared.contains(&from), "{}", line);
+                    assert!(declared.contains(&to), "{}", line);
+                    targets.push(to);
+                }
+                None => {
+                    let (id, label) = line.split_once(' ').unwrap();
+                    assert!(label.starts_with("[label=\"") && label.ends_with("\"]"));
+                    assert!(!declared.contains(&id));
+                    declared.push(id);
+                }
+            }
+        }
+
+        targets.sort();
+        let mut children = declared[1..].to_vec();
+        children.sort();
+        assert_eq!(targets, children);
+    }
+
+    fn print_source(source: &str) -> String {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        SourcePrinter::default().print_program(&Parser::new(tokens).parse_program().unwrap())
+    }
+
+    #[test]
+    fn test_print_source_grouping() {
+        let grouped = print_source("(1 + 2) * 3;");
+        let ungrouped = print_source("1 + 2 * 3;");
+
+        assert_eq!(grouped, "(1 + 2) * 3;");
+        assert_eq!(ungrouped, "1 + 2 * 3;");
+        assert_ne!(grouped, ungrouped);
+
+        // Redundant parentheses are kept as well.
+        assert_eq!(print_source("a = ((b)) * -(1);"), "a = ((b)) * -(1);");
+        assert_eq!(print_source("a = (b = !c);"), "a = (b = !c);");
+    }
+
+    #[test]
+    fn test_print_source_statements() {
+        let source = "var i = 0x10;
+while (i > 0) {
+    if (i % 2 == 0) print \"even\\n\"; else {
+        print 'o';
+    }
+    {}
+    i = i - 1;
+}
+var n;
+fun f(a, b) {
+    fun g() {
+        return;
+    }
+    while (a) {
+        if (b) break; else continue;
+    }
+    return -g()(a, (b));
+}";
+        assert_eq!(print_source(source), source);
+    }
+
+    #[test]
+    fn test_print_source_round_trip() {
+        // Reformatted, the fixture still parses to the same syntax tree.
+        let printed = print_source(PROGRAM);
+        assert_eq!(print_source(&printed), printed);
+        assert_eq!(print(&printed), print(PROGRAM));
+        assert!(printed.contains("var average = (min + max) / 2;"));
+    }
+}
@@ -0,0 +1,51 @@
+use std::{
+    fs,
+    io::{self, Read},
+    process,
+};
+
+use spl::prelude::*;
+
+const USAGE: &str = "Usage: interpreter [FILE]";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+fn main() -> io::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let path = args.next();
+    if args.next().is_some() || path.as_ref().is_some_and(|path| path.starts_with('-')) {
+        usage();
+    }
+
+    // Read from stdin if no file was given.
+    let source = match &path {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut source = String::new();
+            io::stdin().lock().read_to_string(&mut source)?;
+            source
+        }
+    };
+
+    // Errors are rendered here, so exit directly rather than returning them
+    // from `main`, which would print them a second time.
+    let program = match Parser::from_stream(Lexer::new(&source)).parse_program() {
+        Ok(program) => program,
+        Err(errors) => {
+            for e in &errors {
+                eprint!("{}", e.render(&source));
+            }
+            process::exit(1);
+        }
+    };
+
+    if let Err(e) = Interpreter::new().interpret(&program) {
+        eprint!("{}", e.render(&source));
+        process::exit(1);
+    }
+
+    Ok(())
+}
@@ -1,20 +1,93 @@
-use spl::lexer::Lexer;
+use std::{
+    fs,
+    io::{self, Read},
+    process,
+};
 
-fn main() {
-    let mut lexer = Lexer::new("Test");
+use spl::{diagnostics, prelude::*, token::format_token_table};
 
-    match lexer.tokenize() {
+const USAGE: &str = "Usage: lexer [--table] [--format text|json] [FILE]";
+
+/// How diagnostics are reported.
+#[derive(PartialEq, Eq)]
+enum Format {
+    /// Rendered alongside the source, for humans.
+    Text,
+    /// As a JSON array, for tooling. Tokens are not printed.
+    Json,
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+fn main() -> Result<(), SplError> {
+    let mut table = false;
+    let mut format = Format::Text;
+    let mut path = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--table" => table = true,
+            "--format" => {
+                format = match args.next().as_deref() {
+                    Some("text") => Format::Text,
+                    Some("json") => Format::Json,
+                    _ => usage(),
+                }
+            }
+            _ if arg.starts_with('-') || path.is_some() => usage(),
+            _ => path = Some(arg),
+        }
+    }
+
+    // Read from stdin if no file was given. The source is kept around to render errors with.
+    let source = match &path {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut source = String::new();
+            io::stdin().lock().read_to_string(&mut source)?;
+            source
+        }
+    };
+
+    let mut lexer = Lexer::new(&source);
+    let result = lexer.tokenize();
+
+    if format == Format::Json {
+        let mut diagnostics = lexer.warnings().to_vec();
+        if let Err(errors) = &result {
+            diagnostics.extend(errors.iter().map(Diagnostic::from));
+        }
+        println!("{}", diagnostics::to_json(&source, &diagnostics));
+
+        return result.map(|_| ()).map_err(SplError::from);
+    }
+
+    for warning in lexer.warnings() {
+        println!("{}", warning.render(&source));
+    }
+
+    match result {
         Ok(tokens) => {
             println!("Tokenization successful. Tokens:");
-            for token in tokens {
-                println!("{}", token);
+            if table {
+                print!("{}", format_token_table(&tokens));
+            } else {
+                for token in tokens {
+                    println!("{}", token);
+                }
             }
+            Ok(())
         }
         Err(errors) => {
             eprintln!("Tokenization failed. Tokenization errors:");
-            for e in errors {
-                println!("{}", e);
+            for e in &errors {
+                println!("{}", e.render(&source));
             }
+            Err(errors.into())
         }
     }
 }
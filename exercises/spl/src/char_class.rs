@@ -0,0 +1,128 @@
+//! Character classes of the SPL lexical grammar.
+//!
+//! These are the single source of truth for what the lexer considers part of an identifier, a
+//! digit, or whitespace, so that tooling built on top of the lexer classifies characters the
+//! same way.
+//!
+//! ```
+//! use spl::char_class::{digit_value, is_ident_continue, is_ident_start};
+//!
+//! assert!(is_ident_start('_') && !is_ident_start('1'));
+//! assert!(is_ident_continue('1'));
+//! assert_eq!(digit_value('f', 16), Some(15));
+//! ```
+
+/// Whether `c` may start an identifier (or keyword).
+///
+/// Any Unicode letter, as well as the underscore, qualifies. Digits and combining marks do not.
+pub fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+/// Whether `c` may continue an identifier (or keyword) after its first character.
+///
+/// Any Unicode letter or number, as well as the underscore, qualifies. Combining marks do not.
+pub fn is_ident_continue(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether `c` is whitespace which the lexer skips between tokens.
+///
+/// Only spaces, tabs, and line breaks qualify. Other Unicode whitespace, such as a non-breaking
+/// space, is an unexpected character.
+pub fn is_spl_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t') || is_line_break(c)
+}
+
+/// Whether `c` is, or starts, a line break.
+///
+/// Line breaks are either `\n`, `\r\n`, or a lone `\r`.
+pub fn is_line_break(c: char) -> bool {
+    matches!(c, '\n' | '\r')
+}
+
+/// Value of `c` as a digit in the given radix, if it is one.
+///
+/// Only ASCII digits and letters are digits. Returns None for radixes outside of 2 to 36, rather
+/// than panicking like `char::to_digit`.
+pub fn digit_value(c: char, radix: u32) -> Option<u32> {
+    if !(2..=36).contains(&radix) {
+        return None;
+    }
+
+    c.to_digit(radix)
+}
+
+/// Whether `c` is a decimal digit.
+pub fn is_decimal_digit(c: char) -> bool {
+    digit_value(c, 10).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Non-breaking space
+    const NBSP: char = '\u{a0}';
+    // Combining acute accent
+    const COMBINING_ACUTE: char = '\u{301}';
+
+    #[test]
+    fn test_is_ident_start() {
+        for c in ['a', 'Z', 'ä', 'ß', 'λ', 'ж', '_'] {
+            assert!(is_ident_start(c), "{:?}", c);
+        }
+
+        for c in ['0', '9', '٣', ' ', NBSP, COMBINING_ACUTE, '"', '-'] {
+            assert!(!is_ident_start(c), "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn test_is_ident_continue() {
+        for c in ['a', 'Z', 'ä', 'λ', '0', '9', '٣', '_'] {
+            assert!(is_ident_continue(c), "{:?}", c);
+        }
+
+        for c in [' ', NBSP, COMBINING_ACUTE, '.', '-'] {
+            assert!(!is_ident_continue(c), "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn test_is_spl_whitespace() {
+        for c in [' ', '\t', '\n', '\r'] {
+            assert!(is_spl_whitespace(c), "{:?}", c);
+        }
+
+        for c in [NBSP, '\u{2003}', '\u{b}', '\u{c}', 'a', '_'] {
+            assert!(!is_spl_whitespace(c), "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn test_digit_value() {
+        assert_eq!(digit_value('0', 10), Some(0));
+        assert_eq!(digit_value('9', 10), Some(9));
+        assert_eq!(digit_value('a', 10), None);
+
+        assert_eq!(digit_value('1', 2), Some(1));
+        assert_eq!(digit_value('2', 2), None);
+
+        assert_eq!(digit_value('f', 16), Some(15));
+        assert_eq!(digit_value('F', 16), Some(15));
+        assert_eq!(digit_value('g', 16), None);
+
+        // Non-ASCII digits are no digits.
+        assert_eq!(digit_value('٣', 10), None);
+        assert_eq!(digit_value('_', 10), None);
+
+        // Invalid radixes
+        assert_eq!(digit_value('0', 0), None);
+        assert_eq!(digit_value('0', 1), None);
+        assert_eq!(digit_value('0', 37), None);
+
+        assert!(is_decimal_digit('7'));
+        assert!(!is_decimal_digit('x'));
+    }
+}
@@ -0,0 +1,285 @@
+//! Rendering of diagnostics alongside the source they refer to, similar to the output of rustc:
+//!
+//! ```text
+//! error: Unexpected char `@` (unicode \u{40}) found at line 1, column 9
+//!   |
+//! 1 | var a = @;
+//!   |         ^
+//! ```
+//!
+//! For tooling, they can be serialized to JSON instead, see `to_json`.
+
+use crate::{
+    error::Diagnostic,
+    json::push_string,
+    position::{Position, Span},
+};
+
+/// Number of columns a tab is expanded to, so that carets line up with the source line no matter
+/// how wide the terminal displays tabs.
+const TAB_WIDTH: usize = 4;
+
+/// Render `diagnostic`, followed by the source line which its span starts on, with the columns of
+/// the span underlined by carets.
+///
+/// Spans continuing past the end of their first line, such as those of unterminated strings, are
+/// underlined up to the end of that line. Empty spans, such as the end of the input, are marked by
+/// a single caret.
+pub fn render(source: &str, diagnostic: &Diagnostic) -> String {
+    let span = diagnostic.span;
+    let start = span.start.min(source.len());
+    let (line_number, line_start) = line_of(source, start);
+    let line_end = source[line_start..]
+        .find(['\n', '\r'])
+        .map_or(source.len(), |end| line_start + end);
+    let end = span.end.clamp(start, line_end);
+
+    let indent = display_width(&source[line_start..start]);
+    let carets = display_width(&source[start..end]).max(1);
+
+    let gutter = " ".repeat(line_number.to_string().len());
+    format!(
+        "{}\n{} |\n{} | {}\n{} | {}{}\n",
+        diagnostic,
+        gutter,
+        line_number,
+        expand_tabs(&source[line_start..line_end]),
+        gutter,
+        " ".repeat(indent),
+        "^".repeat(carets),
+    )
+}
+
+/// Serialize `diagnostics` to a JSON array of objects, such as
+///
+/// ```text
+/// [{"severity":"error","code":"L0001","message":"Unexpected char ...","line":1,"column":9,
+/// "end_line":1,"end_column":9}]
+/// ```
+///
+/// where `line` and `column` are the position of the first character the diagnostic applies to,
+/// and `end_line` and `end_column` the one of the last. `source` must be the input the
+/// diagnostics were reported for.
+pub fn to_json(source: &str, diagnostics: &[Diagnostic]) -> String {
+    let mut out = String::from("[");
+
+    for (i, diagnostic) in diagnostics.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+
+        let end = end_position(source, diagnostic.span).unwrap_or(diagnostic.position);
+
+        out.push_str("{\"severity\":");
+        push_string(&mut out, &diagnostic.severity.to_string());
+        out.push_str(",\"code\":");
+        push_string(&mut out, diagnostic.code);
+        out.push_str(",\"message\":");
+        push_string(&mut out, &diagnostic.message);
+        out.push_str(&format!(
+            ",\"line\":{},\"column\":{},\"end_line\":{},\"end_column\":{}}}",
+            diagnostic.position.line, diagnostic.position.column, end.line, end.column
+        ));
+    }
+
+    out.push(']');
+    out
+}
+
+/// Position of the last character of `span`, in the same terms as the lexer's. None for empty
+/// spans.
+fn end_position(source: &str, span: Span) -> Option<Position> {
+    let end = span.end.min(source.len());
+    let (mut last, _) = source.get(..end)?.char_indices().next_back()?;
+    if last < span.start {
+        return None;
+    }
+
+    // The `\n` of a `\r\n` is part of the same line break as the `\r`.
+    if source[..last].ends_with('\r') && source[last..].starts_with('\n') {
+        last -= 1;
+    }
+
+    let (line, line_start) = line_of(source, last);
+    Some(Position {
+        line,
+        column: source[line_start..last].chars().count() + 1,
+    })
+}
+
+/// 1-based number and byte offset of the start of the line containing `offset`.
+fn line_of(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line_start = before.rfind(['\n', '\r']).map_or(0, |i| i + 1);
+
+    // A `\r\n` is a single line break, so only its `\n` is counted.
+    let line_breaks = before[..line_start]
+        .char_indices()
+        .filter(|&(i, c)| c == '\n' || (c == '\r' && !before[i + 1..].starts_with('\n')))
+        .count();
+
+    (line_breaks + 1, line_start)
+}
+
+/// Number of columns `text` takes up once its tabs are expanded.
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::TRAILING_DOT_WARNING, lexer::Lexer};
+
+    fn render_errors(source: &str) -> Vec<String> {
+        Lexer::new(source)
+            .tokenize()
+            .unwrap_err()
+            .iter()
+            .map(|e| e.render(source))
+            .collect()
+    }
+
+    #[test]
+    fn test_unexpected_char() {
+        let source = "var a = 1;\nvar b = a @ 2;\nprint b;\n";
+        assert_eq!(
+            render_errors(source),
+            vec![concat!(
+                "error: Unexpected char `@` (unicode \\u{40}) found at line 2, column 11\n",
+                "  |\n",
+                "2 | var b = a @ 2;\n",
+                "  |           ^\n",
+            )]
+        );
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        // Spans to the end of the input, across lines. Only the first line is underlined.
+        let source = "print \"abc;\nprint 1;";
+        assert_eq!(
+            render_errors(source),
+            vec![concat!(
+                "error: Unterminted string sequence found, starting at line 1, column 7, ending at line 2, column 8\n",
+                "  |\n",
+                "1 | print \"abc;\n",
+                "  |       ^^^^^\n",
+            )]
+        );
+    }
+
+    #[test]
+    fn test_tabs() {
+        let source = "{\n\tvar\ta = $;\n}";
+        assert_eq!(
+            render_errors(source),
+            vec![concat!(
+                "error: Unexpected char `$` (unicode \\u{24}) found at line 2, column 10\n",
+                "  |\n",
+                "2 |     var    a = $;\n",
+                "  |                ^\n",
+            )]
+        );
+    }
+
+    #[test]
+    fn test_last_line() {
+        // Without a trailing line break, and with `\r\n` line breaks before it.
+        let source = "var a;\r\nvar b;\r\na = 0x;";
+        assert_eq!(
+            render_errors(source),
+            vec![concat!(
+                "error: Malformed number `0x` found at line 3, column 5\n",
+                "  |\n",
+                "3 | a = 0x;\n",
+                "  |     ^^\n",
+            )]
+        );
+    }
+
+    #[test]
+    fn test_empty_span() {
+        let source = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let span = Span {
+            start: source.len(),
+            end: source.len(),
+        };
+        let diagnostic = Diagnostic::warning(
+            "W9999",
+            "Unexpected end of input".to_string(),
+            Position {
+                line: 11,
+                column: 1,
+            },
+            span,
+        );
+        assert_eq!(
+            render(source, &diagnostic),
+            "warning: Unexpected end of input\n   |\n11 | \n   | ^\n"
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        // One error of each kind.
+        let cases = [
+            ("a @", "L0001", "Unexpected char `@` (unicode \\\\u{40}) found at line 1, column 3", (1, 3, 1, 3)),
+            ("a\n\"bc\nd", "L0002", "Unterminted string sequence found, starting at line 2, column 1, ending at line 3, column 1", (2, 1, 3, 1)),
+            ("\"\\q\"", "L0003", "Unknown escape sequence `\\\\q` found at line 1, column 2", (1, 2, 1, 3)),
+            ("/* a\r\n", "L0004", "Unterminated block comment found, starting at line 1, column 1", (1, 1, 1, 5)),
+            ("@$", "L0005", "Unexpected characters `@$` found at line 1, column 1", (1, 1, 1, 2)),
+            ("0x", "L0006", "Malformed number `0x` found at line 1, column 1", (1, 1, 1, 2)),
+            ("class", "L0007", "Reserved word `class` found at line 1, column 1. It is reserved for future use, and can't be used as an identifier", (1, 1, 1, 5)),
+            ("'ab'", "L0008", "Malformed char literal `'ab'` found at line 1, column 1. It must contain exactly one character", (1, 1, 1, 4)),
+            ("1e999", "L0009", "Number `1e999` found at line 1, column 1 is out of range", (1, 1, 1, 5)),
+        ];
+
+        for (source, code, message, (line, column, end_line, end_column)) in cases {
+            let errors = Lexer::new(source).tokenize().unwrap_err();
+            assert_eq!(errors.len(), 1, "{:?}", source);
+            assert_eq!(errors[0].code(), code);
+
+            let diagnostic = Diagnostic::from(&errors[0]);
+            assert_eq!(
+                diagnostic.to_json(source),
+                format!(
+                    r#"[{{"severity":"error","code":"{}","message":"{}","line":{},"column":{},"end_line":{},"end_column":{}}}]"#,
+                    code, message, line, column, end_line, end_column
+                ),
+                "{:?}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_json_warnings() {
+        let source = "x = §.;\ny = 2.;";
+        let mut lexer = Lexer::new(source);
+        let errors = lexer.tokenize().unwrap_err();
+        let mut diagnostics: Vec<Diagnostic> = lexer.warnings().to_vec();
+        diagnostics.extend(errors.iter().map(Diagnostic::from));
+
+        assert_eq!(diagnostics[0].code, TRAILING_DOT_WARNING);
+        assert_eq!(
+            to_json(source, &diagnostics),
+            concat!(
+                r#"[{"severity":"warning","code":"W0001","#,
+                r#""message":"Number `2` is followed by a trailing dot, which is not part of it","#,
+                r#""line":2,"column":5,"end_line":2,"end_column":6},"#,
+                r#"{"severity":"error","code":"L0001","#,
+                r#""message":"Unexpected char `§` (unicode \\u{a7}) found at line 1, column 5","#,
+                r#""line":1,"column":5,"end_line":1,"end_column":5}]"#,
+            )
+        );
+
+        assert_eq!(to_json(source, &[]), "[]");
+    }
+}
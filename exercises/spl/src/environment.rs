@@ -0,0 +1,141 @@
+//! Storage of the variables of a running program.
+
+use std::collections::HashMap;
+
+use crate::{error::RuntimeError, interpreter::Value, token::Token};
+
+/// Variables of a program, in nested scopes.
+///
+/// The outermost scope holds the global variables, and each block being executed adds a scope of
+/// its own on top. Variables are looked up from the innermost scope outwards, so that a variable
+/// declared within a block shadows any of the same name outside of it.
+#[derive(Debug, Clone)]
+pub struct Environment {
+    // Scopes from the outermost to the innermost one. Never empty.
+    scopes: Vec<HashMap<String, Value>>,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment {
+            scopes: vec![HashMap::new()],
+        }
+    }
+}
+
+impl Environment {
+    /// Create an environment with only the global scope, which is empty.
+    pub fn new() -> Environment {
+        Environment::default()
+    }
+
+    /// Add an inner scope, which variables are declared in until it is removed again.
+    pub fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    /// Remove the innermost scope, along with all variables declared in it. The global scope is
+    /// never removed.
+    pub fn pop_scope(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+
+    /// Declare a variable in the innermost scope. If it was declared in that scope already, it is
+    /// redeclared, replacing its value.
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.scopes
+            .last_mut()
+            .expect("the global scope is never removed")
+            .insert(name.to_string(), value);
+    }
+
+    /// Value of the variable `name` refers to.
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name.lexeme.as_ref()))
+            .cloned()
+            .ok_or_else(|| undefined(name))
+    }
+
+    /// Assign `value` to the variable `name` refers to, which must have been declared.
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        let variable = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name.lexeme.as_ref()))
+            .ok_or_else(|| undefined(name))?;
+        *variable = value;
+
+        Ok(())
+    }
+}
+
+fn undefined(name: &Token) -> RuntimeError {
+    RuntimeError::UndefinedVariable {
+        name: name.lexeme.to_string(),
+        position: name.start,
+        span: name.span,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lexer::Lexer,
+        position::{Position, Span},
+    };
+
+    fn name(name: &str) -> Token<'_> {
+        Lexer::new(name).tokenize().unwrap().remove(0)
+    }
+
+    #[test]
+    fn test_scopes() {
+        let mut environment = Environment::new();
+        environment.define("a", Value::Number(1.0));
+        environment.define("b", Value::Number(2.0));
+
+        environment.push_scope();
+        environment.define("a", Value::Str("inner".to_string()));
+        environment.assign(&name("b"), Value::Nil).unwrap();
+        environment.define("c", Value::Bool(true));
+        assert_eq!(
+            environment.get(&name("a")),
+            Ok(Value::Str("inner".to_string()))
+        );
+        assert_eq!(environment.get(&name("c")), Ok(Value::Bool(true)));
+
+        // The shadowed variable reappears, while assignments to outer ones persist.
+        environment.pop_scope();
+        assert_eq!(environment.get(&name("a")), Ok(Value::Number(1.0)));
+        assert_eq!(environment.get(&name("b")), Ok(Value::Nil));
+        assert_eq!(
+            environment.get(&name("c")),
+            Err(RuntimeError::UndefinedVariable {
+                name: "c".to_string(),
+                position: Position { line: 1, column: 1 },
+                span: Span { start: 0, end: 1 },
+            })
+        );
+
+        // The global scope stays.
+        environment.pop_scope();
+        assert_eq!(environment.get(&name("a")), Ok(Value::Number(1.0)));
+    }
+
+    #[test]
+    fn test_assign_undefined() {
+        let mut environment = Environment::new();
+        assert!(matches!(
+            environment.assign(&name("a"), Value::Nil),
+            Err(RuntimeError::UndefinedVariable { name, .. }) if name == "a"
+        ));
+        assert!(environment.get(&name("a")).is_err());
+    }
+}
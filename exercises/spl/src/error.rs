@@ -1,42 +1,260 @@
-use std::fmt::Display;
+use std::{error::Error, fmt::Display, io, sync::Arc};
 
-/// Position within an input file
-#[derive(Debug, PartialEq, Eq)]
-pub struct Position {
-    pub line: usize,
-    pub column: usize,
+use crate::{
+    diagnostics,
+    token::{Token, TokenType},
+};
+// Positions used to live here, and are re-exported for code which still imports them from here.
+pub use crate::position::{Position, Span};
+
+/// How severe a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The input is invalid, and can't be processed further.
+    Error,
+    /// The input is valid, but likely not what was intended.
+    Warning,
 }
 
-impl Display for Position {
+impl Display for Severity {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "line {}, column {}", self.line, self.column)
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Code of the warning about a number followed by a trailing dot, such as `1.`.
+pub const TRAILING_DOT_WARNING: &str = "W0001";
+
+/// A message about the input, of any severity, attached to where in the input it applies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable, machine-readable code of the kind of diagnostic, see `LexerError::code`. Warnings
+    /// have codes starting with `W`.
+    pub code: &'static str,
+    pub message: String,
+    pub position: Position,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn warning(
+        code: &'static str,
+        message: String,
+        position: Position,
+        span: Span,
+    ) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            code,
+            message,
+            position,
+            span,
+        }
+    }
+
+    /// Render the diagnostic alongside the line of `source` it applies to, see
+    /// `diagnostics::render`.
+    ///
+    /// `source` must be the input the diagnostic was reported for.
+    pub fn render(&self, source: &str) -> String {
+        diagnostics::render(source, self)
+    }
+
+    /// Serialize the diagnostic to a JSON object, see `diagnostics::to_json`.
+    pub fn to_json(&self, source: &str) -> String {
+        diagnostics::to_json(source, std::slice::from_ref(self))
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+impl From<&LexerError> for Diagnostic {
+    fn from(e: &LexerError) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code: e.code(),
+            message: e.to_string(),
+            position: e.position(),
+            span: e.span(),
+        }
     }
 }
 
 /// Errors returned by Lexer
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum LexerError {
     /// Returned when the lexer encounterd an unterminated string sequence.
     UnterminatedStringSequence {
+        /// Position of the opening quote.
         starts_at: Position,
+        /// Position of the last character of the input, where the lexer gave up.
         ends_at: Position,
+        span: Span,
+    },
+
+    /// Returned when the lexer encountered an unknown escape sequence within a string. The
+    /// position is the one of the backslash.
+    UnknownEscapeSequence {
+        position: Position,
+        c: char,
+        span: Span,
     },
 
+    /// Returned when the lexer encountered a char literal which does not contain exactly one
+    /// character, or is not closed on the same line. The position is the one of the opening
+    /// quote.
+    MalformedCharLiteral {
+        position: Position,
+        text: String,
+        span: Span,
+    },
+
+    /// Returned when the lexer encountered an unterminated block comment.
+    UnterminatedBlockComment { starts_at: Position, span: Span },
+
     /// Returned when the lexer encountered an unexpected character.
-    UnexpectedChar { position: Position, c: char },
+    UnexpectedChar {
+        position: Position,
+        c: char,
+        span: Span,
+    },
+
+    /// Returned when the lexer encountered a run of multiple unexpected characters, which are
+    /// reported at once rather than one by one.
+    UnexpectedChars {
+        position: Position,
+        text: String,
+        span: Span,
+    },
+
+    /// Returned when the lexer encountered a malformed number literal, such as a hexadecimal
+    /// prefix without any digits.
+    MalformedNumber {
+        position: Position,
+        lexeme: String,
+        span: Span,
+    },
+
+    /// Returned when the lexer encountered a number literal which is too large to be represented.
+    NumberOutOfRange {
+        position: Position,
+        lexeme: String,
+        span: Span,
+    },
+
+    /// Returned as the last error once the lexer stopped scanning after too many errors, see
+    /// `LexerOptions::max_errors`. The position and span are where it stopped.
+    TooManyErrors {
+        reported: usize,
+        position: Position,
+        span: Span,
+    },
+
+    /// Returned when the lexer encountered a word which is reserved for future use.
+    ReservedWord {
+        position: Position,
+        word: String,
+        span: Span,
+    },
+}
+
+impl LexerError {
+    /// Byte offsets of the input the error refers to.
+    pub fn span(&self) -> Span {
+        match self {
+            LexerError::UnterminatedStringSequence { span, .. }
+            | LexerError::UnknownEscapeSequence { span, .. }
+            | LexerError::MalformedCharLiteral { span, .. }
+            | LexerError::UnterminatedBlockComment { span, .. }
+            | LexerError::UnexpectedChar { span, .. }
+            | LexerError::UnexpectedChars { span, .. }
+            | LexerError::MalformedNumber { span, .. }
+            | LexerError::NumberOutOfRange { span, .. }
+            | LexerError::TooManyErrors { span, .. }
+            | LexerError::ReservedWord { span, .. } => *span,
+        }
+    }
+
+    /// Stable, machine-readable code of the kind of error, for tooling to match on.
+    ///
+    /// Codes of existing kinds never change, new kinds get new codes.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexerError::UnexpectedChar { .. } => "L0001",
+            LexerError::UnterminatedStringSequence { .. } => "L0002",
+            LexerError::UnknownEscapeSequence { .. } => "L0003",
+            LexerError::UnterminatedBlockComment { .. } => "L0004",
+            LexerError::UnexpectedChars { .. } => "L0005",
+            LexerError::MalformedNumber { .. } => "L0006",
+            LexerError::ReservedWord { .. } => "L0007",
+            LexerError::MalformedCharLiteral { .. } => "L0008",
+            LexerError::NumberOutOfRange { .. } => "L0009",
+            LexerError::TooManyErrors { .. } => "L0010",
+        }
+    }
+
+    /// Position of the start of the input the error refers to.
+    pub fn position(&self) -> Position {
+        match self {
+            LexerError::UnterminatedStringSequence { starts_at, .. }
+            | LexerError::UnterminatedBlockComment { starts_at, .. } => *starts_at,
+            LexerError::UnknownEscapeSequence { position, .. }
+            | LexerError::MalformedCharLiteral { position, .. }
+            | LexerError::UnexpectedChar { position, .. }
+            | LexerError::UnexpectedChars { position, .. }
+            | LexerError::MalformedNumber { position, .. }
+            | LexerError::NumberOutOfRange { position, .. }
+            | LexerError::TooManyErrors { position, .. }
+            | LexerError::ReservedWord { position, .. } => *position,
+        }
+    }
+
+    /// Render the error alongside the line of `source` it occurred on, see `diagnostics::render`.
+    ///
+    /// `source` must be the input the error was returned for.
+    pub fn render(&self, source: &str) -> String {
+        Diagnostic::from(self).render(source)
+    }
 }
 
 impl Display for LexerError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            LexerError::UnterminatedStringSequence { starts_at, ends_at } => {
+            LexerError::UnterminatedStringSequence {
+                starts_at, ends_at, ..
+            } => {
                 write!(
                     f,
                     "Unterminted string sequence found, starting at {}, ending at {}",
                     starts_at, ends_at,
                 )
             }
-            LexerError::UnexpectedChar { position, c } => {
+            LexerError::UnknownEscapeSequence { position, c, .. } => {
+                write!(f, "Unknown escape sequence `\\{}` found at {}", c, position)
+            }
+            LexerError::MalformedCharLiteral { position, text, .. } => {
+                write!(
+                    f,
+                    "Malformed char literal `{}` found at {}. It must contain exactly one character",
+                    text, position
+                )
+            }
+            LexerError::UnterminatedBlockComment { starts_at, .. } => {
+                write!(
+                    f,
+                    "Unterminated block comment found, starting at {}",
+                    starts_at
+                )
+            }
+            LexerError::UnexpectedChar { position, c, .. } => {
                 write!(
                     f,
                     "Unexpected char `{}` (unicode {}) found at {}",
@@ -45,6 +263,648 @@ impl Display for LexerError {
                     position
                 )
             }
+            LexerError::UnexpectedChars { position, text, .. } => {
+                write!(f, "Unexpected characters `{}` found at {}", text, position)
+            }
+            LexerError::MalformedNumber {
+                position, lexeme, ..
+            } => {
+                write!(f, "Malformed number `{}` found at {}", lexeme, position)
+            }
+            LexerError::NumberOutOfRange {
+                position, lexeme, ..
+            } => {
+                write!(
+                    f,
+                    "Number `{}` found at {} is out of range",
+                    lexeme, position
+                )
+            }
+            LexerError::TooManyErrors {
+                reported, position, ..
+            } => {
+                write!(
+                    f,
+                    "Too many errors, stopped lexing at {} after {} errors",
+                    position, reported
+                )
+            }
+            LexerError::ReservedWord { position, word, .. } => {
+                write!(
+                    f,
+                    "Reserved word `{}` found at {}. It is reserved for future use, and can't be used as an identifier",
+                    word, position
+                )
+            }
+        }
+    }
+}
+
+impl Error for LexerError {}
+
+/// Errors returned by Parser
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParserError {
+    /// Returned when the parser encountered a token which doesn't fit the grammar.
+    UnexpectedToken {
+        /// Types of all tokens which would have fit instead.
+        expected: Vec<TokenType>,
+        // Boxed, as tokens are large compared to the other variants.
+        found: Box<Token<'static>>,
+    },
+
+    /// Returned when the input ended while the parser still expected more. The position is the
+    /// one of the end of the input.
+    UnexpectedEndOfInput {
+        expected: Vec<TokenType>,
+        position: Position,
+        span: Span,
+    },
+
+    /// Returned when the parenthesis around a condition isn't closed. Refers to the opening one.
+    UnclosedDelimiter { opening: Box<Token<'static>> },
+
+    /// Returned when the left-hand side of an assignment is not a variable. The position is the
+    /// one of the `=`.
+    InvalidAssignmentTarget { position: Position, span: Span },
+
+    /// Returned when expressions or statements are nested deeper than the parser's limit. The
+    /// position is the one of the construct exceeding it.
+    NestingTooDeep {
+        position: Position,
+        limit: usize,
+        span: Span,
+    },
+
+    /// Returned when a function declares more parameters than the limit. The position is the one
+    /// of the first parameter exceeding it.
+    TooManyParameters {
+        position: Position,
+        limit: usize,
+        span: Span,
+    },
+
+    /// Returned when a call passes more arguments than the limit. The position is the one of the
+    /// first argument exceeding it.
+    TooManyArguments {
+        position: Position,
+        limit: usize,
+        span: Span,
+    },
+
+    /// Returned when a `return` is not within a function. The position is the one of the keyword.
+    ReturnOutsideFunction { position: Position, span: Span },
+
+    /// Returned when a `break` is not within a loop. The position is the one of the keyword.
+    BreakOutsideLoop { position: Position, span: Span },
+
+    /// Returned when a `continue` is not within a loop. The position is the one of the keyword.
+    ContinueOutsideLoop { position: Position, span: Span },
+
+    /// Returned when the lexer producing the tokens failed, if parsing from a stream of tokens.
+    Lexer(LexerError),
+}
+
+impl ParserError {
+    /// Position of the input the error refers to.
+    pub fn position(&self) -> Position {
+        match self {
+            ParserError::UnexpectedToken { found, .. } => found.start,
+            ParserError::UnclosedDelimiter { opening } => opening.start,
+            ParserError::UnexpectedEndOfInput { position, .. }
+            | ParserError::InvalidAssignmentTarget { position, .. }
+            | ParserError::ReturnOutsideFunction { position, .. }
+            | ParserError::BreakOutsideLoop { position, .. }
+            | ParserError::ContinueOutsideLoop { position, .. }
+            | ParserError::NestingTooDeep { position, .. }
+            | ParserError::TooManyParameters { position, .. }
+            | ParserError::TooManyArguments { position, .. } => *position,
+            ParserError::Lexer(e) => e.position(),
+        }
+    }
+
+    /// Byte offsets of the input the error refers to.
+    pub fn span(&self) -> Span {
+        match self {
+            ParserError::UnexpectedToken { found, .. } => found.span,
+            ParserError::UnclosedDelimiter { opening } => opening.span,
+            ParserError::UnexpectedEndOfInput { span, .. }
+            | ParserError::InvalidAssignmentTarget { span, .. }
+            | ParserError::ReturnOutsideFunction { span, .. }
+            | ParserError::BreakOutsideLoop { span, .. }
+            | ParserError::ContinueOutsideLoop { span, .. }
+            | ParserError::NestingTooDeep { span, .. }
+            | ParserError::TooManyParameters { span, .. }
+            | ParserError::TooManyArguments { span, .. } => *span,
+            ParserError::Lexer(e) => e.span(),
+        }
+    }
+
+    /// Stable, machine-readable code of the kind of error, like `LexerError::code`. Errors of the
+    /// lexer keep their own codes.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParserError::UnexpectedToken { .. } => "P0001",
+            ParserError::UnexpectedEndOfInput { .. } => "P0002",
+            ParserError::UnclosedDelimiter { .. } => "P0003",
+            ParserError::InvalidAssignmentTarget { .. } => "P0004",
+            ParserError::NestingTooDeep { .. } => "P0005",
+            ParserError::TooManyParameters { .. } => "P0006",
+            ParserError::TooManyArguments { .. } => "P0007",
+            ParserError::ReturnOutsideFunction { .. } => "P0008",
+            ParserError::BreakOutsideLoop { .. } => "P0009",
+            ParserError::ContinueOutsideLoop { .. } => "P0010",
+            ParserError::Lexer(e) => e.code(),
+        }
+    }
+
+    /// Render the error alongside the line of `source` it refers to, see `diagnostics::render`.
+    pub fn render(&self, source: &str) -> String {
+        Diagnostic::from(self).render(source)
+    }
+}
+
+impl From<&ParserError> for Diagnostic {
+    fn from(e: &ParserError) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code: e.code(),
+            message: e.to_string(),
+            position: e.position(),
+            span: e.span(),
+        }
+    }
+}
+
+/// List of alternatives, such as `';', ')' or identifier`.
+fn describe_expected(expected: &[TokenType]) -> String {
+    let descriptions: Vec<String> = expected.iter().map(TokenType::describe).collect();
+
+    match descriptions.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+        None => "nothing".to_string(),
+    }
+}
+
+/// Description of a token which was found, such as `identifier 'foo'` or `';'`.
+fn describe_found(token: &Token) -> String {
+    if token.token_type.as_keyword_str().is_some() {
+        format!("keyword '{}'", token.lexeme)
+    } else if token.token_type.as_fixed_str().is_some() {
+        format!("'{}'", token.lexeme)
+    } else {
+        format!("{} '{}'", token.token_type.describe(), token.lexeme)
+    }
+}
+
+impl Display for ParserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParserError::UnexpectedToken { expected, found } => {
+                write!(
+                    f,
+                    "expected {} but found {} at {}",
+                    describe_expected(expected),
+                    describe_found(found),
+                    found.start
+                )
+            }
+            ParserError::UnexpectedEndOfInput {
+                expected, position, ..
+            } => {
+                write!(
+                    f,
+                    "expected {} but found end of input at {}",
+                    describe_expected(expected),
+                    position
+                )
+            }
+            ParserError::UnclosedDelimiter { opening } => {
+                write!(f, "unclosed '{}' at {}", opening.lexeme, opening.start)
+            }
+            ParserError::InvalidAssignmentTarget { position, .. } => {
+                write!(f, "invalid assignment target at {}", position)
+            }
+            ParserError::NestingTooDeep {
+                position, limit, ..
+            } => {
+                write!(
+                    f,
+                    "nesting exceeds the limit of {} levels at {}",
+                    limit, position
+                )
+            }
+            ParserError::TooManyParameters {
+                position, limit, ..
+            } => {
+                write!(f, "more than {} parameters declared at {}", limit, position)
+            }
+            ParserError::TooManyArguments {
+                position, limit, ..
+            } => {
+                write!(f, "more than {} arguments passed at {}", limit, position)
+            }
+            ParserError::ReturnOutsideFunction { position, .. } => {
+                write!(f, "'return' outside of a function at {}", position)
+            }
+            ParserError::BreakOutsideLoop { position, .. } => {
+                write!(f, "'break' outside of a loop at {}", position)
+            }
+            ParserError::ContinueOutsideLoop { position, .. } => {
+                write!(f, "'continue' outside of a loop at {}", position)
+            }
+            ParserError::Lexer(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for ParserError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParserError::Lexer(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<LexerError> for ParserError {
+    fn from(e: LexerError) -> Self {
+        ParserError::Lexer(e)
+    }
+}
+
+/// Errors returned by Interpreter. They abort the execution of the program.
+///
+/// Each error carries the position and span of the token it is reported at, such as the operator
+/// of a unary or binary expression, or the name of a variable - not the span of the whole syntax
+/// tree node. Only `ExecutionLimitExceeded` spans the statement which couldn't be executed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeError {
+    /// Returned when an operator is applied to an operand of a type other than the one it takes,
+    /// such as `-true`. The position is the one of the operator.
+    TypeMismatch {
+        /// Type the operator takes, see `Value::type_name`.
+        expected: &'static str,
+        found: &'static str,
+        position: Position,
+        span: Span,
+    },
+
+    /// Returned when a binary operator is applied to operands of types it doesn't support, such
+    /// as `true + 1`. The position is the one of the operator.
+    InvalidOperands {
+        /// What the operator does, such as `add`.
+        operation: &'static str,
+        left: &'static str,
+        right: &'static str,
+        position: Position,
+        span: Span,
+    },
+
+    /// Returned when a variable is used which wasn't declared, or whose scope has ended.
+    UndefinedVariable {
+        name: String,
+        position: Position,
+        span: Span,
+    },
+
+    /// Returned when dividing by zero, or taking the remainder of such a division. The position
+    /// is the one of the operator.
+    DivisionByZero { position: Position, span: Span },
+
+    /// Returned for constructs which can be parsed, but not executed yet, such as functions.
+    Unsupported {
+        /// Description of the construct, such as `functions`.
+        construct: &'static str,
+        position: Position,
+        span: Span,
+    },
+
+    /// Returned when a program runs out of the fuel it was given, see `Interpreter::with_fuel`.
+    /// The position is the one of the statement which couldn't be executed anymore.
+    ExecutionLimitExceeded {
+        /// Fuel the program was given.
+        limit: u64,
+        position: Position,
+        span: Span,
+    },
+
+    /// Returned for errors which have no kind of their own.
+    Custom {
+        message: String,
+        position: Position,
+        span: Span,
+    },
+}
+
+impl RuntimeError {
+    /// Position of the source the error refers to.
+    pub fn position(&self) -> Position {
+        match self {
+            RuntimeError::TypeMismatch { position, .. }
+            | RuntimeError::InvalidOperands { position, .. }
+            | RuntimeError::UndefinedVariable { position, .. }
+            | RuntimeError::DivisionByZero { position, .. }
+            | RuntimeError::Unsupported { position, .. }
+            | RuntimeError::ExecutionLimitExceeded { position, .. }
+            | RuntimeError::Custom { position, .. } => *position,
+        }
+    }
+
+    /// Byte offsets of the source the error refers to.
+    pub fn span(&self) -> Span {
+        match self {
+            RuntimeError::TypeMismatch { span, .. }
+            | RuntimeError::InvalidOperands { span, .. }
+            | RuntimeError::UndefinedVariable { span, .. }
+            | RuntimeError::DivisionByZero { span, .. }
+            | RuntimeError::Unsupported { span, .. }
+            | RuntimeError::ExecutionLimitExceeded { span, .. }
+            | RuntimeError::Custom { span, .. } => *span,
         }
     }
+
+    /// Stable, machine-readable code of the kind of error, like `LexerError::code`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            RuntimeError::TypeMismatch { .. } => "R0001",
+            RuntimeError::InvalidOperands { .. } => "R0002",
+            RuntimeError::UndefinedVariable { .. } => "R0003",
+            RuntimeError::DivisionByZero { .. } => "R0004",
+            RuntimeError::Unsupported { .. } => "R0005",
+            RuntimeError::Custom { .. } => "R0006",
+            RuntimeError::ExecutionLimitExceeded { .. } => "R0007",
+        }
+    }
+
+    /// Render the error alongside the line of `source` it refers to, see `diagnostics::render`.
+    pub fn render(&self, source: &str) -> String {
+        Diagnostic::from(self).render(source)
+    }
+}
+
+impl From<&RuntimeError> for Diagnostic {
+    fn from(e: &RuntimeError) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code: e.code(),
+            message: e.to_string(),
+            position: e.position(),
+            span: e.span(),
+        }
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeError::TypeMismatch {
+                expected,
+                found,
+                position,
+                ..
+            } => write!(
+                f,
+                "expected {} but found {} at {}",
+                expected, found, position
+            ),
+            RuntimeError::InvalidOperands {
+                operation,
+                left,
+                right,
+                position,
+                ..
+            } => write!(
+                f,
+                "cannot {} {} and {} at {}",
+                operation, left, right, position
+            ),
+            RuntimeError::UndefinedVariable { name, position, .. } => {
+                write!(f, "undefined variable '{}' at {}", name, position)
+            }
+            RuntimeError::DivisionByZero { position, .. } => {
+                write!(f, "division by zero at {}", position)
+            }
+            RuntimeError::Unsupported {
+                construct,
+                position,
+                ..
+            } => write!(f, "{} are not supported yet, at {}", construct, position),
+            RuntimeError::ExecutionLimitExceeded {
+                limit, position, ..
+            } => write!(
+                f,
+                "execution limit of {} steps exceeded at {}",
+                limit, position
+            ),
+            RuntimeError::Custom {
+                message, position, ..
+            } => write!(f, "{} at {}", message, position),
+        }
+    }
+}
+
+impl Error for RuntimeError {}
+
+/// Errors of any stage of processing SPL source, for drivers which want to handle them uniformly.
+///
+#[derive(Clone)]
+pub enum SplError {
+    /// Reading the source failed.
+    Io(Arc<io::Error>),
+    /// Lexing the source failed. Holds all errors the lexer encountered, of which there is at
+    /// least one.
+    Lexer(Vec<LexerError>),
+    /// Parsing the source failed. Holds all errors the parser encountered, of which there is at
+    /// least one.
+    Parser(Vec<ParserError>),
+    /// Executing the program failed.
+    Runtime(RuntimeError),
+}
+
+impl From<io::Error> for SplError {
+    fn from(e: io::Error) -> Self {
+        SplError::Io(Arc::new(e))
+    }
+}
+
+impl From<LexerError> for SplError {
+    fn from(e: LexerError) -> Self {
+        SplError::Lexer(vec![e])
+    }
+}
+
+impl From<Vec<LexerError>> for SplError {
+    fn from(errors: Vec<LexerError>) -> Self {
+        SplError::Lexer(errors)
+    }
+}
+
+impl From<Vec<ParserError>> for SplError {
+    fn from(errors: Vec<ParserError>) -> Self {
+        SplError::Parser(errors)
+    }
+}
+
+impl From<RuntimeError> for SplError {
+    fn from(e: RuntimeError) -> Self {
+        SplError::Runtime(e)
+    }
+}
+
+impl Display for SplError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SplError::Io(e) => write!(f, "Failed to read input: {}", e),
+            SplError::Lexer(errors) => match errors.as_slice() {
+                [e] => write!(f, "Tokenization failed: {}", e),
+                _ => write!(f, "Tokenization failed with {} errors", errors.len()),
+            },
+            SplError::Parser(errors) => match errors.as_slice() {
+                [e] => write!(f, "Parsing failed: {}", e),
+                _ => write!(f, "Parsing failed with {} errors", errors.len()),
+            },
+            SplError::Runtime(e) => write!(f, "Execution failed: {}", e),
+        }
+    }
+}
+
+// Returning an error from `main` prints its Debug representation, which should be as readable as
+// its Display one.
+impl std::fmt::Debug for SplError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Error for SplError {
+    /// The underlying error. For lexer and parser errors, that is the first one encountered.
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SplError::Io(e) => Some(e.as_ref()),
+            SplError::Lexer(errors) => errors.first().map(|e| e as &(dyn Error + 'static)),
+            SplError::Parser(errors) => errors.first().map(|e| e as &(dyn Error + 'static)),
+            SplError::Runtime(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unexpected_char() -> LexerError {
+        LexerError::UnexpectedChar {
+            position: Position { line: 3, column: 7 },
+            c: '@',
+            span: Span { start: 20, end: 21 },
+        }
+    }
+
+    #[test]
+    fn test_boxed_error() {
+        let error: Box<dyn Error> = unexpected_char().into();
+        assert_eq!(
+            error.to_string(),
+            "Unexpected char `@` (unicode \\u{40}) found at line 3, column 7"
+        );
+        assert!(error.source().is_none());
+        assert_eq!(error.downcast_ref::<LexerError>(), Some(&unexpected_char()));
+
+        let error: Box<dyn Error + Send + Sync> = SplError::from(unexpected_char()).into();
+        assert_eq!(
+            error.to_string(),
+            "Tokenization failed: Unexpected char `@` (unicode \\u{40}) found at line 3, column 7"
+        );
+        let source = error.source().unwrap();
+        assert_eq!(
+            source.downcast_ref::<LexerError>(),
+            Some(&unexpected_char())
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_from_error() {
+        let diagnostic = Diagnostic::from(&unexpected_char());
+        assert_eq!(
+            diagnostic,
+            Diagnostic {
+                severity: Severity::Error,
+                code: "L0001",
+                message: unexpected_char().to_string(),
+                position: Position { line: 3, column: 7 },
+                span: Span { start: 20, end: 21 },
+            }
+        );
+        assert_eq!(
+            diagnostic.to_string(),
+            "error: Unexpected char `@` (unicode \\u{40}) found at line 3, column 7"
+        );
+
+        let error = ParserError::InvalidAssignmentTarget {
+            position: Position { line: 1, column: 3 },
+            span: Span { start: 2, end: 3 },
+        };
+        assert_eq!(error.code(), "P0004");
+        assert_eq!(
+            error.render("1 = 2;"),
+            concat!(
+                "error: invalid assignment target at line 1, column 3\n",
+                "  |\n",
+                "1 | 1 = 2;\n",
+                "  |   ^\n",
+            )
+        );
+        let error = ParserError::Lexer(unexpected_char());
+        assert_eq!(Diagnostic::from(&error), diagnostic);
+    }
+
+    #[test]
+    fn test_spl_error() {
+        let reserved = LexerError::ReservedWord {
+            position: Position { line: 1, column: 1 },
+            word: "class".to_string(),
+            span: Span { start: 0, end: 5 },
+        };
+        let error = SplError::from(vec![unexpected_char(), reserved.clone()]);
+        assert_eq!(error.to_string(), "Tokenization failed with 2 errors");
+        assert_eq!(format!("{:?}", error), error.to_string());
+        match error.clone() {
+            SplError::Lexer(errors) => assert_eq!(errors, vec![unexpected_char(), reserved]),
+            _ => panic!("Expected lexer errors"),
+        }
+
+        let error = SplError::from(vec![ParserError::InvalidAssignmentTarget {
+            position: Position { line: 2, column: 3 },
+            span: Span { start: 13, end: 14 },
+        }]);
+        assert_eq!(
+            error.to_string(),
+            "Parsing failed: invalid assignment target at line 2, column 3"
+        );
+
+        let runtime_error = RuntimeError::UndefinedVariable {
+            name: "a".to_string(),
+            position: Position { line: 1, column: 1 },
+            span: Span { start: 0, end: 1 },
+        };
+        let error = SplError::from(runtime_error.clone());
+        assert_eq!(
+            error.to_string(),
+            "Execution failed: undefined variable 'a' at line 1, column 1"
+        );
+        assert_eq!(
+            error.source().unwrap().downcast_ref::<RuntimeError>(),
+            Some(&runtime_error)
+        );
+
+        let error = SplError::from(io::Error::new(io::ErrorKind::NotFound, "no such file"));
+        assert_eq!(error.to_string(), "Failed to read input: no such file");
+        let source = error.source().unwrap();
+        assert_eq!(
+            source.downcast_ref::<io::Error>().unwrap().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
 }
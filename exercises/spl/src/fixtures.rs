@@ -0,0 +1,5 @@
+//! Inputs shared by the tests of several modules.
+
+/// Program exercising most of the language. It lives in a file of its own, as the integration
+/// tests share it.
+pub(crate) const PROGRAM: &str = include_str!("../tests/fixture.spl");
@@ -0,0 +1,118 @@
+//! Interning of identifiers.
+//!
+//! Every distinct name is stored once in a `StringPool`, and referred to by a `Symbol`. Later
+//! phases can compare and hash symbols, which are plain integers, rather than the names
+//! themselves.
+
+use std::{collections::HashMap, rc::Rc};
+
+/// Handle of a string interned in a `StringPool`.
+///
+/// Symbols are only meaningful for the pool which created them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// Index of the symbol within its pool, in the order the strings were first interned.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Pool of interned strings.
+#[derive(Debug, Default)]
+pub struct StringPool {
+    // Both refer to the same allocation of each string.
+    symbols: HashMap<Rc<str>, Symbol>,
+    strings: Vec<Rc<str>>,
+}
+
+impl StringPool {
+    pub fn new() -> StringPool {
+        StringPool::default()
+    }
+
+    /// Intern `s`, returning its symbol.
+    ///
+    /// Interning the same string again returns the same symbol, without allocating.
+    pub fn intern(&mut self, s: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(s) {
+            return *symbol;
+        }
+
+        let symbol =
+            Symbol(u32::try_from(self.strings.len()).expect("more than u32::MAX strings interned"));
+        let s: Rc<str> = Rc::from(s);
+        self.strings.push(Rc::clone(&s));
+        self.symbols.insert(s, symbol);
+
+        symbol
+    }
+
+    /// Symbol of `s`, if it was interned.
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        self.symbols.get(s).copied()
+    }
+
+    /// String of `symbol`, if it belongs to this pool.
+    pub fn resolve(&self, symbol: Symbol) -> Option<&str> {
+        self.strings.get(symbol.index()).map(|s| s.as_ref())
+    }
+
+    /// Remove all strings from the pool. Symbols created before are invalidated, and will be
+    /// handed out again for other strings.
+    pub fn clear(&mut self) {
+        self.symbols.clear();
+        self.strings.clear();
+    }
+
+    /// Number of distinct strings in the pool.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern() {
+        let mut pool = StringPool::new();
+        assert!(pool.is_empty());
+
+        let a = pool.intern("a");
+        let b = pool.intern("b");
+        assert_ne!(a, b);
+        assert_eq!(pool.intern("a"), a);
+        assert_eq!(pool.len(), 2);
+
+        assert_eq!(pool.get("b"), Some(b));
+        assert_eq!(pool.get("c"), None);
+
+        assert_eq!(pool.resolve(a), Some("a"));
+        assert_eq!(pool.resolve(b), Some("b"));
+        assert_eq!(pool.resolve(Symbol(2)), None);
+
+        pool.clear();
+        assert!(pool.is_empty());
+        assert_eq!(pool.get("a"), None);
+        assert_eq!(pool.intern("c"), a);
+    }
+
+    #[test]
+    fn test_single_allocation() {
+        let mut pool = StringPool::new();
+        let symbol = pool.intern(&String::from("name"));
+        pool.intern(&String::from("name"));
+
+        // The map and the list share the string.
+        let (key, _) = pool.symbols.get_key_value("name").unwrap();
+        assert!(Rc::ptr_eq(key, &pool.strings[symbol.index()]));
+        assert_eq!(Rc::strong_count(key), 2);
+    }
+}
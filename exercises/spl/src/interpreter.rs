@@ -0,0 +1,985 @@
+//! Tree-walking interpreter, executing syntax trees as produced by the parser.
+//!
+//! Values are numbers, strings, booleans and `nil`. Operators apply to them as follows:
+//!
+//! - Arithmetic operators take numbers. `+` also concatenates two strings. Dividing by zero is an
+//!   error, rather than resulting in infinity or NaN.
+//! - Comparisons take numbers, and yield a boolean.
+//! - `==` and `!=` take values of any types, where values of different types are never equal.
+//! - `!` negates whether its operand is truthy, which all values except `false` and `nil` are.
+//! - `and` and `or` evaluate their right operand only if the left one doesn't decide the result.
+//!   They yield the operand which decided it, e.g. `nil or 2` is `2`.
+//!
+//! Variables must be declared before they are used, or assigned to. Blocks are scopes of their
+//! own, see `Environment`.
+//!
+//! `print` writes the values it is given to the output of the interpreter, each followed by a line
+//! break, see `Interpreter::with_output`.
+//!
+//! To stop programs which may not terminate, the interpreter can be given a limited amount of fuel,
+//! see `Interpreter::with_fuel`.
+//!
+//! Functions can't be executed yet.
+
+use std::{
+    fmt::Display,
+    io::{self, Stdout, Write},
+};
+
+use crate::{
+    ast::{Expr, Stmt},
+    environment::Environment,
+    error::RuntimeError,
+    position::Span,
+    token::{Literal, Token, TokenType},
+    visitor::{ExprVisitor, StmtVisitor},
+};
+
+/// Values which expressions evaluate to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Str(String),
+    Bool(bool),
+    Nil,
+}
+
+impl Value {
+    /// Name of the type of the value, as used in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "Number",
+            Value::Str(_) => "Str",
+            Value::Bool(_) => "Bool",
+            Value::Nil => "Nil",
+        }
+    }
+
+    /// Whether the value counts as true in conditions, which all but `false` and `nil` do.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Bool(false) | Value::Nil)
+    }
+}
+
+impl From<&Literal> for Value {
+    fn from(literal: &Literal) -> Self {
+        match literal {
+            Literal::Number(n) => Value::Number(*n),
+            Literal::Str(s) => Value::Str(s.clone()),
+            Literal::Bool(b) => Value::Bool(*b),
+            Literal::None => Value::Nil,
+        }
+    }
+}
+
+/// The form in which `print` outputs values:
+///
+/// - Numbers in decimal notation, without a fractional part if they are integral, e.g. `3` rather
+///   than `3.0`.
+/// - Strings as they are, without quotes.
+/// - Booleans as `true` or `false`, and `nil` as `nil`.
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Str(s) => write!(f, "{}", s),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Nil => write!(f, "nil"),
+        }
+    }
+}
+
+/// How the execution of a statement ended, so that loops can react to `break` and `continue`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+}
+
+/// Executes programs, writing what they print to an output, which is stdout by default.
+///
+/// Global variables persist across calls of `interpret`, so that a program can be executed in
+/// parts.
+#[derive(Debug)]
+pub struct Interpreter<W: Write = Stdout> {
+    environment: Environment,
+    output: W,
+    /// Fuel given to the interpreter, and the amount of it left. None if unlimited.
+    fuel: Option<(u64, u64)>,
+}
+
+impl Interpreter {
+    /// Create an interpreter which writes the output of programs to stdout.
+    pub fn new() -> Interpreter {
+        Interpreter::with_output(io::stdout())
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+impl<W: Write> Interpreter<W> {
+    /// Create an interpreter which writes the output of programs to `output`, such as a
+    /// `Vec<u8>` to capture it.
+    pub fn with_output(output: W) -> Interpreter<W> {
+        Interpreter {
+            environment: Environment::new(),
+            output,
+            fuel: None,
+        }
+    }
+
+    /// Limit execution to `fuel` steps, after which it aborts with
+    /// `RuntimeError::ExecutionLimitExceeded`. By default, execution is unlimited.
+    ///
+    /// Every statement executed takes a step, including those within nested blocks, as does
+    /// every iteration of a loop. Blocks themselves take none. The fuel is shared by all calls of
+    /// `interpret`.
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = Some((fuel, fuel));
+        self
+    }
+
+    /// Fuel left, or None if execution is unlimited.
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel.map(|(_, left)| left)
+    }
+
+    /// The output written to so far.
+    pub fn output(&self) -> &W {
+        &self.output
+    }
+
+    /// Consume the interpreter, returning its output.
+    pub fn into_output(self) -> W {
+        self.output
+    }
+
+    /// Execute `program`, stopping at the first runtime error.
+    ///
+    /// Failing to write to the output is a runtime error as well.
+    pub fn interpret(&mut self, program: &[Stmt]) -> Result<(), RuntimeError> {
+        for statement in program {
+            self.execute(statement)?;
+        }
+
+        Ok(())
+    }
+
+    /// Evaluate a single expression.
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        expr.accept(self)
+    }
+
+    /// Execute `statements` in order, until one of them breaks or continues a loop.
+    fn execute_all(&mut self, statements: &[Stmt]) -> Result<Flow, RuntimeError> {
+        for statement in statements {
+            let flow = self.execute(statement)?;
+            if flow != Flow::Normal {
+                return Ok(flow);
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    /// Execute `statement`, if there is fuel left to do so.
+    fn execute(&mut self, statement: &Stmt) -> Result<Flow, RuntimeError> {
+        let token = match statement {
+            Stmt::Block { .. } => None,
+            Stmt::Expression { expr, .. } => Some(first_token(expr)),
+            Stmt::Print { keyword, .. }
+            | Stmt::If { keyword, .. }
+            | Stmt::While { keyword, .. }
+            | Stmt::Return { keyword, .. }
+            | Stmt::Break { keyword, .. }
+            | Stmt::Continue { keyword, .. } => Some(keyword),
+            Stmt::Var { name, .. } | Stmt::Function { name, .. } => Some(name),
+        };
+        if let Some(token) = token {
+            self.consume_fuel(token, statement.span())?;
+        }
+
+        statement.accept(self)
+    }
+
+    /// Take a step of execution, reporting running out of fuel at `token`.
+    fn consume_fuel(&mut self, token: &Token<'static>, span: Span) -> Result<(), RuntimeError> {
+        match &mut self.fuel {
+            Some((limit, 0)) => Err(RuntimeError::ExecutionLimitExceeded {
+                limit: *limit,
+                position: token.start,
+                span,
+            }),
+            Some((_, left)) => {
+                *left -= 1;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// The leftmost token of `expr`, which parentheses around it don't count as.
+fn first_token(expr: &Expr) -> &Token<'static> {
+    match expr {
+        Expr::Literal { token, .. } => token,
+        Expr::Variable { name, .. } | Expr::Assign { name, .. } => name,
+        Expr::Unary { operator, .. } => operator,
+        Expr::Grouping { expr, .. } | Expr::Binary { left: expr, .. } => first_token(expr),
+        Expr::Call { callee, .. } => first_token(callee),
+    }
+}
+
+/// Error of applying `operator` to operands of types it doesn't support.
+fn invalid_operands(operator: &Token<'static>, left: &Value, right: &Value) -> RuntimeError {
+    let operation = match operator.token_type {
+        TokenType::Plus => "add",
+        TokenType::Minus => "subtract",
+        TokenType::Times => "multiply",
+        TokenType::Divide => "divide",
+        TokenType::Modulo => "take the remainder of",
+        _ => "compare",
+    };
+
+    RuntimeError::InvalidOperands {
+        operation,
+        left: left.type_name(),
+        right: right.type_name(),
+        position: operator.start,
+        span: operator.span,
+    }
+}
+
+impl<W: Write> ExprVisitor<Result<Value, RuntimeError>> for Interpreter<W> {
+    fn visit_literal(
+        &mut self,
+        value: &Literal,
+        _token: &Token<'static>,
+    ) -> Result<Value, RuntimeError> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_variable(&mut self, name: &Token<'static>) -> Result<Value, RuntimeError> {
+        self.environment.get(name)
+    }
+
+    fn visit_assign(&mut self, name: &Token<'static>, value: &Expr) -> Result<Value, RuntimeError> {
+        let value = value.accept(self)?;
+        self.environment.assign(name, value.clone())?;
+
+        Ok(value)
+    }
+
+    fn visit_grouping(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        expr.accept(self)
+    }
+
+    fn visit_unary(
+        &mut self,
+        operator: &Token<'static>,
+        operand: &Expr,
+    ) -> Result<Value, RuntimeError> {
+        let operand = operand.accept(self)?;
+
+        match (operator.token_type, operand) {
+            (TokenType::BooleanNot, operand) => Ok(Value::Bool(!operand.is_truthy())),
+            (TokenType::Minus, Value::Number(n)) => Ok(Value::Number(-n)),
+            (_, operand) => Err(RuntimeError::TypeMismatch {
+                expected: "Number",
+                found: operand.type_name(),
+                position: operator.start,
+                span: operator.span,
+            }),
+        }
+    }
+
+    fn visit_binary(
+        &mut self,
+        left: &Expr,
+        operator: &Token<'static>,
+        right: &Expr,
+    ) -> Result<Value, RuntimeError> {
+        let left = left.accept(self)?;
+
+        // The logical operators only evaluate their right operand if needed.
+        match operator.token_type {
+            TokenType::And if !left.is_truthy() => return Ok(left),
+            TokenType::Or if left.is_truthy() => return Ok(left),
+            TokenType::And | TokenType::Or => return right.accept(self),
+            _ => {}
+        }
+
+        let right = right.accept(self)?;
+        let value = match (operator.token_type, &left, &right) {
+            (TokenType::DoubleEquals, _, _) => Value::Bool(left == right),
+            (TokenType::NotEquals, _, _) => Value::Bool(left != right),
+            (TokenType::Plus, Value::Str(a), Value::Str(b)) => Value::Str(format!("{}{}", a, b)),
+            (TokenType::Divide | TokenType::Modulo, Value::Number(_), Value::Number(b))
+                if *b == 0.0 =>
+            {
+                return Err(RuntimeError::DivisionByZero {
+                    position: operator.start,
+                    span: operator.span,
+                });
+            }
+            (token_type, Value::Number(a), Value::Number(b)) => match token_type {
+                TokenType::Plus => Value::Number(a + b),
+                TokenType::Minus => Value::Number(a - b),
+                TokenType::Times => Value::Number(a * b),
+                TokenType::Divide => Value::Number(a / b),
+                TokenType::Modulo => Value::Number(a % b),
+                TokenType::Less => Value::Bool(a < b),
+                TokenType::LessOrEqual => Value::Bool(a <= b),
+                TokenType::Greater => Value::Bool(a > b),
+                TokenType::GreaterOrEqual => Value::Bool(a >= b),
+                _ => return Err(invalid_operands(operator, &left, &right)),
+            },
+            _ => return Err(invalid_operands(operator, &left, &right)),
+        };
+
+        Ok(value)
+    }
+
+    fn visit_call(
+        &mut self,
+        _callee: &Expr,
+        _args: &[Expr],
+        closing_paren: &Token<'static>,
+    ) -> Result<Value, RuntimeError> {
+        Err(RuntimeError::Unsupported {
+            construct: "function calls",
+            position: closing_paren.start,
+            span: closing_paren.span,
+        })
+    }
+}
+
+impl<W: Write> StmtVisitor<Result<Flow, RuntimeError>> for Interpreter<W> {
+    fn visit_expression_stmt(&mut self, expr: &Expr) -> Result<Flow, RuntimeError> {
+        expr.accept(self)?;
+        Ok(Flow::Normal)
+    }
+
+    fn visit_print(&mut self, keyword: &Token<'static>, expr: &Expr) -> Result<Flow, RuntimeError> {
+        let value = expr.accept(self)?;
+        writeln!(self.output, "{}", value).map_err(|e| RuntimeError::Custom {
+            message: format!("failed to write output: {}", e),
+            position: keyword.start,
+            span: keyword.span,
+        })?;
+
+        Ok(Flow::Normal)
+    }
+
+    fn visit_var(
+        &mut self,
+        name: &Token<'static>,
+        initializer: Option<&Expr>,
+    ) -> Result<Flow, RuntimeError> {
+        let value = match initializer {
+            Some(initializer) => initializer.accept(self)?,
+            None => Value::Nil,
+        };
+        self.environment.define(&name.lexeme, value);
+
+        Ok(Flow::Normal)
+    }
+
+    fn visit_if(
+        &mut self,
+        _keyword: &Token<'static>,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> Result<Flow, RuntimeError> {
+        if condition.accept(self)?.is_truthy() {
+            self.execute(then_branch)
+        } else if let Some(else_branch) = else_branch {
+            self.execute(else_branch)
+        } else {
+            Ok(Flow::Normal)
+        }
+    }
+
+    fn visit_while(
+        &mut self,
+        keyword: &Token<'static>,
+        condition: &Expr,
+        body: &Stmt,
+    ) -> Result<Flow, RuntimeError> {
+        while condition.accept(self)?.is_truthy() {
+            // So that loops with empty bodies run out of fuel as well.
+            self.consume_fuel(keyword, keyword.span)?;
+            if self.execute(body)? == Flow::Break {
+                break;
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt]) -> Result<Flow, RuntimeError> {
+        self.environment.push_scope();
+        let result = self.execute_all(statements);
+        // Also if executing the block failed, so that the interpreter can be used further.
+        self.environment.pop_scope();
+
+        result
+    }
+
+    fn visit_function(
+        &mut self,
+        name: &Token<'static>,
+        _params: &[Token<'static>],
+        _body: &[Stmt],
+    ) -> Result<Flow, RuntimeError> {
+        Err(RuntimeError::Unsupported {
+            construct: "functions",
+            position: name.start,
+            span: name.span,
+        })
+    }
+
+    fn visit_return(
+        &mut self,
+        keyword: &Token<'static>,
+        _value: Option<&Expr>,
+    ) -> Result<Flow, RuntimeError> {
+        // Only valid within functions, which aren't supported to begin with.
+        Err(RuntimeError::Unsupported {
+            construct: "functions",
+            position: keyword.start,
+            span: keyword.span,
+        })
+    }
+
+    fn visit_break(&mut self, _keyword: &Token<'static>) -> Result<Flow, RuntimeError> {
+        Ok(Flow::Break)
+    }
+
+    fn visit_continue(&mut self, _keyword: &Token<'static>) -> Result<Flow, RuntimeError> {
+        Ok(Flow::Continue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixtures::PROGRAM,
+        lexer::Lexer,
+        parser::Parser,
+        position::{Position, Span},
+    };
+
+    fn evaluate(source: &str) -> Result<Value, RuntimeError> {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let expr = Parser::new(tokens).parse_expression().unwrap();
+        Interpreter::new().evaluate(&expr)
+    }
+
+    /// Interpreter which executed `source`, to inspect its variables and output.
+    fn run(source: &str) -> Result<Interpreter<Vec<u8>>, RuntimeError> {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        let mut interpreter = Interpreter::with_output(Vec::new());
+        interpreter.interpret(&program)?;
+        Ok(interpreter)
+    }
+
+    fn variable<W: Write>(interpreter: &mut Interpreter<W>, name: &str) -> Value {
+        let tokens = Lexer::new(name).tokenize().unwrap();
+        let expr = Parser::new(tokens).parse_expression().unwrap();
+        interpreter.evaluate(&expr).unwrap()
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let cases = [
+            ("1 + 2 * 3", 7.0),
+            ("(1 + 2) * 3", 9.0),
+            ("10 - 4 - 3", 3.0),
+            ("7 / 2", 3.5),
+            ("7 % 3", 1.0),
+            ("-(2 - 5)", 3.0),
+            ("--1", 1.0),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(
+                evaluate(source),
+                Ok(Value::Number(expected)),
+                "{:?}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(
+            evaluate(r#""foo" + "bar" + 'c'"#),
+            Ok(Value::Str("foobarc".to_string()))
+        );
+        assert_eq!(evaluate(r#""a" == "a""#), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let cases = [
+            ("1 < 2", true),
+            ("2 <= 2", true),
+            ("1 > 2", false),
+            ("1 >= 2", false),
+            ("1 == 1", true),
+            ("1 != 1", false),
+            // Values of different types are unequal, rather than an error.
+            ("1 == \"1\"", false),
+            ("nil == false", false),
+            ("nil == nil", true),
+            ("true != 1", true),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(evaluate(source), Ok(Value::Bool(expected)), "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_logical_operators() {
+        let cases = [
+            ("!nil", Value::Bool(true)),
+            ("!0", Value::Bool(false)),
+            ("nil or 2", Value::Number(2.0)),
+            ("1 or 2", Value::Number(1.0)),
+            ("1 and 2", Value::Number(2.0)),
+            ("false and 2", Value::Bool(false)),
+            // The right operand isn't evaluated, so its error doesn't occur.
+            ("true or -nil", Value::Bool(true)),
+            ("nil and -nil", Value::Nil),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(evaluate(source), Ok(expected), "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_type_errors() {
+        let cases = [
+            ("true + 1", "cannot add Bool and Number at line 1, column 6"),
+            (
+                "1 +\n\"a\"",
+                "cannot add Number and Str at line 1, column 3",
+            ),
+            (
+                "nil - 1",
+                "cannot subtract Nil and Number at line 1, column 5",
+            ),
+            (
+                "\"a\" * 2",
+                "cannot multiply Str and Number at line 1, column 5",
+            ),
+            (
+                "1 < \"2\"",
+                "cannot compare Number and Str at line 1, column 3",
+            ),
+            (
+                "-\"a\"",
+                "expected Number but found Str at line 1, column 1",
+            ),
+            (
+                "1 + (2 * -true)",
+                "expected Number but found Bool at line 1, column 10",
+            ),
+        ];
+
+        for (source, expected) in cases {
+            let error = evaluate(source).unwrap_err();
+            assert_eq!(error.to_string(), expected, "{:?}", source);
+        }
+
+        assert_eq!(
+            evaluate("1 + 2 - false"),
+            Err(RuntimeError::InvalidOperands {
+                operation: "subtract",
+                left: "Number",
+                right: "Bool",
+                position: Position { line: 1, column: 7 },
+                span: Span { start: 6, end: 7 },
+            })
+        );
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        let source = "1 + (2 * (3 / (1 - 1)))";
+        let error = evaluate(source).unwrap_err();
+        assert_eq!(
+            error,
+            RuntimeError::DivisionByZero {
+                position: Position {
+                    line: 1,
+                    column: 13
+                },
+                span: Span { start: 12, end: 13 },
+            }
+        );
+        assert_eq!(error.to_string(), "division by zero at line 1, column 13");
+        assert_eq!(
+            error.render(source),
+            concat!(
+                "error: division by zero at line 1, column 13\n",
+                "  |\n",
+                "1 | 1 + (2 * (3 / (1 - 1)))\n",
+                "  |             ^\n",
+            )
+        );
+
+        assert!(matches!(
+            evaluate("5 % 0"),
+            Err(RuntimeError::DivisionByZero { .. })
+        ));
+        assert_eq!(evaluate("0 / 5"), Ok(Value::Number(0.0)));
+    }
+
+    #[test]
+    fn test_errors_in_branches() {
+        // Only branches which are executed can fail.
+        let source = "var a = 1;\nif (a == 1) {\n    print a;\n} else {\n    print b;\n}";
+        assert!(run(source).is_ok());
+        assert!(run("while (false) print -nil;").is_ok());
+        assert!(run("false and x;").is_ok());
+
+        let source = "var a = 1;\nif (a == 2) {\n    print a;\n} else {\n    print b;\n}";
+        let error = run(source).unwrap_err();
+        assert_eq!(
+            error,
+            RuntimeError::UndefinedVariable {
+                name: "b".to_string(),
+                position: Position {
+                    line: 5,
+                    column: 11
+                },
+                span: Span { start: 57, end: 58 },
+            }
+        );
+        assert_eq!(
+            error.render(source),
+            concat!(
+                "error: undefined variable 'b' at line 5, column 11\n",
+                "  |\n",
+                "5 |     print b;\n",
+                "  |           ^\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_variables() {
+        let mut interpreter = run("var a = 1; var b; a = a + 1; var c = a = 5;").unwrap();
+        assert_eq!(variable(&mut interpreter, "a"), Value::Number(5.0));
+        assert_eq!(variable(&mut interpreter, "b"), Value::Nil);
+        assert_eq!(variable(&mut interpreter, "c"), Value::Number(5.0));
+
+        assert_eq!(
+            run("var a = 1;\nb = a;").unwrap_err(),
+            RuntimeError::UndefinedVariable {
+                name: "b".to_string(),
+                position: Position { line: 2, column: 1 },
+                span: Span { start: 11, end: 12 },
+            }
+        );
+        assert_eq!(
+            run("print x;").unwrap_err().to_string(),
+            "undefined variable 'x' at line 1, column 7"
+        );
+    }
+
+    #[test]
+    fn test_scopes() {
+        let source = "
+var a = \"global\";
+var b = 1;
+{
+    var a = \"block\";
+    b = a;
+    var c = 3;
+    {
+        var a = a + \" inner\";
+        c = a;
+    }
+    b = c;
+}";
+        let mut interpreter = run(source).unwrap();
+        // Shadowed within the block only, while assignments to outer variables persist.
+        assert_eq!(
+            variable(&mut interpreter, "a"),
+            Value::Str("global".to_string())
+        );
+        assert_eq!(
+            variable(&mut interpreter, "b"),
+            Value::Str("block inner".to_string())
+        );
+
+        // Variables of the block are gone after it.
+        assert_eq!(
+            run("{\n    var c = 1;\n}\nprint c;").unwrap_err(),
+            RuntimeError::UndefinedVariable {
+                name: "c".to_string(),
+                position: Position { line: 4, column: 7 },
+                span: Span { start: 25, end: 26 },
+            }
+        );
+        assert!(run("for (var i = 0; i < 1; i = i + 1) {} print i;").is_err());
+        assert!(run("while (true) { var x = 1; break; } x = 2;").is_err());
+    }
+
+    #[test]
+    fn test_scope_after_error() {
+        let mut interpreter = Interpreter::with_output(Vec::new());
+        let program = |source| {
+            Parser::new(Lexer::new(source).tokenize().unwrap())
+                .parse_program()
+                .unwrap()
+        };
+
+        assert!(interpreter
+            .interpret(&program("var a = 1; { var a = 2; a = a + nil; }"))
+            .is_err());
+        interpreter.interpret(&program("var b = a;")).unwrap();
+        assert_eq!(variable(&mut interpreter, "b"), Value::Number(1.0));
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let source = "
+var n = 0;
+var evens = 0;
+while (true) {
+    n = n + 1;
+    if (n > 10) break;
+    if (n % 2 == 1) continue;
+    evens = evens + 1;
+}
+var sum = 0;
+for (var i = 1; i <= 4; i = i + 1) {
+    if (i == 2) continue;
+    sum = sum + i;
+}";
+        let mut interpreter = run(source).unwrap();
+        assert_eq!(variable(&mut interpreter, "n"), Value::Number(11.0));
+        assert_eq!(variable(&mut interpreter, "evens"), Value::Number(5.0));
+        assert_eq!(variable(&mut interpreter, "sum"), Value::Number(8.0));
+
+        // The increment updates the loop variable, even when the body shadows it and continues.
+        let source = "var n = 0;
+for (var i = 0; i < 3; i = i + 1) {
+    var i = 100;
+    n = n + 1;
+    continue;
+}";
+        let mut interpreter = run(source).unwrap();
+        assert_eq!(variable(&mut interpreter, "n"), Value::Number(3.0));
+
+        // A loop which continues still breaks out of itself, rather than only of the inner loop
+        // running its body.
+        let source = "var n = 0;
+for (var i = 0; i < 10; i = i + 1) {
+    if (i == 1) continue;
+    if (i == 4) break;
+    n = n + i;
+}";
+        let mut interpreter = run(source).unwrap();
+        assert_eq!(variable(&mut interpreter, "n"), Value::Number(5.0));
+
+        let mut interpreter = run("var a; if (nil) a = 1; else a = 2;").unwrap();
+        assert_eq!(variable(&mut interpreter, "a"), Value::Number(2.0));
+    }
+
+    #[test]
+    fn test_stops_at_first_error() {
+        let source = "var a = 1;\nprint a;\na = a + nil;\nprint a;";
+        let mut interpreter = Interpreter::with_output(Vec::new());
+        let program = Parser::new(Lexer::new(source).tokenize().unwrap())
+            .parse_program()
+            .unwrap();
+
+        assert!(interpreter.interpret(&program).is_err());
+        assert_eq!(variable(&mut interpreter, "a"), Value::Number(1.0));
+        assert_eq!(interpreter.output(), b"1\n");
+    }
+
+    #[test]
+    fn test_functions_unsupported() {
+        assert_eq!(
+            run("fun f() {}").unwrap_err().to_string(),
+            "functions are not supported yet, at line 1, column 5"
+        );
+        assert_eq!(
+            run("print f(1);").unwrap_err().to_string(),
+            "function calls are not supported yet, at line 1, column 10"
+        );
+    }
+
+    #[test]
+    fn test_fuel() {
+        let program = |source: &str| {
+            Parser::new(Lexer::new(source).tokenize().unwrap())
+                .parse_program()
+                .unwrap()
+        };
+
+        let count = program("var i = 0;\nwhile (i < 10) {\n    i = i + 1;\n}");
+        let mut interpreter = Interpreter::with_output(Vec::new()).with_fuel(1000);
+        interpreter.interpret(&count).unwrap();
+        assert_eq!(variable(&mut interpreter, "i"), Value::Number(10.0));
+        // The declaration and the loop, and per iteration, one for the loop and its statement.
+        assert_eq!(interpreter.fuel(), Some(1000 - 2 - 10 * 2));
+
+        // Exactly enough, and one step too few.
+        let mut interpreter = Interpreter::with_output(Vec::new()).with_fuel(22);
+        interpreter.interpret(&count).unwrap();
+        assert_eq!(interpreter.fuel(), Some(0));
+        let mut interpreter = Interpreter::with_output(Vec::new()).with_fuel(21);
+        let error = interpreter.interpret(&count).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "execution limit of 21 steps exceeded at line 3, column 5"
+        );
+        assert_eq!(error.code(), "R0007");
+        assert_eq!(variable(&mut interpreter, "i"), Value::Number(9.0));
+
+        let mut interpreter = Interpreter::with_output(Vec::new()).with_fuel(1000);
+        for source in [
+            "while (true) {}",
+            "for (;;) {}",
+            "while (true) { { print 1; } }",
+        ] {
+            let error = interpreter.interpret(&program(source)).unwrap_err();
+            assert!(
+                matches!(
+                    error,
+                    RuntimeError::ExecutionLimitExceeded { limit: 1000, .. }
+                ),
+                "{:?}",
+                source
+            );
+            // The fuel is used up for good.
+            assert_eq!(interpreter.fuel(), Some(0));
+            interpreter = Interpreter::with_output(Vec::new()).with_fuel(1000);
+        }
+
+        // Statements within nested blocks and branches take fuel, the blocks themselves don't.
+        let nested = program("var a = 0;\n{ { a = 1; if (true) { (a = 2); } } }\na = 3;");
+        let mut interpreter = Interpreter::with_output(Vec::new()).with_fuel(3);
+        let error = interpreter.interpret(&nested).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "execution limit of 3 steps exceeded at line 2, column 25"
+        );
+        assert_eq!(variable(&mut interpreter, "a"), Value::Number(1.0));
+
+        // Unlimited by default.
+        let mut interpreter = Interpreter::with_output(Vec::new());
+        interpreter.interpret(&nested).unwrap();
+        assert_eq!(interpreter.fuel(), None);
+    }
+
+    fn output(source: &str) -> String {
+        String::from_utf8(run(source).unwrap().into_output()).unwrap()
+    }
+
+    #[test]
+    fn test_print() {
+        let source = r#"
+print 1;
+print 2.50;
+print 1 / 4;
+print 0x10 * 2;
+print -0.5 * 2;
+print "a" + 'b';
+print "quoted \"string\"";
+print 1 < 2;
+print !true;
+print nil;
+"#;
+        assert_eq!(
+            output(source),
+            "1\n2.5\n0.25\n32\n-1\nab\nquoted \"string\"\ntrue\nfalse\nnil\n"
+        );
+        assert_eq!(output("var a;"), "");
+    }
+
+    #[test]
+    fn test_fixture_output() {
+        // The fixture averages two variables which it never declares.
+        let source = format!("var min = 1;\nvar max = 9;\n{}", PROGRAM);
+        let mut interpreter = run(&source).unwrap();
+
+        assert_eq!(
+            interpreter.output(),
+            b"Hello , world !\nHello , SPL Prime world !\nno\n1\n2\n3\n4\n5\n6\n7\n8\n9\n"
+        );
+        assert_eq!(variable(&mut interpreter, "average"), Value::Number(5.0));
+        assert_eq!(variable(&mut interpreter, "a"), Value::Number(10.0));
+    }
+
+    #[test]
+    fn test_output_error() {
+        /// Fails to write, once `capacity` bytes were written.
+        struct Full {
+            written: Vec<u8>,
+            capacity: usize,
+        }
+
+        impl Write for Full {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.written.len() + buf.len() > self.capacity {
+                    return Err(io::Error::new(io::ErrorKind::StorageFull, "output is full"));
+                }
+                self.written.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let source = "print 1;\nprint 2;\nprint 3;";
+        let program = Parser::new(Lexer::new(source).tokenize().unwrap())
+            .parse_program()
+            .unwrap();
+        let mut interpreter = Interpreter::with_output(Full {
+            written: Vec::new(),
+            capacity: 4,
+        });
+
+        assert_eq!(
+            interpreter.interpret(&program).unwrap_err().to_string(),
+            "failed to write output: output is full at line 3, column 1"
+        );
+        assert_eq!(interpreter.output().written, b"1\n2\n");
+    }
+
+    #[test]
+    fn test_display() {
+        let cases = [
+            (Value::Number(3.0), "3"),
+            (Value::Number(-0.5), "-0.5"),
+            (Value::Str("a \"b\"".to_string()), "a \"b\""),
+            (Value::Bool(false), "false"),
+            (Value::Nil, "nil"),
+        ];
+
+        for (value, expected) in cases {
+            assert_eq!(value.to_string(), expected);
+        }
+    }
+}
@@ -0,0 +1,510 @@
+//! Serialization of token streams to and from JSON.
+//!
+//! Tokens are represented as an array of objects, such as
+//! `[{"type": "Var", "lexeme": "var", "line": 1, "column": 1, "end_line": 1, "end_column": 3,
+//! "start": 0, "end": 3}]`, where `line` and `column` are the start position of the token, and
+//! `start` and `end` its byte offsets. The serializer and parser are hand-rolled, and only support
+//! as much of JSON as needed for this.
+
+use std::{borrow::Cow, fmt::Display, iter::Peekable, str::CharIndices};
+
+use crate::{
+    position::{Position, Span},
+    token::{Literal, Token, TokenType},
+};
+
+/// Errors returned when parsing tokens from JSON.
+#[derive(Debug, PartialEq, Eq)]
+pub enum JsonError {
+    /// Returned when the input ended in the middle of a value.
+    UnexpectedEnd,
+
+    /// Returned when an unexpected character was encountered. The offset is a byte offset into
+    /// the input.
+    UnexpectedChar { offset: usize, c: char },
+
+    /// Returned when a string contained an invalid escape sequence.
+    InvalidEscape { offset: usize },
+
+    /// Returned when a number was not a valid non-negative integer.
+    InvalidNumber { offset: usize },
+
+    /// Returned when a token object lacked one of its fields.
+    MissingField { field: &'static str },
+
+    /// Returned when a token object had a field of the wrong type.
+    InvalidField { field: String },
+
+    /// Returned when a token type was not known.
+    UnknownTokenType { name: String },
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonError::UnexpectedEnd => write!(f, "Unexpected end of JSON input"),
+            JsonError::UnexpectedChar { offset, c } => {
+                write!(f, "Unexpected char `{}` at offset {}", c, offset)
+            }
+            JsonError::InvalidEscape { offset } => {
+                write!(f, "Invalid escape sequence at offset {}", offset)
+            }
+            JsonError::InvalidNumber { offset } => {
+                write!(f, "Invalid number at offset {}", offset)
+            }
+            JsonError::MissingField { field } => write!(f, "Missing field `{}`", field),
+            JsonError::InvalidField { field } => write!(f, "Field `{}` has the wrong type", field),
+            JsonError::UnknownTokenType { name } => write!(f, "Unknown token type `{}`", name),
+        }
+    }
+}
+
+/// Serialize `tokens` to a JSON array of token objects.
+///
+/// Interned symbols are not serialized, as they are only meaningful for the lexer which produced
+/// them.
+pub fn to_json(tokens: &[Token]) -> String {
+    let mut out = String::from("[");
+
+    for (i, token) in tokens.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+
+        out.push_str("{\"type\":");
+        push_string(&mut out, &token.token_type.to_string());
+        out.push_str(",\"lexeme\":");
+        push_string(&mut out, &token.lexeme);
+        out.push_str(&format!(
+            ",\"line\":{},\"column\":{},\"end_line\":{},\"end_column\":{},\"start\":{},\"end\":{}}}",
+            token.start.line,
+            token.start.column,
+            token.end.line,
+            token.end.column,
+            token.span.start,
+            token.span.end
+        ));
+    }
+
+    out.push(']');
+    out
+}
+
+/// Parse tokens from a JSON array of token objects, as produced by `to_json`.
+///
+/// Fields other than the ones of tokens are ignored, as long as their values are strings or
+/// numbers.
+pub fn tokens_from_json(json: &str) -> Result<Vec<Token<'static>>, JsonError> {
+    let mut parser = Parser {
+        chars: json.char_indices().peekable(),
+    };
+
+    let tokens = parser.tokens()?;
+
+    parser.skip_whitespace();
+    match parser.chars.next() {
+        Some((offset, c)) => Err(JsonError::UnexpectedChar { offset, c }),
+        None => Ok(tokens),
+    }
+}
+
+/// Append `s` as a JSON string literal to `out`.
+pub(crate) fn push_string(out: &mut String, s: &str) {
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
+
+/// Value of a field of a token object.
+enum Value {
+    String(String),
+    Number(usize),
+}
+
+struct Parser<'a> {
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self
+            .chars
+            .next_if(|(_, c)| matches!(c, ' ' | '\t' | '\n' | '\r'))
+            .is_some()
+        {}
+    }
+
+    /// Consume the next non-whitespace character, which must be `expected`.
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        self.skip_whitespace();
+
+        match self.chars.next() {
+            Some((_, c)) if c == expected => Ok(()),
+            Some((offset, c)) => Err(JsonError::UnexpectedChar { offset, c }),
+            None => Err(JsonError::UnexpectedEnd),
+        }
+    }
+
+    /// Consume the next non-whitespace character if it is `expected`.
+    fn consume_if(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        self.chars.next_if(|(_, c)| *c == expected).is_some()
+    }
+
+    fn tokens(&mut self) -> Result<Vec<Token<'static>>, JsonError> {
+        let mut tokens = Vec::new();
+
+        self.expect('[')?;
+        if self.consume_if(']') {
+            return Ok(tokens);
+        }
+
+        loop {
+            tokens.push(self.token()?);
+
+            if !self.consume_if(',') {
+                self.expect(']')?;
+                return Ok(tokens);
+            }
+        }
+    }
+
+    fn token(&mut self) -> Result<Token<'static>, JsonError> {
+        let mut token_type = None;
+        let mut lexeme = None;
+        let mut line = None;
+        let mut column = None;
+        let mut end_line = None;
+        let mut end_column = None;
+        let mut start = None;
+        let mut end = None;
+
+        self.expect('{')?;
+        if !self.consume_if('}') {
+            loop {
+                self.skip_whitespace();
+                let key = self.string()?;
+                self.expect(':')?;
+                let value = self.value()?;
+
+                match (key.as_str(), value) {
+                    ("type", Value::String(name)) => {
+                        token_type = Some(
+                            TokenType::from_name(&name)
+                                .ok_or(JsonError::UnknownTokenType { name })?,
+                        )
+                    }
+                    ("lexeme", Value::String(s)) => lexeme = Some(s),
+                    ("line", Value::Number(n)) => line = Some(n),
+                    ("column", Value::Number(n)) => column = Some(n),
+                    ("end_line", Value::Number(n)) => end_line = Some(n),
+                    ("end_column", Value::Number(n)) => end_column = Some(n),
+                    ("start", Value::Number(n)) => start = Some(n),
+                    ("end", Value::Number(n)) => end = Some(n),
+                    (
+                        "type" | "lexeme" | "line" | "column" | "end_line" | "end_column" | "start"
+                        | "end",
+                        _,
+                    ) => {
+                        return Err(JsonError::InvalidField { field: key });
+                    }
+                    // Unknown fields are ignored.
+                    _ => {}
+                }
+
+                if !self.consume_if(',') {
+                    self.expect('}')?;
+                    break;
+                }
+            }
+        }
+
+        let token_type = token_type.ok_or(JsonError::MissingField { field: "type" })?;
+        let lexeme = lexeme.ok_or(JsonError::MissingField { field: "lexeme" })?;
+        // Literal values are not serialized, but determined from the lexeme like the lexer does.
+        let literal = Literal::from_lexeme(token_type, &lexeme);
+        if token_type == TokenType::Number && literal.is_none() {
+            return Err(JsonError::InvalidField {
+                field: "lexeme".to_string(),
+            });
+        }
+
+        Ok(Token {
+            token_type,
+            lexeme: Cow::Owned(lexeme),
+            start: Position {
+                line: line.ok_or(JsonError::MissingField { field: "line" })?,
+                column: column.ok_or(JsonError::MissingField { field: "column" })?,
+            },
+            end: Position {
+                line: end_line.ok_or(JsonError::MissingField { field: "end_line" })?,
+                column: end_column.ok_or(JsonError::MissingField {
+                    field: "end_column",
+                })?,
+            },
+            span: Span {
+                start: start.ok_or(JsonError::MissingField { field: "start" })?,
+                end: end.ok_or(JsonError::MissingField { field: "end" })?,
+            },
+            symbol: None,
+            literal,
+        })
+    }
+
+    fn value(&mut self) -> Result<Value, JsonError> {
+        self.skip_whitespace();
+
+        match self.chars.peek() {
+            Some((_, '"')) => Ok(Value::String(self.string()?)),
+            Some((_, c)) if c.is_ascii_digit() => Ok(Value::Number(self.number()?)),
+            Some((offset, c)) => Err(JsonError::UnexpectedChar {
+                offset: *offset,
+                c: *c,
+            }),
+            None => Err(JsonError::UnexpectedEnd),
+        }
+    }
+
+    /// Parse a non-negative integer.
+    fn number(&mut self) -> Result<usize, JsonError> {
+        let offset = self.chars.peek().map_or(0, |(offset, _)| *offset);
+        let mut n: usize = 0;
+
+        while let Some((_, c)) = self.chars.next_if(|(_, c)| c.is_ascii_digit()) {
+            n = n
+                .checked_mul(10)
+                .and_then(|n| n.checked_add(c as usize - '0' as usize))
+                .ok_or(JsonError::InvalidNumber { offset })?;
+        }
+
+        // Fractions and exponents are valid JSON, but no valid positions.
+        match self.chars.peek() {
+            Some((_, '.' | 'e' | 'E')) => Err(JsonError::InvalidNumber { offset }),
+            _ => Ok(n),
+        }
+    }
+
+    /// Parse a string literal, including its quotes.
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect('"')?;
+        let mut out = String::new();
+
+        loop {
+            match self.chars.next() {
+                Some((_, '"')) => return Ok(out),
+                Some((offset, '\\')) => {
+                    let c = match self.chars.next() {
+                        Some((_, '"')) => '"',
+                        Some((_, '\\')) => '\\',
+                        Some((_, '/')) => '/',
+                        Some((_, 'b')) => '\u{8}',
+                        Some((_, 'f')) => '\u{c}',
+                        Some((_, 'n')) => '\n',
+                        Some((_, 'r')) => '\r',
+                        Some((_, 't')) => '\t',
+                        Some((_, 'u')) => self.unicode_escape(offset)?,
+                        Some(_) => return Err(JsonError::InvalidEscape { offset }),
+                        None => return Err(JsonError::UnexpectedEnd),
+                    };
+                    out.push(c);
+                }
+                Some((_, c)) => out.push(c),
+                None => return Err(JsonError::UnexpectedEnd),
+            }
+        }
+    }
+
+    /// Parse the code point of a `\u` escape sequence, whose `\u` was already consumed. Code
+    /// points outside of the basic multilingual plane are encoded as a surrogate pair.
+    fn unicode_escape(&mut self, offset: usize) -> Result<char, JsonError> {
+        let high = self.hex4(offset)?;
+
+        let code_point = if (0xd800..0xdc00).contains(&high) {
+            if !(self.chars.next_if(|(_, c)| *c == '\\').is_some()
+                && self.chars.next_if(|(_, c)| *c == 'u').is_some())
+            {
+                return Err(JsonError::InvalidEscape { offset });
+            }
+
+            let low = self.hex4(offset)?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(JsonError::InvalidEscape { offset });
+            }
+
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+
+        char::from_u32(code_point).ok_or(JsonError::InvalidEscape { offset })
+    }
+
+    /// Parse four hexadecimal digits.
+    fn hex4(&mut self, offset: usize) -> Result<u32, JsonError> {
+        let mut value = 0;
+
+        for _ in 0..4 {
+            let digit = self
+                .chars
+                .next()
+                .and_then(|(_, c)| c.to_digit(16))
+                .ok_or(JsonError::InvalidEscape { offset })?;
+            value = value * 16 + digit;
+        }
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    #[test]
+    fn test_to_json() {
+        let tokens = Lexer::new("print \"a\\\"b\";").tokenize().unwrap();
+        assert_eq!(
+            to_json(&tokens),
+            concat!(
+                r#"[{"type":"Print","lexeme":"print","line":1,"column":1,"#,
+                r#""end_line":1,"end_column":5,"start":0,"end":5},"#,
+                r#"{"type":"String","lexeme":"a\"b","line":1,"column":7,"#,
+                r#""end_line":1,"end_column":12,"start":6,"end":12},"#,
+                r#"{"type":"Semicolon","lexeme":";","line":1,"column":13,"#,
+                r#""end_line":1,"end_column":13,"start":12,"end":13},"#,
+                r#"{"type":"EndOfFile","lexeme":"","line":1,"column":14,"#,
+                r#""end_line":1,"end_column":14,"start":13,"end":13}]"#,
+            )
+        );
+
+        assert_eq!(to_json(&[]), "[]");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let source = "var s = \"say \\\"hi\\\"\\n\\tand \\\\ leave\";\nprint s; // done\n";
+        let tokens = Lexer::new(source).with_trivia(true).tokenize().unwrap();
+
+        let parsed = tokens_from_json(&to_json(&tokens)).unwrap();
+        assert_eq!(parsed.len(), tokens.len());
+        for (parsed, token) in parsed.iter().zip(&tokens) {
+            assert_eq!(parsed.token_type, token.token_type);
+            assert_eq!(parsed.lexeme, token.lexeme);
+            assert_eq!((parsed.start, parsed.end), (token.start, token.end));
+            assert_eq!(parsed.span, token.span);
+        }
+        assert_eq!(parsed[3].lexeme, "say \"hi\"\n\tand \\ leave");
+    }
+
+    #[test]
+    fn test_control_characters() {
+        let tokens = tokens_from_json(&to_json(&[Token {
+            token_type: TokenType::String,
+            lexeme: "\u{0}\u{1b}\r".into(),
+            start: Position { line: 1, column: 1 },
+            end: Position { line: 1, column: 5 },
+            span: Span { start: 0, end: 5 },
+            symbol: None,
+            literal: None,
+        }]))
+        .unwrap();
+        assert_eq!(tokens[0].lexeme, "\u{0}\u{1b}\r");
+    }
+
+    #[test]
+    fn test_tokens_from_json() {
+        // Whitespace, unknown fields, and escapes which `to_json` doesn't produce.
+        let json = r#" [ { "lexeme" : "ä😀\/", "type": "Identifier", "note": "x",
+            "line": 2, "column": 3, "end_line": 2, "end_column": 5, "start": 10, "end": 17 } ] "#;
+        let tokens = tokens_from_json(json).unwrap();
+        assert_eq!(
+            tokens,
+            vec![Token {
+                token_type: TokenType::Identifier,
+                lexeme: "ä😀/".into(),
+                start: Position { line: 2, column: 3 },
+                end: Position { line: 2, column: 5 },
+                span: Span { start: 10, end: 17 },
+                symbol: None,
+                literal: None,
+            }]
+        );
+
+        assert_eq!(tokens_from_json("[]"), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_malformed_json() {
+        let valid = concat!(
+            r#"{"type":"Plus","lexeme":"+","line":1,"column":1,"#,
+            r#""end_line":1,"end_column":1,"start":0,"end":1}"#
+        );
+
+        assert_eq!(tokens_from_json(""), Err(JsonError::UnexpectedEnd));
+        assert_eq!(
+            tokens_from_json(&format!("[{}", valid)),
+            Err(JsonError::UnexpectedEnd)
+        );
+        assert_eq!(
+            tokens_from_json(&format!("[{}] x", valid)),
+            Err(JsonError::UnexpectedChar {
+                offset: valid.len() + 3,
+                c: 'x'
+            })
+        );
+        assert_eq!(
+            tokens_from_json(&format!("[{},]", valid)),
+            Err(JsonError::UnexpectedChar {
+                offset: valid.len() + 2,
+                c: ']'
+            })
+        );
+        assert_eq!(
+            tokens_from_json(&format!("[{}]", valid.replace("Plus", "Pluss"))),
+            Err(JsonError::UnknownTokenType {
+                name: "Pluss".to_string()
+            })
+        );
+        assert_eq!(
+            tokens_from_json(&format!("[{}]", valid.replace(",\"end\":1", ""))),
+            Err(JsonError::MissingField { field: "end" })
+        );
+        assert_eq!(
+            tokens_from_json(&format!(
+                "[{}]",
+                valid.replace("\"line\":1", "\"line\":\"1\"")
+            )),
+            Err(JsonError::InvalidField {
+                field: "line".to_string()
+            })
+        );
+        assert_eq!(
+            tokens_from_json(&format!(
+                "[{}]",
+                valid.replace("\"line\":1", "\"line\":1.5")
+            )),
+            Err(JsonError::InvalidNumber { offset: 36 })
+        );
+        assert_eq!(
+            tokens_from_json(r#"[{"type":"\x"}]"#),
+            Err(JsonError::InvalidEscape { offset: 10 })
+        );
+        assert_eq!(
+            tokens_from_json(r#"[{"type":"\ud83d"}]"#),
+            Err(JsonError::InvalidEscape { offset: 10 })
+        );
+    }
+}
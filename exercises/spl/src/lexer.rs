@@ -1,425 +1,1449 @@
-use std::{iter::Peekable, str::Chars};
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    mem,
+    path::Path,
+};
 
 use crate::{
-    error::{LexerError, Position},
-    token::{Token, TokenType},
+    char_class,
+    error::{Diagnostic, LexerError, Position, Span, TRAILING_DOT_WARNING},
+    interner::StringPool,
+    token::{self, Literal, Token, TokenType},
 };
 
+/// Outcome of advancing until a given character.
+#[derive(Debug, PartialEq, Eq)]
+enum AdvanceUntil<'a> {
+    /// The character was found, and consumed. Holds the slice of the source before it.
+    Found(&'a str),
+    /// The input ended before the character was found. Holds the slice of the source up to the
+    /// end of the input.
+    EndOfInput(&'a str),
+}
+
+/// Whether the underscores in a run of digits only separate digits, rather than being at its
+/// start or end, or doubled.
+fn is_well_separated(digits: &str) -> bool {
+    !digits.starts_with('_') && !digits.ends_with('_') && !digits.contains("__")
+}
+
+/// Options for the dialect of SPL which the lexer accepts.
+///
+/// The default is the standard dialect, as lexed by `Lexer::new`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LexerOptions {
+    /// Whether identifiers may contain any Unicode letters and numbers, rather than only ASCII
+    /// ones. Other characters are unexpected characters.
+    pub unicode_identifiers: bool,
+    /// Whether block comments nest, so that `/* a /* b */ c */` is a single comment. If not, the
+    /// first `*/` closes the comment.
+    pub nested_block_comments: bool,
+    /// Whether keywords are recognized regardless of their case, so that `WHILE` is the same as
+    /// `while`.
+    pub case_insensitive_keywords: bool,
+    /// Number of errors after which the lexer stops scanning, and reports a final
+    /// `LexerError::TooManyErrors` instead, so that e.g. binary input doesn't produce a flood of
+    /// errors. None for no limit.
+    pub max_errors: Option<usize>,
+    /// Whether the lexer stops scanning at the first error, without reporting any further errors
+    /// or tokens.
+    pub fail_fast: bool,
+}
+
+impl Default for LexerOptions {
+    fn default() -> Self {
+        LexerOptions {
+            unicode_identifiers: true,
+            nested_block_comments: true,
+            case_insensitive_keywords: false,
+            max_errors: Some(100),
+            fail_fast: false,
+        }
+    }
+}
+
+impl LexerOptions {
+    /// Whether `c` may start an identifier, see `char_class::is_ident_start`.
+    fn is_ident_start(&self, c: char) -> bool {
+        char_class::is_ident_start(c) && (self.unicode_identifiers || c.is_ascii())
+    }
+
+    /// Whether `c` may continue an identifier, see `char_class::is_ident_continue`.
+    fn is_ident_continue(&self, c: char) -> bool {
+        char_class::is_ident_continue(c) && (self.unicode_identifiers || c.is_ascii())
+    }
+
+    /// Whether `c` may start a token, or is whitespace. All other characters are unexpected.
+    fn may_start_token(&self, c: char) -> bool {
+        // Must be kept in sync with the characters `Lexer::scan` handles.
+        const PUNCTUATION: &str = "+-*/%=><!;,.(){}[]?:\"'";
+
+        PUNCTUATION.contains(c)
+            || char_class::is_spl_whitespace(c)
+            || char_class::is_decimal_digit(c)
+            || self.is_ident_start(c)
+    }
+}
+
+/// Character which the escape sequence `\c` stands for in string and char literals, if it is a
+/// known one.
+fn decode_escape(c: char) -> Option<char> {
+    match c {
+        '"' => Some('"'),
+        '\'' => Some('\''),
+        '\\' => Some('\\'),
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        _ => None,
+    }
+}
+
+/// Everything lexing a whole input produced, see `Lexer::tokenize_reader`.
+#[derive(Debug)]
+pub struct Tokenized {
+    /// The tokens, or the errors if there were any, as returned by `Lexer::tokenize`.
+    pub result: Result<Vec<Token<'static>>, Vec<LexerError>>,
+    /// Warnings about the input, as returned by `Lexer::warnings`.
+    pub warnings: Vec<Diagnostic>,
+    /// Pool of the identifiers, which the `symbol` of identifier tokens refers to.
+    pub symbols: StringPool,
+}
+
 pub struct Lexer<'a> {
-    chars: Peekable<Chars<'a>>,
+    source: &'a str,
+    options: LexerOptions,
+    // Byte offset of the next character in the source.
+    offset: usize,
     line: usize,
     column: usize,
+    // Tokens and errors which were scanned, but not yet returned by the iterator.
+    pending: VecDeque<Result<Token<'a>, LexerError>>,
+    // Whether the end of the input, and with it the EOF token, was reached.
+    finished: bool,
+    // Whether to emit comments as tokens.
+    trivia: bool,
+    // Names of the identifiers encountered so far.
+    symbols: StringPool,
+    // Whether only token types and spans are of interest, see `scan_kinds`.
+    kinds_only: bool,
+    // Warnings encountered so far, which unlike errors don't fail tokenization.
+    warnings: Vec<Diagnostic>,
+    // Number of errors encountered so far, see `LexerOptions::max_errors`.
+    error_count: usize,
+    // Unknown escape sequences of the string or char literal being scanned, which are reported
+    // after the literal itself, so that items stay in the order of the input.
+    escape_errors: Vec<LexerError>,
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(source: &str) -> Lexer {
+    pub fn new(source: &'a str) -> Lexer<'a> {
+        Lexer::with_options(source, LexerOptions::default())
+    }
+
+    /// Create a lexer for a dialect of SPL, as configured by `options`.
+    pub fn with_options(source: &'a str, options: LexerOptions) -> Lexer<'a> {
         Lexer {
-            chars: source.chars().peekable(),
+            source,
+            options,
+            offset: 0,
             line: 1,
             column: 0,
+            pending: VecDeque::new(),
+            finished: false,
+            trivia: false,
+            symbols: StringPool::new(),
+            kinds_only: false,
+            warnings: Vec::new(),
+            error_count: 0,
+            escape_errors: Vec::new(),
+        }
+    }
+
+    /// Configure whether comments are emitted as `TokenType::Comment` tokens, rather than being
+    /// discarded.
+    ///
+    /// Comment tokens carry the full text of the comment, including its delimiters. They are off
+    /// by default, as the parser has no use for them.
+    pub fn with_trivia(mut self, trivia: bool) -> Lexer<'a> {
+        self.trivia = trivia;
+        self
+    }
+
+    /// Reset the lexer to the start of a new source, as if freshly constructed. Its options are
+    /// retained.
+    pub fn reset(&mut self, source: &'a str) {
+        self.source = source;
+        self.offset = 0;
+        self.line = 1;
+        self.column = 0;
+        self.pending.clear();
+        self.finished = false;
+        self.symbols.clear();
+        self.warnings.clear();
+        self.error_count = 0;
+        self.escape_errors.clear();
+    }
+
+    /// Pool of the identifiers encountered so far, which the `symbol` of identifier tokens refers
+    /// to.
+    pub fn symbols(&self) -> &StringPool {
+        &self.symbols
+    }
+
+    /// Warnings about the input scanned so far.
+    ///
+    /// Warnings don't fail tokenization, so they are reported here rather than alongside errors.
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    /// Value of a string or char literal with the given decoded content.
+    fn text_literal(&self, content: &str) -> Option<Literal> {
+        // When only scanning for token types, the value is of no interest.
+        if self.kinds_only {
+            None
+        } else {
+            Some(Literal::Str(content.to_string()))
+        }
+    }
+
+    /// Position of the character which was consumed last, see `advance`.
+    fn current_position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    /// Add a token to the items which are yet to be returned by the iterator.
+    fn push_token(&mut self, token: Token<'a>) {
+        // After bailing out due to errors, the remainder of the lexeme being scanned is dropped.
+        if !self.finished {
+            self.pending.push_back(Ok(token));
+        }
+    }
+
+    /// Add an error to the items which are yet to be returned by the iterator.
+    ///
+    /// Stops scanning if the error limit is reached, or at the first error in fail-fast mode.
+    fn push_error(&mut self, error: LexerError) {
+        if self.finished {
+            return;
+        }
+
+        self.pending.push_back(Err(error));
+        self.error_count += 1;
+
+        if self.options.fail_fast {
+            self.finished = true;
+        } else if self
+            .options
+            .max_errors
+            .is_some_and(|max| self.error_count >= max)
+        {
+            self.pending.push_back(Err(LexerError::TooManyErrors {
+                reported: self.error_count,
+                position: self.current_position(),
+                span: self.span_from(self.offset),
+            }));
+            self.finished = true;
+        }
+    }
+
+    /// Report the unknown escape sequences of the literal which was just scanned, see
+    /// `escape_errors`.
+    fn report_escape_errors(&mut self) {
+        for error in mem::take(&mut self.escape_errors) {
+            self.push_error(error);
         }
     }
 
     /// Peek at the next character without advancing the position in the input.
     ///
     /// Returns None if the end of the input is reached.
-    fn peek(&mut self) -> Option<&char> {
-        self.chars.peek()
+    fn peek(&self) -> Option<char> {
+        self.source[self.offset..].chars().next()
+    }
+
+    /// Peek at the character after the next one without advancing the position in the input.
+    ///
+    /// Returns None if the end of the input is reached before it.
+    fn peek_next(&self) -> Option<char> {
+        self.source[self.offset..].chars().nth(1)
+    }
+
+    /// Slice of the source from the byte offset `start` up to the current position.
+    fn slice_from(&self, start: usize) -> &'a str {
+        &self.source[start..self.offset]
+    }
+
+    /// Span from the byte offset `start` up to the current position.
+    fn span_from(&self, start: usize) -> Span {
+        Span {
+            start,
+            end: self.offset,
+        }
     }
 
     /// Advance by one character, returning it.
     ///
-    /// Returns None if the end of the input is reached.
+    /// This is the only place where the position in the input is updated. Afterwards, `column`
+    /// is the 1-based column of the character which was consumed - or 0 if it was a line break,
+    /// in which case `line` already refers to the following line.
+    ///
+    /// Returns None, without changing the position, if the end of the input is reached.
     fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
         self.column += 1;
 
-        let next = self.chars.next();
-
-        if next.is_some() {
-            if next.unwrap() == '\n' {
-                self.line += 1;
-                self.column = 0;
-            }
+        // Line breaks are either `\n`, `\r\n`, or a lone `\r`. Of a `\r\n` pair, only the `\n` is
+        // counted.
+        let line_break = match c {
+            '\n' => true,
+            '\r' => self.peek() != Some('\n'),
+            _ => false,
+        };
+        if line_break {
+            self.line += 1;
+            self.column = 0;
         }
 
-        next
+        Some(c)
     }
 
     /// Advance if the next character is equal to `expected`.
     fn advance_if_equal(&mut self, expected: char) -> bool {
-        match self.peek() {
-            None => return false,
-            Some(c) => {
-                if *c == expected {
-                    self.chars.next();
-                    self.column += 1;
-                    true
-                } else {
-                    false
-                }
-            }
+        if self.peek() == Some(expected) {
+            self.advance();
+            true
+        } else {
+            false
         }
     }
 
-    /// Advance until the next character is equal to `expected`.
+    /// Advance through and including the next character for which the provided closure evaluates
+    /// to true, or until the end of the input if there is none.
     ///
-    /// Returns a vector of all characters through which the lexer advanced. `expected` is not part
-    /// of the output vector.
-    ///
-    /// Returns an error if the lexer ran out of input before finding a match.
-    fn advance_until_equal(&mut self, expected: char) -> Result<Vec<char>, ()> {
-        let mut out = Vec::new();
-
-        loop {
-            match self.advance() {
-                Some(c) => {
-                    if c == expected {
-                        break;
-                    }
-
-                    out.push(c);
-                }
+    /// Which of the two happened is up to the caller to interpret, see `AdvanceUntil`. The end of
+    /// the input itself is never advanced past.
+    fn advance_until_matching<F>(&mut self, f: F) -> AdvanceUntil<'a>
+    where
+        F: Fn(char) -> bool,
+    {
+        let start = self.offset;
 
-                // We reached the end of the input without ifnding our expected character.
-                None => return Err(()),
+        while let Some(c) = self.advance() {
+            if f(c) {
+                return AdvanceUntil::Found(&self.source[start..self.offset - c.len_utf8()]);
             }
         }
 
-        return Ok(out);
+        AdvanceUntil::EndOfInput(self.slice_from(start))
     }
 
     /// Advance as long as the provided closure evaluates to true for the next character.
     ///
-    /// Returns a vector of all characters through which the lexer advanced.
-    fn advance_while_matching<F>(&mut self, f: F) -> Vec<char>
+    /// Returns the slice of the source through which the lexer advanced.
+    fn advance_while_matching<F>(&mut self, f: F) -> &'a str
     where
         F: Fn(char) -> bool,
     {
-        let mut out = Vec::new();
+        let start = self.offset;
 
         while let Some(c) = self.peek() {
-            if !f(*c) {
+            if !f(c) {
                 break;
             }
 
-            // We know that something is there as peek() returned Some, so we unwrap.
-            out.push(self.advance().unwrap());
+            self.advance();
         }
 
-        return out;
+        self.slice_from(start)
     }
 
-    pub fn tokenize(&mut self) -> Result<Vec<Token>, Vec<LexerError>> {
-        let mut errors: Vec<LexerError> = Vec::new();
+    /// Advance past the end of a number literal, whose first digit `first` was already consumed.
+    ///
+    /// Numbers are either decimal, with an optional fractional part and exponent (`6.02e23`), or
+    /// integers in hexadecimal (`0x1F`) or binary (`0b1010`) notation. Digits may be separated by
+    /// underscores (`1_000_000`), see `is_well_separated`.
+    ///
+    /// Returns an error if the literal is malformed. The lexer still advances past all of it, so
+    /// that lexing resumes after the literal.
+    fn advance_number(&mut self, first: char) -> Result<(), ()> {
+        let start = self.offset - first.len_utf8();
+
+        if first == '0' {
+            let radix = match self.peek() {
+                Some('x') => Some(16),
+                Some('b') => Some(2),
+                _ => None,
+            };
+
+            if let Some(radix) = radix {
+                // Consume the prefix.
+                self.advance();
+
+                let digits = self.advance_while_matching(|c| {
+                    c == '_' || char_class::digit_value(c, radix).is_some()
+                });
+                if digits.is_empty() {
+                    // Whatever follows the prefix, such as the `2` of `0b2`, was presumably meant
+                    // to be part of the literal.
+                    self.advance_while_matching(char_class::is_ident_continue);
+                    return Err(());
+                }
 
-        let mut tokens = Vec::new();
+                return if is_well_separated(digits) {
+                    Ok(())
+                } else {
+                    Err(())
+                };
+            }
+        }
 
-        while let Some(c) = self.advance() {
-            match c {
-                '+' => tokens.push(Token {
-                    token_type: TokenType::Plus,
-                    lexeme: "+".into(),
-                    line: self.line,
-                }),
+        let is_digit = |c| c == '_' || char_class::is_decimal_digit(c);
+        // Rather than bailing at the first problem, we keep going to advance past the whole
+        // literal.
+        let mut well_formed = true;
+
+        // Consume all digits before the decimal point.
+        self.advance_while_matching(is_digit);
+        well_formed &= is_well_separated(self.slice_from(start));
+
+        // Consume decimal digits if present
+        if self.at_decimal_point() {
+            self.advance();
+            let fraction = self.advance_while_matching(is_digit);
+            well_formed &= is_well_separated(fraction);
+
+            // Any further decimal point, as in `1.2.3`, is an error. The digits following it are
+            // still consumed, so that the whole literal is reported at once.
+            while self.at_decimal_point() {
+                self.advance();
+                self.advance_while_matching(is_digit);
+                well_formed = false;
+            }
+        }
 
-                '-' => tokens.push(Token {
-                    token_type: TokenType::Minus,
-                    lexeme: "-".into(),
-                    line: self.line,
-                }),
+        // Consume exponent if present. It must not be empty.
+        if self.advance_if_equal('e') || self.advance_if_equal('E') {
+            if !self.advance_if_equal('+') {
+                self.advance_if_equal('-');
+            }
 
-                '*' => tokens.push(Token {
-                    token_type: TokenType::Times,
-                    lexeme: "*".into(),
-                    line: self.line,
-                }),
+            let exponent = self.advance_while_matching(is_digit);
+            well_formed &= !exponent.is_empty() && is_well_separated(exponent);
+        }
 
-                '/' => {
-                    if self.advance_if_equal('/') {
-                        // Line comment
-                        let _ = self.advance_until_equal('\n');
-                    } else {
-                        // Divides operator
-                        tokens.push(Token {
-                            token_type: TokenType::Divide,
-                            lexeme: "/".into(),
-                            line: self.line,
-                        });
-                    }
-                }
+        if well_formed {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
 
-                '=' => {
-                    if self.advance_if_equal('=') {
-                        tokens.push(Token {
-                            token_type: TokenType::DoubleEquals,
-                            lexeme: "==".into(),
-                            line: self.line,
-                        });
-                    } else {
-                        tokens.push(Token {
-                            token_type: TokenType::Equals,
-                            lexeme: "=".into(),
-                            line: self.line,
-                        });
-                    }
-                }
+    /// Whether the next character is a decimal point which belongs to a number literal.
+    ///
+    /// That is only the case if a digit follows it. Otherwise, as in `1.foo` or `1..2`, the dot is
+    /// a token of its own.
+    fn at_decimal_point(&self) -> bool {
+        self.peek() == Some('.') && self.peek_next().is_some_and(char_class::is_decimal_digit)
+    }
 
-                '>' => {
-                    if self.advance_if_equal('=') {
-                        tokens.push(Token {
-                            token_type: TokenType::GreaterOrEqual,
-                            lexeme: ">=".into(),
-                            line: self.line,
-                        });
-                    } else {
-                        tokens.push(Token {
-                            token_type: TokenType::Greater,
-                            lexeme: ">".into(),
-                            line: self.line,
-                        });
-                    }
+    /// Advance past the end of a block comment, whose opening `/*` was already consumed.
+    ///
+    /// Unless disabled in the options, block comments nest, so `/* a /* b */ c */` is a single
+    /// comment.
+    ///
+    /// Returns an error if the lexer ran out of input before the comment was closed.
+    fn skip_block_comment(&mut self) -> Result<(), ()> {
+        let nested = self.options.nested_block_comments;
+        let mut depth = 1;
+
+        while depth > 0 {
+            match self.advance() {
+                Some('/') if nested && self.advance_if_equal('*') => depth += 1,
+                Some('*') if self.advance_if_equal('/') => depth -= 1,
+                Some(_) => {}
+                None => return Err(()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Advance past the end of a string literal, whose opening quote was already consumed.
+    ///
+    /// Returns the content of the string, with escape sequences decoded. Strings may span multiple
+    /// lines. Unknown escape sequences are collected in `escape_errors`, with lexing of the string
+    /// continuing after them.
+    ///
+    /// The content is borrowed from the source, unless decoding escape sequences made a copy
+    /// necessary.
+    ///
+    /// Returns an error if the lexer ran out of input before finding the closing quote.
+    fn advance_string(&mut self) -> Result<Cow<'a, str>, ()> {
+        let start = self.offset;
+        // Only allocated once the first escape sequence is encountered.
+        let mut decoded: Option<String> = None;
+
+        loop {
+            match self.advance() {
+                Some('"') => {
+                    return match decoded {
+                        Some(out) => Ok(Cow::Owned(out)),
+                        None => Ok(Cow::Borrowed(&self.source[start..self.offset - 1])),
+                    };
                 }
+                Some('\\') => {
+                    let position = self.current_position();
+
+                    // Backslashes are a single byte.
+                    let backslash_offset = self.offset - 1;
+                    // When only scanning for token types, the content is of no interest.
+                    if decoded.is_none() && !self.kinds_only {
+                        decoded = Some(self.source[start..backslash_offset].to_string());
+                    }
 
-                '<' => {
-                    if self.advance_if_equal('=') {
-                        tokens.push(Token {
-                            token_type: TokenType::LessOrEqual,
-                            lexeme: "<=".into(),
-                            line: self.line,
-                        });
-                    } else {
-                        tokens.push(Token {
-                            token_type: TokenType::Less,
-                            lexeme: "<".into(),
-                            line: self.line,
-                        });
+                    let c = match self.advance() {
+                        Some(c) => match decode_escape(c) {
+                            Some(c) => c,
+                            None => {
+                                self.escape_errors.push(LexerError::UnknownEscapeSequence {
+                                    position,
+                                    c,
+                                    span: self.span_from(backslash_offset),
+                                });
+                                continue;
+                            }
+                        },
+                        None => return Err(()),
+                    };
+
+                    if let Some(out) = decoded.as_mut() {
+                        out.push(c);
                     }
                 }
-
-                '!' => {
-                    if self.advance_if_equal('=') {
-                        tokens.push(Token {
-                            token_type: TokenType::NotEquals,
-                            lexeme: "!=".into(),
-                            line: self.line,
-                        });
-                    } else {
-                        tokens.push(Token {
-                            token_type: TokenType::BooleanNot,
-                            lexeme: "!".into(),
-                            line: self.line,
-                        });
+                Some(c) => {
+                    if let Some(out) = decoded.as_mut() {
+                        out.push(c);
                     }
                 }
+                None => return Err(()),
+            }
+        }
+    }
 
-                ';' => tokens.push(Token {
-                    token_type: TokenType::Semicolon,
-                    lexeme: ";".into(),
-                    line: self.line,
-                }),
-
-                '(' => tokens.push(Token {
-                    token_type: TokenType::OpeningParentheses,
-                    lexeme: "(".into(),
-                    line: self.line,
-                }),
-                ')' => tokens.push(Token {
-                    token_type: TokenType::ClosingParentheses,
-                    lexeme: ")".into(),
-                    line: self.line,
-                }),
+    /// Advance past the end of a char literal, whose opening quote was already consumed.
+    ///
+    /// Returns the character, with escape sequences decoded like in strings. Unknown escape
+    /// sequences are collected in `escape_errors`.
+    ///
+    /// Returns an error if the literal does not contain exactly one character, or is not closed
+    /// before the end of the line. In the latter case, the line break is not consumed.
+    fn advance_char(&mut self) -> Result<Cow<'a, str>, ()> {
+        let start = self.offset;
+        let mut chars = 0;
+        let mut escaped = None;
 
-                '{' => tokens.push(Token {
-                    token_type: TokenType::OpeningBraces,
-                    lexeme: "{".into(),
-                    line: self.line,
-                }),
-                '}' => tokens.push(Token {
-                    token_type: TokenType::ClosingBraces,
-                    lexeme: "}".into(),
-                    line: self.line,
-                }),
-
-                '"' => {
-                    // We'll keep track of this to be able to provide a better error in case of an
-                    // unterminated string.
-                    let (starts_at_line, starts_at_column) = (self.line, self.column);
-
-                    match self.advance_until_equal('"') {
-                        Ok(chars) => tokens.push(Token {
-                            token_type: TokenType::String,
-                            lexeme: String::from_iter(chars.iter()),
-                            line: self.line,
-                        }),
-                        Err(_) => errors.push(LexerError::UnterminatedStringSequence {
-                            starts_at: Position {
-                                line: starts_at_line,
-                                column: starts_at_column,
-                            },
-                            ends_at: Position {
-                                line: self.line,
-                                column: self.column,
-                            },
-                        }),
+        loop {
+            match self.peek() {
+                None => return Err(()),
+                Some(c) if char_class::is_line_break(c) => return Err(()),
+                Some('\'') => {
+                    self.advance();
+                    break;
+                }
+                Some('\\') => {
+                    let position = Position {
+                        line: self.line,
+                        column: self.column + 1,
+                    };
+                    let backslash_offset = self.offset;
+                    self.advance();
+
+                    match self.advance() {
+                        Some(c) => match decode_escape(c) {
+                            Some(c) => escaped = Some(c),
+                            None => self.escape_errors.push(LexerError::UnknownEscapeSequence {
+                                position,
+                                c,
+                                span: self.span_from(backslash_offset),
+                            }),
+                        },
+                        None => return Err(()),
                     }
                 }
+                Some(_) => {
+                    self.advance();
+                }
+            }
 
-                // advance() handles line and column numbers, there's naught for us to do but
-                // enjoy this fleeting moment of quiet.
-                '\n' => {}
-
-                // Whitespace is silently consumed
-                ' ' | '\t' => {}
-
-                _ => {
-                    if c.is_alphabetic() {
-                        let mut name = String::new();
-                        name.push(c);
-
-                        // Consume all following alphanumeric characters
-                        let additional_chars = self.advance_while_matching(|c| c.is_alphanumeric());
-                        name.extend(additional_chars.iter());
+            chars += 1;
+        }
 
-                        // Keywords take precedence over identifiers
-                        match name.as_str() {
-                            "true" => tokens.push(Token {
-                                token_type: TokenType::True,
-                                lexeme: "true".into(),
-                                line: self.line,
-                            }),
+        if chars != 1 {
+            return Err(());
+        }
 
-                            "false" => tokens.push(Token {
-                                token_type: TokenType::False,
-                                lexeme: "false".into(),
-                                line: self.line,
-                            }),
+        Ok(match escaped {
+            Some(c) => Cow::Owned(c.to_string()),
+            // Without the closing quote.
+            None => Cow::Borrowed(&self.source[start..self.offset - 1]),
+        })
+    }
 
-                            "and" => tokens.push(Token {
-                                token_type: TokenType::And,
-                                lexeme: "and".into(),
-                                line: self.line,
-                            }),
+    /// Tokenize all input of `reader` incrementally, in the dialect configured by `options`,
+    /// producing the same tokens, errors and warnings as a lexer of the whole input would.
+    ///
+    /// The input is read and lexed a line at a time, so that only the current line is held in
+    /// memory. Tokens never span line breaks, except unterminated strings and block comments.
+    /// Lines are therefore added to the current one until none of its items runs up to its end,
+    /// before they are lexed for good. Returns an error if reading fails, or the input is not
+    /// valid UTF-8.
+    pub fn tokenize_reader<R: BufRead>(
+        mut reader: R,
+        options: LexerOptions,
+    ) -> io::Result<Tokenized> {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        // Carried over from the lexer of one chunk to the next, so that symbols refer to the same
+        // pool, and the error limit applies to the whole input.
+        let mut symbols = StringPool::new();
+        let mut error_count = 0;
+
+        // Input yet to be lexed, which starts at the start of a line, as well as the line and
+        // byte offset it starts at.
+        let mut chunk = String::new();
+        let mut line = 1;
+        let mut offset = 0;
+        // Lines to read before lexing the chunk again. Doubled whenever lexing it was premature,
+        // so that a long unterminated comment isn't lexed again for every line of it.
+        let mut lines_to_read = 1;
 
-                            "or" => tokens.push(Token {
-                                token_type: TokenType::Or,
-                                lexeme: "or".into(),
-                                line: self.line,
-                            }),
+        loop {
+            let mut end_of_input = false;
+            for _ in 0..lines_to_read {
+                // Only the last line of the input lacks a trailing `\n`, which the EOF token is
+                // placed after.
+                if reader.read_line(&mut chunk)? == 0 || !chunk.ends_with('\n') {
+                    end_of_input = true;
+                    break;
+                }
+            }
 
-                            "var" => tokens.push(Token {
-                                token_type: TokenType::Var,
-                                lexeme: "var".into(),
-                                line: self.line,
-                            }),
+            let mut lexer = Lexer::with_options(&chunk, options);
+            lexer.symbols = mem::take(&mut symbols);
+            lexer.error_count = error_count;
+            let items: Vec<_> = lexer.by_ref().collect();
+            symbols = mem::take(&mut lexer.symbols);
+
+            // An item running up to the end of the chunk may continue in the lines after it.
+            let is_eof = |item: &Result<Token, LexerError>| matches!(item, Ok(token) if token.is(TokenType::EndOfFile));
+            let runs_to_end = items.iter().any(|item| {
+                let span = match item {
+                    Ok(token) => token.span,
+                    Err(e) => e.span(),
+                };
+                !is_eof(item) && span.end == chunk.len()
+            });
+            if runs_to_end && !end_of_input {
+                lines_to_read *= 2;
+                continue;
+            }
 
-                            "print" => tokens.push(Token {
-                                token_type: TokenType::Print,
-                                lexeme: "print".into(),
-                                line: self.line,
-                            }),
+            error_count = lexer.error_count;
+            warnings.extend(
+                lexer
+                    .warnings
+                    .drain(..)
+                    .map(|warning| shift_warning(warning, line, offset)),
+            );
+            // Without an EOF token, the lexer bailed out due to errors, and so does the whole input.
+            let bailed_out = !items.iter().any(is_eof);
+
+            let lines = lexer.line - 1;
+            for item in items {
+                if is_eof(&item) && !end_of_input {
+                    continue;
+                }
+                match item {
+                    Ok(token) => tokens.push(shift_token(token.into_owned(), line, offset)),
+                    Err(e) => errors.push(shift_error(e, line, offset)),
+                }
+            }
 
-                            "if" => tokens.push(Token {
-                                token_type: TokenType::If,
-                                lexeme: "if".into(),
-                                line: self.line,
-                            }),
+            if end_of_input || bailed_out {
+                break;
+            }
 
-                            "else" => tokens.push(Token {
-                                token_type: TokenType::Else,
-                                lexeme: "else".into(),
-                                line: self.line,
-                            }),
+            line += lines;
+            offset += chunk.len();
+            chunk.clear();
+            lines_to_read = 1;
+        }
 
-                            "while" => tokens.push(Token {
-                                token_type: TokenType::While,
-                                lexeme: "while".into(),
-                                line: self.line,
-                            }),
+        Ok(Tokenized {
+            result: if errors.is_empty() {
+                Ok(tokens)
+            } else {
+                Err(errors)
+            },
+            warnings,
+            symbols,
+        })
+    }
 
-                            _ => {
-                                // An alphanumeric name which doesn't correspond to any
-                                // keyword is an identifier.
-                                tokens.push(Token {
-                                    token_type: TokenType::Identifier,
-                                    lexeme: name,
-                                    line: self.line,
-                                });
-                            }
-                        }
-                    } else if c.is_digit(10) {
-                        let mut number = String::new();
-                        number.push(c);
-
-                        // Consume all digits before the decimal point.
-                        let additional_digits = self.advance_while_matching(|c| c.is_digit(10));
-                        number.extend(additional_digits.iter());
-
-                        // Consume decimal digits if present
-                        if self.advance_if_equal('.') {
-                            number.push('.');
-                            let additional_digits = self.advance_while_matching(|c| c.is_digit(10));
-                            number.extend(additional_digits.iter());
-                        }
+    /// Tokenize the file at `path` incrementally, see `tokenize_reader`.
+    pub fn from_file(path: &Path, options: LexerOptions) -> io::Result<Tokenized> {
+        Lexer::tokenize_reader(BufReader::new(File::open(path)?), options)
+    }
 
-                        tokens.push(Token {
-                            token_type: TokenType::Number,
-                            lexeme: number,
-                            line: self.line,
-                        });
-                    } else {
-                        errors.push(LexerError::UnexpectedChar {
-                            position: Position {
-                                line: self.line,
-                                column: self.column,
-                            },
-                            c,
-                        });
-                    }
-                }
-            }
-        }
+    pub fn tokenize(&mut self) -> Result<Vec<Token<'a>>, Vec<LexerError>> {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
 
-        // Reached end of file, add final token.
-        tokens.push(Token {
-            token_type: TokenType::EndOfile,
-            lexeme: "".into(),
-            line: self.line,
-        });
+        self.tokenize_into(&mut tokens, &mut errors);
 
         if errors.is_empty() {
-            return Ok(tokens);
+            Ok(tokens)
         } else {
-            return Err(errors);
+            Err(errors)
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::token::TokenType;
+    /// Scan the remaining input for the types and spans of its tokens only, as needed e.g. for
+    /// syntax highlighting.
+    ///
+    /// Produces the same token types as `tokenize`, but skips the work which only goes into
+    /// lexemes, such as decoding escape sequences of strings and interning identifiers. Errors are
+    /// skipped as well.
+    pub fn scan_kinds(&mut self) -> Vec<(TokenType, Span)> {
+        self.kinds_only = true;
+        let kinds = self
+            .by_ref()
+            .filter_map(|item| item.ok())
+            .map(|token| (token.token_type, token.span))
+            .collect();
+        self.kinds_only = false;
+
+        kinds
+    }
 
-    use super::*;
+    /// Tokenize the remaining input into caller-provided buffers.
+    ///
+    /// Both buffers are cleared first, so they can be reused across calls - in combination with
+    /// `reset` - to avoid repeated allocations when lexing many inputs. Tokenization succeeded if
+    /// `errors` is empty afterwards.
+    pub fn tokenize_into(&mut self, tokens: &mut Vec<Token<'a>>, errors: &mut Vec<LexerError>) {
+        tokens.clear();
+        errors.clear();
+
+        for item in self.by_ref() {
+            match item {
+                Ok(token) => tokens.push(token),
+                Err(e) => errors.push(e),
+            }
+        }
+    }
 
-    #[test]
-    fn test_peek() {
-        let mut lex = Lexer::new("foo");
+    /// Scan the next lexeme of the input, adding the resulting token or error(s) to the pending
+    /// items.
+    ///
+    /// Whitespace and comments produce no items, while reaching the end of the input produces the
+    /// final EOF token.
+    fn scan(&mut self) {
+        let c = match self.advance() {
+            Some(c) => c,
+            None => {
+                // Reached end of file, add final token. It is placed right after the last
+                // character, where the next one would be.
+                let eof = Position {
+                    line: self.line,
+                    column: self.column + 1,
+                };
+                self.push_token(Token {
+                    token_type: TokenType::EndOfFile,
+                    lexeme: "".into(),
+                    start: eof,
+                    end: eof,
+                    span: self.span_from(self.offset),
+                    symbol: None,
+                    literal: None,
+                });
+                self.finished = true;
+
+                return;
+            }
+        };
 
-        assert_eq!(*lex.peek().unwrap(), 'f');
+        // Position of the first character of the token which is being lexed.
+        let position = self.current_position();
+        let start = self.offset - c.len_utf8();
 
-        // Should not have advanced
-        assert_eq!(*lex.peek().unwrap(), 'f');
+        match c {
+            '+' => self.push_token(Token {
+                token_type: TokenType::Plus,
+                lexeme: "+".into(),
+                start: position,
+                end: self.current_position(),
+                span: self.span_from(start),
+                symbol: None,
+                literal: None,
+            }),
+
+            '-' => self.push_token(Token {
+                token_type: TokenType::Minus,
+                lexeme: "-".into(),
+                start: position,
+                end: self.current_position(),
+                span: self.span_from(start),
+                symbol: None,
+                literal: None,
+            }),
+
+            '*' => self.push_token(Token {
+                token_type: TokenType::Times,
+                lexeme: "*".into(),
+                start: position,
+                end: self.current_position(),
+                span: self.span_from(start),
+                symbol: None,
+                literal: None,
+            }),
+
+            '/' => {
+                if self.advance_if_equal('/') {
+                    // Line comment. The terminating line break, if any, is not part of it.
+                    let body = match self.advance_until_matching(char_class::is_line_break) {
+                        AdvanceUntil::Found(body) => body,
+                        // A comment on the last line, which is terminated by the end of the input
+                        // rather than a line break, is fine too.
+                        AdvanceUntil::EndOfInput(body) => body,
+                    };
+                    // Past the `//` and the body.
+                    let end = start + 2 + body.len();
+
+                    if self.trivia {
+                        self.push_token(Token {
+                            token_type: TokenType::Comment,
+                            lexeme: self.source[start..end].into(),
+                            start: position,
+                            end: self.current_position(),
+                            span: Span { start, end },
+                            symbol: None,
+                            literal: None,
+                        });
+                    }
+                } else if self.advance_if_equal('*') {
+                    // Block comment
+                    if self.skip_block_comment().is_err() {
+                        self.push_error(LexerError::UnterminatedBlockComment {
+                            starts_at: position,
+                            span: self.span_from(start),
+                        });
+                    } else if self.trivia {
+                        self.push_token(Token {
+                            token_type: TokenType::Comment,
+                            lexeme: self.slice_from(start).into(),
+                            start: position,
+                            end: self.current_position(),
+                            span: self.span_from(start),
+                            symbol: None,
+                            literal: None,
+                        });
+                    }
+                } else {
+                    // Divides operator
+                    self.push_token(Token {
+                        token_type: TokenType::Divide,
+                        lexeme: "/".into(),
+                        start: position,
+                        end: self.current_position(),
+                        span: self.span_from(start),
+                        symbol: None,
+                        literal: None,
+                    });
+                }
+            }
 
-        // At end of input
-        let mut lex = Lexer::new("");
-        assert!(lex.peek().is_none());
-    }
+            '%' => self.push_token(Token {
+                token_type: TokenType::Modulo,
+                lexeme: "%".into(),
+                start: position,
+                end: self.current_position(),
+                span: self.span_from(start),
+                symbol: None,
+                literal: None,
+            }),
+
+            '=' => {
+                if self.advance_if_equal('=') {
+                    self.push_token(Token {
+                        token_type: TokenType::DoubleEquals,
+                        lexeme: "==".into(),
+                        start: position,
+                        end: self.current_position(),
+                        span: self.span_from(start),
+                        symbol: None,
+                        literal: None,
+                    });
+                } else {
+                    self.push_token(Token {
+                        token_type: TokenType::Equals,
+                        lexeme: "=".into(),
+                        start: position,
+                        end: self.current_position(),
+                        span: self.span_from(start),
+                        symbol: None,
+                        literal: None,
+                    });
+                }
+            }
 
-    #[test]
-    fn test_advance() {
-        let mut lex = Lexer::new("foo");
+            '>' => {
+                if self.advance_if_equal('=') {
+                    self.push_token(Token {
+                        token_type: TokenType::GreaterOrEqual,
+                        lexeme: ">=".into(),
+                        start: position,
+                        end: self.current_position(),
+                        span: self.span_from(start),
+                        symbol: None,
+                        literal: None,
+                    });
+                } else {
+                    self.push_token(Token {
+                        token_type: TokenType::Greater,
+                        lexeme: ">".into(),
+                        start: position,
+                        end: self.current_position(),
+                        span: self.span_from(start),
+                        symbol: None,
+                        literal: None,
+                    });
+                }
+            }
 
-        assert_eq!(lex.advance().unwrap(), 'f');
-        assert_eq!(lex.line, 1);
+            '<' => {
+                if self.advance_if_equal('=') {
+                    self.push_token(Token {
+                        token_type: TokenType::LessOrEqual,
+                        lexeme: "<=".into(),
+                        start: position,
+                        end: self.current_position(),
+                        span: self.span_from(start),
+                        symbol: None,
+                        literal: None,
+                    });
+                } else {
+                    self.push_token(Token {
+                        token_type: TokenType::Less,
+                        lexeme: "<".into(),
+                        start: position,
+                        end: self.current_position(),
+                        span: self.span_from(start),
+                        symbol: None,
+                        literal: None,
+                    });
+                }
+            }
+
+            '!' => {
+                if self.advance_if_equal('=') {
+                    self.push_token(Token {
+                        token_type: TokenType::NotEquals,
+                        lexeme: "!=".into(),
+                        start: position,
+                        end: self.current_position(),
+                        span: self.span_from(start),
+                        symbol: None,
+                        literal: None,
+                    });
+                } else {
+                    self.push_token(Token {
+                        token_type: TokenType::BooleanNot,
+                        lexeme: "!".into(),
+                        start: position,
+                        end: self.current_position(),
+                        span: self.span_from(start),
+                        symbol: None,
+                        literal: None,
+                    });
+                }
+            }
+
+            ';' => self.push_token(Token {
+                token_type: TokenType::Semicolon,
+                lexeme: ";".into(),
+                start: position,
+                end: self.current_position(),
+                span: self.span_from(start),
+                symbol: None,
+                literal: None,
+            }),
+
+            ',' => self.push_token(Token {
+                token_type: TokenType::Comma,
+                lexeme: ",".into(),
+                start: position,
+                end: self.current_position(),
+                span: self.span_from(start),
+                symbol: None,
+                literal: None,
+            }),
+
+            // Decimal points are consumed as part of number literals, so this is only reached for
+            // other dots, see `at_decimal_point`.
+            '.' => self.push_token(Token {
+                token_type: TokenType::Dot,
+                lexeme: ".".into(),
+                start: position,
+                end: self.current_position(),
+                span: self.span_from(start),
+                symbol: None,
+                literal: None,
+            }),
+
+            '(' => self.push_token(Token {
+                token_type: TokenType::OpeningParentheses,
+                lexeme: "(".into(),
+                start: position,
+                end: self.current_position(),
+                span: self.span_from(start),
+                symbol: None,
+                literal: None,
+            }),
+            ')' => self.push_token(Token {
+                token_type: TokenType::ClosingParentheses,
+                lexeme: ")".into(),
+                start: position,
+                end: self.current_position(),
+                span: self.span_from(start),
+                symbol: None,
+                literal: None,
+            }),
+
+            '{' => self.push_token(Token {
+                token_type: TokenType::OpeningBraces,
+                lexeme: "{".into(),
+                start: position,
+                end: self.current_position(),
+                span: self.span_from(start),
+                symbol: None,
+                literal: None,
+            }),
+            '}' => self.push_token(Token {
+                token_type: TokenType::ClosingBraces,
+                lexeme: "}".into(),
+                start: position,
+                end: self.current_position(),
+                span: self.span_from(start),
+                symbol: None,
+                literal: None,
+            }),
+
+            '[' => self.push_token(Token {
+                token_type: TokenType::OpeningBracket,
+                lexeme: "[".into(),
+                start: position,
+                end: self.current_position(),
+                span: self.span_from(start),
+                symbol: None,
+                literal: None,
+            }),
+            ']' => self.push_token(Token {
+                token_type: TokenType::ClosingBracket,
+                lexeme: "]".into(),
+                start: position,
+                end: self.current_position(),
+                span: self.span_from(start),
+                symbol: None,
+                literal: None,
+            }),
+
+            '?' => self.push_token(Token {
+                token_type: TokenType::Question,
+                lexeme: "?".into(),
+                start: position,
+                end: self.current_position(),
+                span: self.span_from(start),
+                symbol: None,
+                literal: None,
+            }),
+            ':' => self.push_token(Token {
+                token_type: TokenType::Colon,
+                lexeme: ":".into(),
+                start: position,
+                end: self.current_position(),
+                span: self.span_from(start),
+                symbol: None,
+                literal: None,
+            }),
+
+            '"' => {
+                match self.advance_string() {
+                    Ok(content) => self.push_token(Token {
+                        token_type: TokenType::String,
+                        literal: self.text_literal(&content),
+                        lexeme: content,
+                        start: position,
+                        end: self.current_position(),
+                        span: self.span_from(start),
+                        symbol: None,
+                    }),
+                    Err(_) => self.push_error(LexerError::UnterminatedStringSequence {
+                        starts_at: position,
+                        ends_at: self.current_position(),
+                        span: self.span_from(start),
+                    }),
+                }
+                self.report_escape_errors();
+            }
+
+            // A raw string, such as `r"C:\temp"`, in which backslashes have no special meaning.
+            // Without the quote, `r` starts an identifier, which is handled below.
+            'r' if self.peek() == Some('"') => {
+                self.advance();
+
+                match self.advance_until_matching(|c| c == '"') {
+                    AdvanceUntil::Found(content) => self.push_token(Token {
+                        token_type: TokenType::String,
+                        lexeme: Cow::Borrowed(content),
+                        start: position,
+                        end: self.current_position(),
+                        span: self.span_from(start),
+                        symbol: None,
+                        literal: self.text_literal(content),
+                    }),
+                    AdvanceUntil::EndOfInput(_) => {
+                        self.push_error(LexerError::UnterminatedStringSequence {
+                            starts_at: position,
+                            ends_at: self.current_position(),
+                            span: self.span_from(start),
+                        })
+                    }
+                }
+            }
+
+            '\'' => {
+                match self.advance_char() {
+                    Ok(content) => self.push_token(Token {
+                        token_type: TokenType::Char,
+                        literal: self.text_literal(&content),
+                        lexeme: content,
+                        start: position,
+                        end: self.current_position(),
+                        span: self.span_from(start),
+                        symbol: None,
+                    }),
+                    Err(_) => self.push_error(LexerError::MalformedCharLiteral {
+                        position,
+                        text: self.slice_from(start).to_string(),
+                        span: self.span_from(start),
+                    }),
+                }
+                self.report_escape_errors();
+            }
+
+            // A shebang line, such as `#!/usr/bin/env spl`, makes scripts executable. It is only
+            // allowed at the very start of the input, and treated like a line comment.
+            '#' if start == 0 && self.peek() == Some('!') => {
+                // As for line comments, the terminating line break is not part of it.
+                let body = match self.advance_until_matching(char_class::is_line_break) {
+                    AdvanceUntil::Found(body) | AdvanceUntil::EndOfInput(body) => body,
+                };
+                // Past the `#` and the body, which starts at the `!`.
+                let end = start + 1 + body.len();
+
+                if self.trivia {
+                    self.push_token(Token {
+                        token_type: TokenType::Comment,
+                        lexeme: self.source[start..end].into(),
+                        start: position,
+                        end: self.current_position(),
+                        span: Span { start, end },
+                        symbol: None,
+                        literal: None,
+                    });
+                }
+            }
+
+            // Whitespace is silently consumed. For newlines, advance() handles line and column
+            // numbers, there's naught for us to do but enjoy this fleeting moment of quiet.
+            c if char_class::is_spl_whitespace(c) => {}
+
+            _ => {
+                let options = self.options;
+
+                if options.is_ident_start(c) {
+                    // Consume all following alphanumeric characters
+                    self.advance_while_matching(|c| options.is_ident_continue(c));
+                    let name = self.slice_from(start);
+
+                    // Name as which keywords and reserved words are looked up. The lexeme keeps the
+                    // original casing either way.
+                    let word: Cow<str> = if options.case_insensitive_keywords {
+                        Cow::Owned(name.to_lowercase())
+                    } else {
+                        Cow::Borrowed(name)
+                    };
+
+                    // Keywords take precedence over identifiers
+                    if let Some(token_type) = token::keyword_type(&word) {
+                        self.push_token(Token {
+                            token_type,
+                            lexeme: name.into(),
+                            start: position,
+                            end: self.current_position(),
+                            span: self.span_from(start),
+                            symbol: None,
+                            literal: Literal::from_lexeme(token_type, name),
+                        });
+                    } else if token::is_reserved_word(&word) {
+                        self.push_error(LexerError::ReservedWord {
+                            position,
+                            word: name.into(),
+                            span: self.span_from(start),
+                        });
+                    } else {
+                        // An alphanumeric name which doesn't correspond to any keyword is an
+                        // identifier.
+                        let symbol = if self.kinds_only {
+                            None
+                        } else {
+                            Some(self.symbols.intern(name))
+                        };
+                        self.push_token(Token {
+                            token_type: TokenType::Identifier,
+                            lexeme: name.into(),
+                            start: position,
+                            end: self.current_position(),
+                            span: self.span_from(start),
+                            symbol,
+                            literal: None,
+                        });
+                    }
+                } else if char_class::is_decimal_digit(c) {
+                    if self.advance_number(c).is_ok() {
+                        let lexeme = self.slice_from(start);
+                        let Some(value) = Literal::parse_number(lexeme) else {
+                            self.push_error(LexerError::NumberOutOfRange {
+                                position,
+                                lexeme: lexeme.to_string(),
+                                span: self.span_from(start),
+                            });
+                            return;
+                        };
+
+                        self.push_token(Token {
+                            token_type: TokenType::Number,
+                            lexeme: lexeme.into(),
+                            start: position,
+                            end: self.current_position(),
+                            span: self.span_from(start),
+                            symbol: None,
+                            literal: Some(Literal::Number(value)),
+                        });
+
+                        // A dot directly following a number, such as in `1.`, is not part of it,
+                        // unless followed by digits. Unless it's accessing a member, as in
+                        // `1.foo`, or the start of a range, as in `1..2`, a fractional part was
+                        // likely intended. Hex and binary numbers have no fractional part to
+                        // begin with.
+                        let decimal = !lexeme.starts_with("0x") && !lexeme.starts_with("0b");
+                        if decimal
+                            && self.peek() == Some('.')
+                            && !self
+                                .peek_next()
+                                .is_some_and(|c| c == '.' || options.is_ident_start(c))
+                        {
+                            self.warnings.push(Diagnostic::warning(
+                                TRAILING_DOT_WARNING,
+                                format!("Number `{}` is followed by a trailing dot, which is not part of it", lexeme),
+                                position,
+                                Span {
+                                    start,
+                                    end: self.offset + 1,
+                                },
+                            ));
+                        }
+                    } else {
+                        self.push_error(LexerError::MalformedNumber {
+                            position,
+                            lexeme: self.slice_from(start).to_string(),
+                            span: self.span_from(start),
+                        });
+                    }
+                } else {
+                    // Rather than reporting each of a run of unexpected characters - such as
+                    // pasted binary garbage - on its own, we report them all at once.
+                    let rest = self.advance_while_matching(|c| !options.may_start_token(c));
+
+                    if rest.is_empty() {
+                        self.push_error(LexerError::UnexpectedChar {
+                            position,
+                            c,
+                            span: self.span_from(start),
+                        });
+                    } else {
+                        self.push_error(LexerError::UnexpectedChars {
+                            position,
+                            text: self.slice_from(start).to_string(),
+                            span: self.span_from(start),
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Move `position`, which is relative to a chunk of the input starting at `line`, to the whole
+/// input.
+fn shift_position(position: Position, line: usize) -> Position {
+    Position {
+        line: position.line + line - 1,
+        column: position.column,
+    }
+}
+
+/// Move `span`, which is relative to a chunk of the input starting at byte `offset`, to the whole
+/// input.
+fn shift_span(span: Span, offset: usize) -> Span {
+    Span {
+        start: span.start + offset,
+        end: span.end + offset,
+    }
+}
+
+/// Move `token`, which was lexed from a chunk of the input starting at the start of `line` and
+/// byte `offset`, to the whole input.
+fn shift_token(token: Token<'static>, line: usize, offset: usize) -> Token<'static> {
+    Token {
+        start: shift_position(token.start, line),
+        end: shift_position(token.end, line),
+        span: shift_span(token.span, offset),
+        ..token
+    }
+}
+
+/// Move `warning` like `shift_token`.
+fn shift_warning(warning: Diagnostic, line: usize, offset: usize) -> Diagnostic {
+    Diagnostic {
+        position: shift_position(warning.position, line),
+        span: shift_span(warning.span, offset),
+        ..warning
+    }
+}
+
+/// Move `error` like `shift_token`.
+fn shift_error(mut error: LexerError, line: usize, offset: usize) -> LexerError {
+    match &mut error {
+        LexerError::UnterminatedStringSequence {
+            starts_at,
+            ends_at,
+            span,
+        } => {
+            *starts_at = shift_position(*starts_at, line);
+            *ends_at = shift_position(*ends_at, line);
+            *span = shift_span(*span, offset);
+        }
+        LexerError::UnterminatedBlockComment { starts_at, span } => {
+            *starts_at = shift_position(*starts_at, line);
+            *span = shift_span(*span, offset);
+        }
+        LexerError::UnknownEscapeSequence { position, span, .. }
+        | LexerError::MalformedCharLiteral { position, span, .. }
+        | LexerError::UnexpectedChar { position, span, .. }
+        | LexerError::UnexpectedChars { position, span, .. }
+        | LexerError::MalformedNumber { position, span, .. }
+        | LexerError::NumberOutOfRange { position, span, .. }
+        | LexerError::TooManyErrors { position, span, .. }
+        | LexerError::ReservedWord { position, span, .. } => {
+            *position = shift_position(*position, line);
+            *span = shift_span(*span, offset);
+        }
+    }
+
+    error
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token<'a>, LexerError>;
+
+    /// Return the next token, or error, of the input.
+    ///
+    /// Errors are returned in-stream, with lexing resuming after them. The iterator ends after
+    /// the EOF token, or early once the lexer bails out due to errors, see
+    /// `LexerOptions::max_errors` and `LexerOptions::fail_fast`.
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() && !self.finished {
+            self.scan();
+        }
+
+        self.pending.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use crate::{
+        fixtures::PROGRAM,
+        position::{tokens_to_source, SourceFile},
+        token::TokenType,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_peek() {
+        let lex = Lexer::new("foo");
+
+        assert_eq!(lex.peek().unwrap(), 'f');
+
+        // Should not have advanced
+        assert_eq!(lex.peek().unwrap(), 'f');
+
+        // At end of input
+        let lex = Lexer::new("");
+        assert!(lex.peek().is_none());
+    }
+
+    #[test]
+    fn test_peek_next() {
+        let mut lex = Lexer::new("ä\nb");
+
+        assert_eq!(lex.peek_next().unwrap(), '\n');
+        // Should not have advanced
+        assert_eq!(lex.peek().unwrap(), 'ä');
+        assert_eq!((lex.line, lex.column, lex.offset), (1, 0, 0));
+
+        lex.advance();
+        assert_eq!(lex.peek_next().unwrap(), 'b');
+        lex.advance();
+        assert_eq!((lex.line, lex.column, lex.offset), (2, 0, 3));
+
+        // At end of input
+        assert!(lex.peek_next().is_none());
+        let lex = Lexer::new("");
+        assert!(lex.peek_next().is_none());
+    }
+
+    #[test]
+    fn test_advance() {
+        let mut lex = Lexer::new("foo");
+
+        assert_eq!(lex.advance().unwrap(), 'f');
+        assert_eq!(lex.line, 1);
         assert_eq!(lex.column, 1);
 
         assert_eq!(lex.advance().unwrap(), 'o');
@@ -430,9 +1454,17 @@ mod tests {
         assert_eq!(lex.line, 1);
         assert_eq!(lex.column, 3);
 
+        // Past the end of input, the position stays the one of the last character.
+        assert!(lex.advance().is_none());
+        assert!(lex.advance().is_none());
+        assert_eq!(lex.line, 1);
+        assert_eq!(lex.column, 3);
+        assert_eq!(lex.offset, 3);
+
         // At end of input
         let mut lex = Lexer::new("");
         assert!(lex.advance().is_none());
+        assert_eq!(lex.column, 0);
     }
 
     #[test]
@@ -452,13 +1484,13 @@ mod tests {
     fn test_advance_if_equal() {
         let mut lex = Lexer::new("foo");
 
-        assert_eq!(lex.advance_if_equal('f'), true);
+        assert!(lex.advance_if_equal('f'));
         assert_eq!(lex.column, 1);
 
-        assert_eq!(lex.advance_if_equal('f'), false);
+        assert!(!lex.advance_if_equal('f'));
         assert_eq!(lex.column, 1);
 
-        assert_eq!(lex.advance_if_equal('o'), true);
+        assert!(lex.advance_if_equal('o'));
         assert_eq!(lex.column, 2);
 
         // At end of input
@@ -467,27 +1499,28 @@ mod tests {
     }
 
     #[test]
-    fn test_advance_until_equal() {
+    fn test_advance_until_matching() {
         let mut lex = Lexer::new("abc|def");
-        let tokens = lex.advance_until_equal('|');
-        assert_eq!(tokens.unwrap(), vec!['a', 'b', 'c']);
+        let tokens = lex.advance_until_matching(|c| c == '|');
+        assert_eq!(tokens, AdvanceUntil::Found("abc"));
         assert_eq!(lex.column, 4);
-        assert_eq!(*lex.peek().unwrap(), 'd');
+        assert_eq!(lex.peek().unwrap(), 'd');
 
         // At end of input
         let mut lex = Lexer::new("abc|");
-        let tokens = lex.advance_until_equal('|');
-        assert_eq!(tokens.unwrap(), vec!['a', 'b', 'c']);
+        let tokens = lex.advance_until_matching(|c| c == '|');
+        assert_eq!(tokens, AdvanceUntil::Found("abc"));
         assert_eq!(lex.column, 4);
         assert!(lex.peek().is_none());
     }
 
     #[test]
-    fn test_advance_until_equal_no_match() {
+    fn test_advance_until_matching_no_match() {
         let mut lex = Lexer::new("abc");
-        let tokens = lex.advance_until_equal('|');
-        assert!(tokens.is_err());
-        assert_eq!(lex.column, 4);
+        let tokens = lex.advance_until_matching(|c| c == '|');
+        assert_eq!(tokens, AdvanceUntil::EndOfInput("abc"));
+        // Must not have advanced past the end of the input.
+        assert_eq!(lex.column, 3);
         assert!(lex.peek().is_none());
     }
 
@@ -495,12 +1528,12 @@ mod tests {
     fn test_advance_while_matching() {
         let mut lex = Lexer::new("abc123def");
         let tokens = lex.advance_while_matching(|c| c.is_alphabetic());
-        assert_eq!(tokens, vec!['a', 'b', 'c']);
+        assert_eq!(tokens, "abc");
         assert_eq!(lex.column, 3);
 
         let mut lex = Lexer::new("abc123def");
         let tokens = lex.advance_while_matching(|c| c.is_alphanumeric());
-        assert_eq!(tokens, vec!['a', 'b', 'c', '1', '2', '3', 'd', 'e', 'f']);
+        assert_eq!(tokens, "abc123def");
         assert_eq!(lex.column, 9);
     }
 
@@ -508,7 +1541,7 @@ mod tests {
     fn test_advance_while_matching_no_match() {
         let mut lex = Lexer::new("-0abc123def");
         let tokens = lex.advance_while_matching(|c| c.is_alphanumeric());
-        assert_eq!(tokens, vec![]);
+        assert_eq!(tokens, "");
         assert_eq!(lex.column, 0);
     }
 
@@ -521,7 +1554,11 @@ mod tests {
             Token {
                 token_type: TokenType::Plus,
                 lexeme: "+".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 1 },
+                span: Span { start: 0, end: 1 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -534,7 +1571,11 @@ mod tests {
             Token {
                 token_type: TokenType::Minus,
                 lexeme: "-".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 1 },
+                span: Span { start: 0, end: 1 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -547,7 +1588,11 @@ mod tests {
             Token {
                 token_type: TokenType::Times,
                 lexeme: "*".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 1 },
+                span: Span { start: 0, end: 1 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -560,10 +1605,63 @@ mod tests {
             Token {
                 token_type: TokenType::Divide,
                 lexeme: "/".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 1 },
+                span: Span { start: 0, end: 1 },
+                symbol: None,
+                literal: None,
+            }
+        );
+    }
+    #[test]
+    fn test_modulo() {
+        let mut lex = Lexer::new("%");
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(
+            tokens[0],
+            Token {
+                token_type: TokenType::Modulo,
+                lexeme: "%".into(),
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 1 },
+                span: Span { start: 0, end: 1 },
+                symbol: None,
+                literal: None,
             }
         );
+
+        let mut lex = Lexer::new("a % b");
+        let tokens = lex.tokenize().unwrap();
+        let types: Vec<_> = tokens.iter().map(|t| &t.token_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                &TokenType::Identifier,
+                &TokenType::Modulo,
+                &TokenType::Identifier,
+                &TokenType::EndOfFile,
+            ]
+        );
+
+        // There is no compound assignment, so this is a modulo followed by an assignment.
+        let mut lex = Lexer::new("a %= 2");
+        let tokens = lex.tokenize().unwrap();
+        let types: Vec<_> = tokens
+            .iter()
+            .map(|t| (&t.token_type, t.start.column))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                (&TokenType::Identifier, 1),
+                (&TokenType::Modulo, 3),
+                (&TokenType::Equals, 4),
+                (&TokenType::Number, 6),
+                (&TokenType::EndOfFile, 7),
+            ]
+        );
     }
+
     #[test]
     fn test_equals() {
         let mut lex = Lexer::new("=");
@@ -573,7 +1671,11 @@ mod tests {
             Token {
                 token_type: TokenType::Equals,
                 lexeme: "=".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 1 },
+                span: Span { start: 0, end: 1 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -586,7 +1688,11 @@ mod tests {
             Token {
                 token_type: TokenType::DoubleEquals,
                 lexeme: "==".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 2 },
+                span: Span { start: 0, end: 2 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -599,7 +1705,11 @@ mod tests {
             Token {
                 token_type: TokenType::NotEquals,
                 lexeme: "!=".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 2 },
+                span: Span { start: 0, end: 2 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -613,7 +1723,11 @@ mod tests {
             Token {
                 token_type: TokenType::Greater,
                 lexeme: ">".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 1 },
+                span: Span { start: 0, end: 1 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -627,7 +1741,11 @@ mod tests {
             Token {
                 token_type: TokenType::Less,
                 lexeme: "<".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 1 },
+                span: Span { start: 0, end: 1 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -641,7 +1759,11 @@ mod tests {
             Token {
                 token_type: TokenType::GreaterOrEqual,
                 lexeme: ">=".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 2 },
+                span: Span { start: 0, end: 2 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -655,7 +1777,11 @@ mod tests {
             Token {
                 token_type: TokenType::LessOrEqual,
                 lexeme: "<=".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 2 },
+                span: Span { start: 0, end: 2 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -669,7 +1795,11 @@ mod tests {
             Token {
                 token_type: TokenType::BooleanNot,
                 lexeme: "!".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 1 },
+                span: Span { start: 0, end: 1 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -684,7 +1814,11 @@ mod tests {
             Token {
                 token_type: TokenType::Semicolon,
                 lexeme: ";".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 1 },
+                span: Span { start: 0, end: 1 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -699,7 +1833,11 @@ mod tests {
             Token {
                 token_type: TokenType::OpeningParentheses,
                 lexeme: "(".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 1 },
+                span: Span { start: 0, end: 1 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -714,7 +1852,11 @@ mod tests {
             Token {
                 token_type: TokenType::ClosingParentheses,
                 lexeme: ")".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 1 },
+                span: Span { start: 0, end: 1 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -729,7 +1871,11 @@ mod tests {
             Token {
                 token_type: TokenType::OpeningBraces,
                 lexeme: "{".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 1 },
+                span: Span { start: 0, end: 1 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -744,9 +1890,192 @@ mod tests {
             Token {
                 token_type: TokenType::ClosingBraces,
                 lexeme: "}".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 1 },
+                span: Span { start: 0, end: 1 },
+                symbol: None,
+                literal: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_comma() {
+        let mut lex = Lexer::new("f(a, b)");
+        let tokens = lex.tokenize().unwrap();
+        let types: Vec<_> = tokens.iter().map(|t| &t.token_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                &TokenType::Identifier,
+                &TokenType::OpeningParentheses,
+                &TokenType::Identifier,
+                &TokenType::Comma,
+                &TokenType::Identifier,
+                &TokenType::ClosingParentheses,
+                &TokenType::EndOfFile,
+            ]
+        );
+        assert_eq!(
+            tokens[3],
+            Token {
+                token_type: TokenType::Comma,
+                lexeme: ",".into(),
+                start: Position { line: 1, column: 4 },
+                end: Position { line: 1, column: 4 },
+                span: Span { start: 3, end: 4 },
+                symbol: None,
+                literal: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_brackets() {
+        let mut lex = Lexer::new("xs[0]");
+        let tokens = lex.tokenize().unwrap();
+        let types: Vec<_> = tokens
+            .iter()
+            .map(|t| (&t.token_type, t.lexeme.as_ref(), t.start.column))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                (&TokenType::Identifier, "xs", 1),
+                (&TokenType::OpeningBracket, "[", 3),
+                (&TokenType::Number, "0", 4),
+                (&TokenType::ClosingBracket, "]", 5),
+                (&TokenType::EndOfFile, "", 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dot() {
+        let mut lex = Lexer::new(".");
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(
+            tokens[0],
+            Token {
+                token_type: TokenType::Dot,
+                lexeme: ".".into(),
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 1 },
+                span: Span { start: 0, end: 1 },
+                symbol: None,
+                literal: None,
             }
         );
+
+        let mut lex = Lexer::new("obj.field");
+        let tokens = lex.tokenize().unwrap();
+        let types: Vec<_> = tokens
+            .iter()
+            .map(|t| (&t.token_type, t.lexeme.as_ref()))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                (&TokenType::Identifier, "obj"),
+                (&TokenType::Dot, "."),
+                (&TokenType::Identifier, "field"),
+                (&TokenType::EndOfFile, ""),
+            ]
+        );
+
+        // A decimal point after a number is only part of it if a digit follows.
+        for (source, expected) in [
+            ("1.5", vec![(&TokenType::Number, "1.5")]),
+            (
+                "1.",
+                vec![(&TokenType::Number, "1"), (&TokenType::Dot, ".")],
+            ),
+            (
+                "1.x",
+                vec![
+                    (&TokenType::Number, "1"),
+                    (&TokenType::Dot, "."),
+                    (&TokenType::Identifier, "x"),
+                ],
+            ),
+            (
+                "1.foo",
+                vec![
+                    (&TokenType::Number, "1"),
+                    (&TokenType::Dot, "."),
+                    (&TokenType::Identifier, "foo"),
+                ],
+            ),
+            (
+                "1..2",
+                vec![
+                    (&TokenType::Number, "1"),
+                    (&TokenType::Dot, "."),
+                    (&TokenType::Dot, "."),
+                    (&TokenType::Number, "2"),
+                ],
+            ),
+            (
+                "1.5.x",
+                vec![
+                    (&TokenType::Number, "1.5"),
+                    (&TokenType::Dot, "."),
+                    (&TokenType::Identifier, "x"),
+                ],
+            ),
+        ] {
+            let tokens = Lexer::new(source).tokenize().unwrap();
+            let types: Vec<_> = tokens
+                .iter()
+                .map(|t| (&t.token_type, t.lexeme.as_ref()))
+                .filter(|(token_type, _)| **token_type != TokenType::EndOfFile)
+                .collect();
+            assert_eq!(types, expected, "{}", source);
+        }
+
+        // Whereas one before a number is not.
+        let mut lex = Lexer::new(".5");
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(tokens[0].token_type, TokenType::Dot);
+        assert_eq!(tokens[1].lexeme, "5");
+    }
+
+    #[test]
+    fn test_question_and_colon() {
+        let mut lex = Lexer::new("a ? b : c;");
+        let tokens = lex.tokenize().unwrap();
+        let types: Vec<_> = tokens
+            .iter()
+            .map(|t| {
+                (
+                    t.token_type,
+                    t.lexeme.as_ref(),
+                    t.start.line,
+                    t.start.column,
+                )
+            })
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                (TokenType::Identifier, "a", 1, 1),
+                (TokenType::Question, "?", 1, 3),
+                (TokenType::Identifier, "b", 1, 5),
+                (TokenType::Colon, ":", 1, 7),
+                (TokenType::Identifier, "c", 1, 9),
+                (TokenType::Semicolon, ";", 1, 10),
+                (TokenType::EndOfFile, "", 1, 11),
+            ]
+        );
+
+        // There is no `:=` operator.
+        let mut lex = Lexer::new(":=");
+        let tokens = lex.tokenize().unwrap();
+        let types: Vec<_> = tokens.iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            types,
+            vec![TokenType::Colon, TokenType::Equals, TokenType::EndOfFile]
+        );
     }
 
     #[test]
@@ -759,7 +2088,11 @@ mod tests {
             Token {
                 token_type: TokenType::True,
                 lexeme: "true".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 4 },
+                span: Span { start: 0, end: 4 },
+                symbol: None,
+                literal: Some(Literal::Bool(true)),
             }
         );
     }
@@ -774,7 +2107,11 @@ mod tests {
             Token {
                 token_type: TokenType::False,
                 lexeme: "false".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 5 },
+                span: Span { start: 0, end: 5 },
+                symbol: None,
+                literal: Some(Literal::Bool(false)),
             }
         );
     }
@@ -789,7 +2126,11 @@ mod tests {
             Token {
                 token_type: TokenType::And,
                 lexeme: "and".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 3 },
+                span: Span { start: 0, end: 3 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -804,7 +2145,11 @@ mod tests {
             Token {
                 token_type: TokenType::Or,
                 lexeme: "or".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 2 },
+                span: Span { start: 0, end: 2 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -819,7 +2164,11 @@ mod tests {
             Token {
                 token_type: TokenType::Var,
                 lexeme: "var".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 3 },
+                span: Span { start: 0, end: 3 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -834,7 +2183,11 @@ mod tests {
             Token {
                 token_type: TokenType::Print,
                 lexeme: "print".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 5 },
+                span: Span { start: 0, end: 5 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -849,7 +2202,11 @@ mod tests {
             Token {
                 token_type: TokenType::If,
                 lexeme: "if".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 2 },
+                span: Span { start: 0, end: 2 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -864,7 +2221,11 @@ mod tests {
             Token {
                 token_type: TokenType::Else,
                 lexeme: "else".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 4 },
+                span: Span { start: 0, end: 4 },
+                symbol: None,
+                literal: None,
             }
         );
     }
@@ -879,11 +2240,51 @@ mod tests {
             Token {
                 token_type: TokenType::While,
                 lexeme: "while".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 5 },
+                span: Span { start: 0, end: 5 },
+                symbol: None,
+                literal: None,
             }
         );
     }
 
+    #[test]
+    fn test_new_keywords() {
+        let mut lex = Lexer::new("fun return for break continue nil");
+        let tokens = lex.tokenize().unwrap();
+        let types: Vec<_> = tokens
+            .iter()
+            .map(|t| (&t.token_type, t.lexeme.as_ref(), t.start.column))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                (&TokenType::Fun, "fun", 1),
+                (&TokenType::Return, "return", 5),
+                (&TokenType::For, "for", 12),
+                (&TokenType::Break, "break", 16),
+                (&TokenType::Continue, "continue", 22),
+                (&TokenType::Nil, "nil", 31),
+                (&TokenType::EndOfFile, "", 34),
+            ]
+        );
+
+        // Identifiers which merely start with a keyword are still identifiers.
+        for name in [
+            "returned",
+            "forEach",
+            "funny",
+            "breaks",
+            "continued",
+            "nilly",
+        ] {
+            let tokens = Lexer::new(name).tokenize().unwrap();
+            assert_eq!(tokens[0].token_type, TokenType::Identifier, "{}", name);
+            assert_eq!(tokens[0].lexeme, name);
+        }
+    }
+
     #[test]
     fn test_identifier() {
         let mut lex = Lexer::new("foo");
@@ -894,7 +2295,11 @@ mod tests {
             Token {
                 token_type: TokenType::Identifier,
                 lexeme: "foo".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 3 },
+                span: Span { start: 0, end: 3 },
+                symbol: lex.symbols().get("foo"),
+                literal: None,
             }
         );
 
@@ -907,11 +2312,237 @@ mod tests {
             Token {
                 token_type: TokenType::Identifier,
                 lexeme: "if32".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 4 },
+                span: Span { start: 0, end: 4 },
+                symbol: lex.symbols().get("if32"),
+                literal: None,
             }
         );
     }
 
+    #[test]
+    fn test_identifier_with_underscores() {
+        let mut lex = Lexer::new("var my_count = _private + snake_case_name * a_1 - _ - __;");
+        let tokens = lex.tokenize().unwrap();
+        let identifiers: Vec<_> = tokens
+            .iter()
+            .filter(|t| t.token_type == TokenType::Identifier)
+            .map(|t| (t.lexeme.as_ref(), t.start.column))
+            .collect();
+        assert_eq!(
+            identifiers,
+            vec![
+                ("my_count", 5),
+                ("_private", 16),
+                ("snake_case_name", 27),
+                ("a_1", 45),
+                ("_", 51),
+                ("__", 55),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_option_unicode_identifiers() {
+        let tokens = Lexer::new("straße").tokenize().unwrap();
+        assert_eq!(tokens[0].token_type, TokenType::Identifier);
+        assert_eq!(tokens[0].lexeme, "straße");
+
+        let options = LexerOptions {
+            unicode_identifiers: false,
+            ..Default::default()
+        };
+        let mut lex = Lexer::with_options("straße λ", options);
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        lex.tokenize_into(&mut tokens, &mut errors);
+        assert_eq!(tokens[0].lexeme, "stra");
+        assert_eq!(tokens[1].lexeme, "e");
+        assert_eq!(
+            errors,
+            vec![
+                LexerError::UnexpectedChar {
+                    position: Position { line: 1, column: 5 },
+                    c: 'ß',
+                    span: Span { start: 4, end: 6 },
+                },
+                LexerError::UnexpectedChar {
+                    position: Position { line: 1, column: 8 },
+                    c: 'λ',
+                    span: Span { start: 8, end: 10 },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_option_nested_block_comments() {
+        let source = "/* a /* b */ c */ x";
+
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        assert_eq!(tokens[0].lexeme, "x");
+
+        let options = LexerOptions {
+            nested_block_comments: false,
+            ..Default::default()
+        };
+        let tokens = Lexer::with_options(source, options).tokenize().unwrap();
+        let lexemes: Vec<_> = tokens.iter().map(|t| t.lexeme.as_ref()).collect();
+        assert_eq!(lexemes, vec!["c", "*", "/", "x", ""]);
+    }
+
+    #[test]
+    fn test_option_case_insensitive_keywords() {
+        let source = "IF If iF if WhIlE PRINT FooBar CLASS";
+
+        let mut lex = Lexer::new(source);
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        lex.tokenize_into(&mut tokens, &mut errors);
+        let types: Vec<_> = tokens
+            .iter()
+            .map(|t| (t.token_type, t.lexeme.as_ref()))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                (TokenType::Identifier, "IF"),
+                (TokenType::Identifier, "If"),
+                (TokenType::Identifier, "iF"),
+                (TokenType::If, "if"),
+                (TokenType::Identifier, "WhIlE"),
+                (TokenType::Identifier, "PRINT"),
+                (TokenType::Identifier, "FooBar"),
+                (TokenType::Identifier, "CLASS"),
+                (TokenType::EndOfFile, ""),
+            ]
+        );
+        assert!(errors.is_empty());
+
+        let options = LexerOptions {
+            case_insensitive_keywords: true,
+            ..Default::default()
+        };
+        let mut lex = Lexer::with_options(source, options);
+        lex.tokenize_into(&mut tokens, &mut errors);
+        let types: Vec<_> = tokens
+            .iter()
+            .map(|t| (t.token_type, t.lexeme.as_ref()))
+            .collect();
+        // Keywords keep their original casing too.
+        assert_eq!(
+            types,
+            vec![
+                (TokenType::If, "IF"),
+                (TokenType::If, "If"),
+                (TokenType::If, "iF"),
+                (TokenType::If, "if"),
+                (TokenType::While, "WhIlE"),
+                (TokenType::Print, "PRINT"),
+                (TokenType::Identifier, "FooBar"),
+                (TokenType::EndOfFile, ""),
+            ]
+        );
+        // Reserved words are reserved regardless of their case as well.
+        assert_eq!(
+            errors,
+            vec![LexerError::ReservedWord {
+                position: Position {
+                    line: 1,
+                    column: 32
+                },
+                word: "CLASS".into(),
+                span: Span { start: 31, end: 36 },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_options_retained_on_reset() {
+        let options = LexerOptions {
+            case_insensitive_keywords: true,
+            ..Default::default()
+        };
+        let mut lex = Lexer::with_options("IF", options);
+        let _ = lex.tokenize().unwrap();
+
+        lex.reset("PRINT");
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(tokens[0].token_type, TokenType::Print);
+    }
+
+    #[test]
+    fn test_interned_identifiers() {
+        let mut lex = Lexer::new("a a a a");
+        let tokens = lex.tokenize().unwrap();
+
+        let a = tokens[0].symbol.unwrap();
+        for token in &tokens[..4] {
+            assert_eq!(token.symbol, Some(a));
+        }
+        assert_eq!(lex.symbols().resolve(a), Some("a"));
+        assert_eq!(lex.symbols().len(), 1);
+
+        // Only identifiers are interned.
+        assert_eq!(tokens[4].symbol, None);
+        let tokens = Lexer::new("while 1 \"a\"").tokenize().unwrap();
+        assert!(tokens.iter().all(|t| t.symbol.is_none()));
+
+        // Resetting starts over with an empty pool.
+        lex.reset("b a");
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(tokens[0].symbol, Some(a));
+        assert_eq!(lex.symbols().resolve(a), Some("b"));
+        assert_eq!(lex.symbols().len(), 2);
+    }
+
+    #[test]
+    fn test_reserved_word() {
+        let mut lex = Lexer::new("var class = 1;\nimport class;");
+        let errors = lex.tokenize().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                LexerError::ReservedWord {
+                    position: Position { line: 1, column: 5 },
+                    word: "class".into(),
+                    span: Span { start: 4, end: 9 },
+                },
+                LexerError::ReservedWord {
+                    position: Position { line: 2, column: 1 },
+                    word: "import".into(),
+                    span: Span { start: 15, end: 21 },
+                },
+                LexerError::ReservedWord {
+                    position: Position { line: 2, column: 8 },
+                    word: "class".into(),
+                    span: Span { start: 22, end: 27 },
+                },
+            ]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "Reserved word `class` found at line 1, column 5. It is reserved for future use, and can't be used as an identifier"
+        );
+
+        // Every reserved word is rejected, while words merely starting with one are not.
+        for word in token::RESERVED_WORDS {
+            assert!(Lexer::new(word).tokenize().is_err(), "{}", word);
+
+            let longer = format!("{}s", word);
+            let tokens = Lexer::new(&longer).tokenize().unwrap();
+            assert_eq!(tokens[0].token_type, TokenType::Identifier);
+        }
+
+        // Active keywords are unaffected.
+        for (word, token_type) in token::KEYWORDS {
+            let tokens = Lexer::new(word).tokenize().unwrap();
+            assert_eq!(tokens[0].token_type, *token_type);
+            assert_eq!(tokens[0].lexeme, *word);
+        }
+    }
+
     #[test]
     fn test_number() {
         // Integer
@@ -922,7 +2553,11 @@ mod tests {
             Token {
                 token_type: TokenType::Number,
                 lexeme: "123".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 3 },
+                span: Span { start: 0, end: 3 },
+                symbol: None,
+                literal: Some(Literal::Number(123.0)),
             }
         );
 
@@ -934,39 +2569,250 @@ mod tests {
             Token {
                 token_type: TokenType::Number,
                 lexeme: "123.456".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 7 },
+                span: Span { start: 0, end: 7 },
+                symbol: None,
+                literal: Some(Literal::Number(123.456)),
             }
         );
 
-        // Float with no decimal digits.
+        // Number followed by a dot, which isn't a decimal point as no digits follow it.
         let mut lex = Lexer::new("123.");
         let tokens = lex.tokenize().unwrap();
         assert_eq!(
             tokens[0],
             Token {
                 token_type: TokenType::Number,
-                lexeme: "123.".into(),
-                line: 1
+                lexeme: "123".into(),
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 3 },
+                span: Span { start: 0, end: 3 },
+                symbol: None,
+                literal: Some(Literal::Number(123.0)),
             }
         );
+        assert_eq!(tokens[1].token_type, TokenType::Dot);
 
-        // Float with no multiple decimal points.
-        // Lexer should recogniez the number (123.456) fine, but then balk on finding a lone
-        // decimal point.
-        let mut lex = Lexer::new("123.456.");
+        // Float with multiple decimal points. The whole literal is malformed, rather than the
+        // lexer recognizing 123.456 and then balking on a lone decimal point.
+        let mut lex = Lexer::new("123.456.789");
         let errors = lex.tokenize().unwrap_err();
         assert_eq!(
-            errors[0],
-            LexerError::UnexpectedChar {
-                position: Position { line: 1, column: 8 },
-                c: '.'
-            }
+            errors,
+            vec![LexerError::MalformedNumber {
+                position: Position { line: 1, column: 1 },
+                lexeme: "123.456.789".to_string(),
+                span: Span { start: 0, end: 11 },
+            }]
         );
-        assert_eq!(errors.len(), 1);
-    }
 
-    #[test]
-    fn test_string() {
+        // Lexing resumes after the malformed literal.
+        let mut lex = Lexer::new("1.2.3;");
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        lex.tokenize_into(&mut tokens, &mut errors);
+        assert_eq!(
+            errors,
+            vec![LexerError::MalformedNumber {
+                position: Position { line: 1, column: 1 },
+                lexeme: "1.2.3".to_string(),
+                span: Span { start: 0, end: 5 },
+            }]
+        );
+        assert_eq!(
+            tokens[0],
+            Token {
+                token_type: TokenType::Semicolon,
+                lexeme: ";".into(),
+                start: Position { line: 1, column: 6 },
+                end: Position { line: 1, column: 6 },
+                span: Span { start: 5, end: 6 },
+                symbol: None,
+                literal: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_hex_and_binary_numbers() {
+        let mut lex = Lexer::new("0x1F 0b1010 0xff");
+        let tokens = lex.tokenize().unwrap();
+        let numbers: Vec<_> = tokens
+            .iter()
+            .map(|t| (&t.token_type, t.lexeme.as_ref(), t.start.column, t.span))
+            .collect();
+        assert_eq!(
+            numbers,
+            vec![
+                (&TokenType::Number, "0x1F", 1, Span { start: 0, end: 4 }),
+                (&TokenType::Number, "0b1010", 6, Span { start: 5, end: 11 }),
+                (&TokenType::Number, "0xff", 13, Span { start: 12, end: 16 }),
+                (&TokenType::EndOfFile, "", 17, Span { start: 16, end: 16 }),
+            ]
+        );
+
+        // Plain zero is still a decimal number.
+        let mut lex = Lexer::new("0;");
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(tokens[0].token_type, TokenType::Number);
+        assert_eq!(tokens[0].lexeme, "0");
+        assert_eq!(tokens[1].token_type, TokenType::Semicolon);
+
+        // A decimal point is not part of a hexadecimal literal.
+        let mut lex = Lexer::new("0x1F.");
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(tokens[0].lexeme, "0x1F");
+        assert_eq!(tokens[1].token_type, TokenType::Dot);
+        assert_eq!(tokens[1].span, Span { start: 4, end: 5 });
+    }
+
+    #[test]
+    fn test_malformed_hex_and_binary_numbers() {
+        let mut lex = Lexer::new("var x = 0x;");
+        let errors = lex.tokenize().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![LexerError::MalformedNumber {
+                position: Position { line: 1, column: 9 },
+                lexeme: "0x".to_string(),
+                span: Span { start: 8, end: 10 },
+            }]
+        );
+
+        // The invalid digit is part of the malformed literal, and lexing resumes after it.
+        let mut lex = Lexer::new("1 +\n  0b2;");
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        lex.tokenize_into(&mut tokens, &mut errors);
+        assert_eq!(
+            errors,
+            vec![LexerError::MalformedNumber {
+                position: Position { line: 2, column: 3 },
+                lexeme: "0b2".to_string(),
+                span: Span { start: 6, end: 9 },
+            }]
+        );
+        assert_eq!(tokens[2].token_type, TokenType::Semicolon);
+        assert_eq!(tokens[2].span, Span { start: 9, end: 10 });
+    }
+
+    #[test]
+    fn test_number_separators_and_exponents() {
+        for source in [
+            "1_000_000",
+            "1_0.2_5",
+            "6.02e23",
+            "6.02E23",
+            "1e+5",
+            "2.5e-3",
+            "1e1_0",
+            "0xFF_FF",
+            "0b1010_1010",
+        ] {
+            let mut lex = Lexer::new(source);
+            let tokens = lex.tokenize().unwrap();
+            assert_eq!(tokens[0].token_type, TokenType::Number, "{}", source);
+            assert_eq!(tokens[0].lexeme, source);
+            assert_eq!(tokens.len(), 2, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_malformed_number_separators_and_exponents() {
+        for source in [
+            "1__0", "1_", "1_.5", "1.5_", "1e", "1e+", "1e_5", "0x_FF", "0xFF_",
+        ] {
+            let mut lex = Lexer::new(source);
+            let errors = lex.tokenize().unwrap_err();
+            assert_eq!(
+                errors,
+                vec![LexerError::MalformedNumber {
+                    position: Position { line: 1, column: 1 },
+                    lexeme: source.to_string(),
+                    span: Span {
+                        start: 0,
+                        end: source.len()
+                    },
+                }]
+            );
+        }
+
+        // Lexing resumes after the malformed literal.
+        let mut lex = Lexer::new("x = 1e;");
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        lex.tokenize_into(&mut tokens, &mut errors);
+        assert_eq!(
+            errors,
+            vec![LexerError::MalformedNumber {
+                position: Position { line: 1, column: 5 },
+                lexeme: "1e".to_string(),
+                span: Span { start: 4, end: 6 },
+            }]
+        );
+        assert_eq!(tokens[2].token_type, TokenType::Semicolon);
+    }
+
+    #[test]
+    fn test_trailing_dot_warning() {
+        let mut lex = Lexer::new("x = 12.;\ny = 1.foo + 0x1f.;");
+        let tokens = lex.tokenize().unwrap();
+        let types: Vec<_> = tokens
+            .iter()
+            .map(|t| (t.token_type, t.lexeme.as_ref()))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                (TokenType::Identifier, "x"),
+                (TokenType::Equals, "="),
+                (TokenType::Number, "12"),
+                (TokenType::Dot, "."),
+                (TokenType::Semicolon, ";"),
+                (TokenType::Identifier, "y"),
+                (TokenType::Equals, "="),
+                (TokenType::Number, "1"),
+                (TokenType::Dot, "."),
+                (TokenType::Identifier, "foo"),
+                (TokenType::Plus, "+"),
+                (TokenType::Number, "0x1f"),
+                (TokenType::Dot, "."),
+                (TokenType::Semicolon, ";"),
+                (TokenType::EndOfFile, ""),
+            ]
+        );
+
+        // Neither member access, as in `1.foo`, nor the dot after the hex number is warned about.
+        assert_eq!(
+            lex.warnings(),
+            &[Diagnostic::warning(
+                TRAILING_DOT_WARNING,
+                "Number `12` is followed by a trailing dot, which is not part of it".to_string(),
+                Position { line: 1, column: 5 },
+                Span { start: 4, end: 7 },
+            )]
+        );
+
+        // Nor are ranges, or other hex and binary numbers followed by a dot.
+        for source in ["1..2", "a[0..10]", "0x1F.5", "0b101.", "0x1f.;"] {
+            let mut lex = Lexer::new(source);
+            lex.tokenize().unwrap();
+            assert!(lex.warnings().is_empty(), "{:?}", source);
+        }
+
+        // Also at the end of the input, and alongside errors.
+        let mut lex = Lexer::new("@ 1.");
+        assert_eq!(lex.tokenize().unwrap_err().len(), 1);
+        assert_eq!(lex.warnings().len(), 1);
+
+        lex.reset("1.5");
+        lex.tokenize().unwrap();
+        assert!(lex.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_string() {
         let mut lex = Lexer::new("\"Hello world\"");
         let tokens = lex.tokenize().unwrap();
         assert_eq!(
@@ -974,7 +2820,14 @@ mod tests {
             Token {
                 token_type: TokenType::String,
                 lexeme: "Hello world".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position {
+                    line: 1,
+                    column: 13
+                },
+                span: Span { start: 0, end: 13 },
+                symbol: None,
+                literal: Some(Literal::Str("Hello world".to_string())),
             }
         );
     }
@@ -988,7 +2841,11 @@ mod tests {
             Token {
                 token_type: TokenType::String,
                 lexeme: "".into(),
-                line: 1
+                start: Position { line: 1, column: 1 },
+                end: Position { line: 1, column: 2 },
+                span: Span { start: 0, end: 2 },
+                symbol: None,
+                literal: Some(Literal::Str("".to_string())),
             }
         );
     }
@@ -1001,15 +2858,336 @@ mod tests {
             errors[0],
             LexerError::UnterminatedStringSequence {
                 starts_at: Position { line: 1, column: 1 },
+                // The last character of the input.
                 ends_at: Position {
                     line: 1,
-                    column: 13
-                }
+                    column: 12
+                },
+                span: Span { start: 0, end: 12 },
             },
         );
         assert_eq!(errors.len(), 1);
     }
 
+    #[test]
+    fn test_unterminated_multi_line_string() {
+        let source = "var a = 1;\nvar b = \"unterminated;\nvar c = 3;\nvar d = 4;\nprint a;";
+        let mut lex = Lexer::new(source);
+        let errors = lex.tokenize().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![LexerError::UnterminatedStringSequence {
+                // The opening quote, rather than the line the lexer gave up on.
+                starts_at: Position { line: 2, column: 9 },
+                ends_at: Position { line: 5, column: 8 },
+                span: Span {
+                    start: 19,
+                    end: source.len()
+                },
+            }]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "Unterminted string sequence found, starting at line 2, column 9, ending at line 5, column 8"
+        );
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let mut lex = Lexer::new(r#""He said \"hi\"" "a\\b\n\t\r\'""#);
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(tokens[0].lexeme, "He said \"hi\"");
+        assert_eq!(tokens[1].lexeme, "a\\b\n\t\r'");
+        assert_eq!(tokens[1].start.column, 18);
+    }
+
+    #[test]
+    fn test_raw_string() {
+        let mut lex = Lexer::new(r#"x = r"C:\temp\n" + r"\";"#);
+        let tokens = lex.tokenize().unwrap();
+        let strings: Vec<_> = tokens
+            .iter()
+            .filter(|t| t.token_type == TokenType::String)
+            .map(|t| (t.lexeme.as_ref(), t.start.column, t.span))
+            .collect();
+        assert_eq!(
+            strings,
+            vec![
+                (r"C:\temp\n", 5, Span { start: 4, end: 16 }),
+                (r"\", 20, Span { start: 19, end: 23 }),
+            ]
+        );
+        assert_eq!(tokens[5].token_type, TokenType::Semicolon);
+
+        // Without a directly following quote, `r` is an identifier, or the start of one.
+        let mut lex = Lexer::new(r#"r radius r "a""#);
+        let tokens = lex.tokenize().unwrap();
+        let types: Vec<_> = tokens
+            .iter()
+            .map(|t| (t.token_type, t.lexeme.as_ref()))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                (TokenType::Identifier, "r"),
+                (TokenType::Identifier, "radius"),
+                (TokenType::Identifier, "r"),
+                (TokenType::String, "a"),
+                (TokenType::EndOfFile, ""),
+            ]
+        );
+
+        let mut lex = Lexer::new("x = r\"abc\\\n");
+        let errors = lex.tokenize().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![LexerError::UnterminatedStringSequence {
+                starts_at: Position { line: 1, column: 5 },
+                ends_at: Position { line: 2, column: 0 },
+                span: Span { start: 4, end: 11 },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_unknown_escape() {
+        let mut lex = Lexer::new(r#"a "x\qy\z" b"#);
+        let errors = lex.tokenize().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                LexerError::UnknownEscapeSequence {
+                    position: Position { line: 1, column: 5 },
+                    c: 'q',
+                    span: Span { start: 4, end: 6 },
+                },
+                LexerError::UnknownEscapeSequence {
+                    position: Position { line: 1, column: 8 },
+                    c: 'z',
+                    span: Span { start: 7, end: 9 },
+                },
+            ]
+        );
+
+        // The errors follow the string they belong to in the stream.
+        let items: Vec<_> = Lexer::new(r#"a "x\qy\z" 'b\q' '\q'"#)
+            .map(|item| match item {
+                Ok(token) => format!("{:?} {}", token.token_type, token.lexeme),
+                Err(e) => e.code().to_string(),
+            })
+            .collect();
+        assert_eq!(
+            items,
+            vec![
+                "Identifier a",
+                "String xy",
+                "L0003",
+                "L0003",
+                "L0008",
+                "L0003",
+                "Char \\q",
+                "L0003",
+                "EndOfFile ",
+            ]
+        );
+
+        // An escaped quote doesn't terminate the string.
+        let mut lex = Lexer::new(r#""abc\""#);
+        let errors = lex.tokenize().unwrap_err();
+        assert!(matches!(
+            errors[0],
+            LexerError::UnterminatedStringSequence { .. }
+        ));
+
+        // Neither does a backslash at the end of the input.
+        let mut lex = Lexer::new(r#""abc\"#);
+        let errors = lex.tokenize().unwrap_err();
+        assert!(matches!(
+            errors[0],
+            LexerError::UnterminatedStringSequence { .. }
+        ));
+    }
+
+    #[test]
+    fn test_string_with_newline() {
+        let mut lex = Lexer::new("\"a\nb\" c");
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(tokens[0].lexeme, "a\nb");
+        assert_eq!((tokens[1].start.line, tokens[1].start.column), (2, 4));
+    }
+
+    #[test]
+    fn test_multi_line_string() {
+        let mut lex = Lexer::new("print\n  \"one\ntwo\nthree\" after;");
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(
+            tokens[1],
+            Token {
+                token_type: TokenType::String,
+                lexeme: "one\ntwo\nthree".into(),
+                // The closing quote is on a later line.
+                start: Position { line: 2, column: 3 },
+                end: Position { line: 4, column: 6 },
+                span: Span { start: 8, end: 23 },
+                symbol: None,
+                literal: Some(Literal::Str("one\ntwo\nthree".to_string())),
+            }
+        );
+        assert_eq!(
+            tokens[2],
+            Token {
+                token_type: TokenType::Identifier,
+                lexeme: "after".into(),
+                start: Position { line: 4, column: 8 },
+                end: Position {
+                    line: 4,
+                    column: 12
+                },
+                span: Span { start: 24, end: 29 },
+                symbol: lex.symbols().get("after"),
+                literal: None,
+            }
+        );
+        assert_eq!((tokens[3].start.line, tokens[3].start.column), (4, 13));
+    }
+
+    #[test]
+    fn test_char() {
+        let mut lex = Lexer::new("'a' 'ß' '\\n' '\\'' '\"'");
+        let tokens = lex.tokenize().unwrap();
+        let chars: Vec<_> = tokens
+            .iter()
+            .map(|t| (t.token_type, t.lexeme.as_ref(), t.start.column, t.span))
+            .collect();
+        assert_eq!(
+            chars,
+            vec![
+                (TokenType::Char, "a", 1, Span { start: 0, end: 3 }),
+                (TokenType::Char, "ß", 5, Span { start: 4, end: 8 }),
+                (TokenType::Char, "\n", 9, Span { start: 9, end: 13 }),
+                (TokenType::Char, "'", 14, Span { start: 14, end: 18 }),
+                (TokenType::Char, "\"", 19, Span { start: 19, end: 22 }),
+                (TokenType::EndOfFile, "", 22, Span { start: 22, end: 22 }),
+            ]
+        );
+
+        // Directly followed by an identifier
+        let mut lex = Lexer::new("'a'b");
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(tokens[0].lexeme, "a");
+        assert_eq!(tokens[1].token_type, TokenType::Identifier);
+        assert_eq!(tokens[1].lexeme, "b");
+    }
+
+    #[test]
+    fn test_malformed_char() {
+        for (source, text) in [("''", "''"), ("'ab'", "'ab'"), ("'\\n\\t'", "'\\n\\t'")] {
+            let mut lex = Lexer::new(source);
+            let errors = lex.tokenize().unwrap_err();
+            assert_eq!(
+                errors,
+                vec![LexerError::MalformedCharLiteral {
+                    position: Position { line: 1, column: 1 },
+                    text: text.to_string(),
+                    span: Span {
+                        start: 0,
+                        end: text.len()
+                    },
+                }]
+            );
+        }
+
+        // Unterminated, at the end of the input and of the line. The next line is lexed as usual.
+        let mut lex = Lexer::new("x = 'a");
+        let errors = lex.tokenize().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![LexerError::MalformedCharLiteral {
+                position: Position { line: 1, column: 5 },
+                text: "'a".to_string(),
+                span: Span { start: 4, end: 6 },
+            }]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "Malformed char literal `'a` found at line 1, column 5. It must contain exactly one character"
+        );
+
+        let mut lex = Lexer::new("x = '\ny");
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        lex.tokenize_into(&mut tokens, &mut errors);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(tokens[2].lexeme, "y");
+        assert_eq!((tokens[2].start.line, tokens[2].start.column), (2, 1));
+
+        // Unknown escape sequences are reported as in strings.
+        let mut lex = Lexer::new("'\\q'");
+        let errors = lex.tokenize().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![LexerError::UnknownEscapeSequence {
+                position: Position { line: 1, column: 2 },
+                c: 'q',
+                span: Span { start: 1, end: 3 },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_lexemes_borrowed() {
+        // Operator-heavy input must not allocate any lexeme.
+        let input = "a+b*(c-d)/e>=f!=g==h<=i;{x=1.5;}".repeat(100);
+        let tokens = Lexer::new(&input).tokenize().unwrap();
+        assert!(tokens.len() > 1000);
+        assert!(tokens.iter().all(|t| matches!(t.lexeme, Cow::Borrowed(_))));
+
+        // Strings are only copied if they contain escape sequences.
+        let tokens = Lexer::new(r#""äöü plain" "with\tescape" ñ"#)
+            .tokenize()
+            .unwrap();
+        assert_eq!(tokens[0].lexeme, Cow::Borrowed("äöü plain"));
+        assert_eq!(
+            tokens[1].lexeme,
+            Cow::<str>::Owned("with\tescape".to_string())
+        );
+        assert!(matches!(tokens[1].lexeme, Cow::Owned(_)));
+        assert_eq!(tokens[2].lexeme, Cow::Borrowed("ñ"));
+    }
+
+    #[test]
+    fn test_spans() {
+        // Offsets must be in bytes, so multi-byte characters advance them by more than one.
+        let input = "\"größe\" x >= ü2 \"\\\"\"";
+        let tokens = Lexer::new(input).tokenize().unwrap();
+
+        let spans: Vec<(&str, Span)> = tokens
+            .iter()
+            .map(|t| (&input[t.span.start..t.span.end], t.span))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("\"größe\"", Span { start: 0, end: 9 }),
+                ("x", Span { start: 10, end: 11 }),
+                (">=", Span { start: 12, end: 14 }),
+                ("ü2", Span { start: 15, end: 18 }),
+                ("\"\\\"\"", Span { start: 19, end: 23 }),
+                ("", Span { start: 23, end: 23 }),
+            ]
+        );
+
+        let errors = Lexer::new("\"ä\" @").tokenize().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![LexerError::UnexpectedChar {
+                position: Position { line: 1, column: 5 },
+                c: '@',
+                span: Span { start: 5, end: 6 },
+            }]
+        );
+    }
+
     #[test]
     fn test_comment() {
         let mut lex = Lexer::new("// This is a comment\n1");
@@ -1021,14 +3199,278 @@ mod tests {
             Token {
                 token_type: TokenType::Number,
                 lexeme: "1".into(),
-                line: 2
+                start: Position { line: 2, column: 1 },
+                end: Position { line: 2, column: 1 },
+                span: Span { start: 21, end: 22 },
+                symbol: None,
+                literal: Some(Literal::Number(1.0)),
             }
         );
 
         assert_eq!(lex.line, 2);
-        // We already consumed the single digit on line two, so are in column two now. (Which
-        // happens to be the EOF)
-        assert_eq!(lex.column, 2);
+        // We already consumed the single digit on line two, which is the last character. Reaching
+        // the EOF does not advance the column any further.
+        assert_eq!(lex.column, 1);
+    }
+
+    #[test]
+    fn test_block_comment() {
+        let mut lex = Lexer::new("1 /* A comment\nspanning * / lines */ 2/**/3");
+        let tokens = lex.tokenize().unwrap();
+
+        let positions: Vec<(&str, usize, usize)> = tokens
+            .iter()
+            .map(|t| (t.lexeme.as_ref(), t.start.line, t.start.column))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![("1", 1, 1), ("2", 2, 23), ("3", 2, 28), ("", 2, 29)]
+        );
+
+        // Outside of a comment, `*/` are two operators.
+        let mut lex = Lexer::new("*/");
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(tokens[0].token_type, TokenType::Times);
+        assert_eq!(tokens[1].token_type, TokenType::Divide);
+    }
+
+    #[test]
+    fn test_nested_block_comment() {
+        let mut lex = Lexer::new("/* a /* b */ c */ d");
+        let tokens = lex.tokenize().unwrap();
+
+        assert_eq!(
+            tokens[0],
+            Token {
+                token_type: TokenType::Identifier,
+                lexeme: "d".into(),
+                start: Position {
+                    line: 1,
+                    column: 19
+                },
+                end: Position {
+                    line: 1,
+                    column: 19
+                },
+                span: Span { start: 18, end: 19 },
+                symbol: lex.symbols().get("d"),
+                literal: None,
+            }
+        );
+        assert_eq!(tokens.len(), 2);
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let mut lex = Lexer::new("a\n  /* never\nclosed");
+        let errors = lex.tokenize().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![LexerError::UnterminatedBlockComment {
+                starts_at: Position { line: 2, column: 3 },
+                span: Span { start: 4, end: 19 },
+            }]
+        );
+
+        // The inner comment being closed doesn't close the outer one.
+        let mut lex = Lexer::new("/* a /* b */ c");
+        let errors = lex.tokenize().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![LexerError::UnterminatedBlockComment {
+                starts_at: Position { line: 1, column: 1 },
+                span: Span { start: 0, end: 14 },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_comment_trivia() {
+        let input = "a; // First\n/* Second\n*/ b; // Last";
+
+        let tokens = Lexer::new(input).with_trivia(true).tokenize().unwrap();
+        let lexemes: Vec<(&TokenType, &str, usize)> = tokens
+            .iter()
+            .map(|t| (&t.token_type, t.lexeme.as_ref(), t.start.line))
+            .collect();
+        assert_eq!(
+            lexemes,
+            vec![
+                (&TokenType::Identifier, "a", 1),
+                (&TokenType::Semicolon, ";", 1),
+                (&TokenType::Comment, "// First", 1),
+                (&TokenType::Comment, "/* Second\n*/", 2),
+                (&TokenType::Identifier, "b", 3),
+                (&TokenType::Semicolon, ";", 3),
+                (&TokenType::Comment, "// Last", 3),
+                (&TokenType::EndOfFile, "", 3),
+            ]
+        );
+        assert_eq!(tokens[2].start.column, 4);
+        assert_eq!(tokens[6].start.column, 7);
+        for token in &tokens {
+            assert_eq!(&input[token.span.start..token.span.end], token.lexeme);
+        }
+
+        // Comments are discarded by default.
+        let tokens = Lexer::new(input).tokenize().unwrap();
+        assert_eq!(tokens.len(), 5);
+        assert!(tokens.iter().all(|t| t.token_type != TokenType::Comment));
+    }
+
+    #[test]
+    fn test_comment_at_end_of_file() {
+        // Last line consisting only of a comment
+        let mut lex = Lexer::new("a;\n// Nothing more");
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(
+            tokens[2],
+            Token {
+                token_type: TokenType::EndOfFile,
+                lexeme: "".into(),
+                start: Position {
+                    line: 2,
+                    column: 16
+                },
+                end: Position {
+                    line: 2,
+                    column: 16
+                },
+                span: Span { start: 18, end: 18 },
+                symbol: None,
+                literal: None,
+            }
+        );
+
+        // Comment following code on the last line
+        let mut lex = Lexer::new("a; // Nothing more");
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(tokens.len(), 3);
+        assert_eq!((tokens[2].start.line, tokens[2].start.column), (1, 19));
+
+        // Comment immediately followed by the end of the input
+        let mut lex = Lexer::new("//");
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!((tokens[0].start.line, tokens[0].start.column), (1, 3));
+    }
+
+    #[test]
+    fn test_spans_lossless() {
+        let corpus = [
+            PROGRAM,
+            "#!/usr/bin/env spl\nprint 1;",
+            "a+b-c*d/e%f=g==h!=i>j<k>=l<=m!n;o,p.q(r)s{t}u[v]w?x:y",
+            "x>=1<=2==3!=4>=>==<=<!==!",
+            "\"größe\" \"a\\tb\\\"\" r\"C:\\temp\" 'a' 'ß' '\\n' '\\''",
+            "0x1F 0b1010 1_000 6.02e23 2.5e-3 1.",
+            "/* a /* b */ c */ x // done\r\ny\r// again\rz",
+            "\"multi\nline\" \t ü2 straße",
+            "",
+            "   ",
+            "// only a comment",
+        ];
+
+        for source in corpus {
+            let file = SourceFile::new(source);
+            let tokens = Lexer::new(file.text())
+                .with_trivia(true)
+                .tokenize()
+                .unwrap();
+
+            let mut previous_end = 0;
+            for token in &tokens {
+                let text = file.slice(token.span);
+
+                // Consecutive tokens neither overlap nor leave anything but whitespace between
+                // them.
+                assert!(
+                    token.span.start >= previous_end,
+                    "{:?} in {:?}",
+                    token,
+                    source
+                );
+                let gap = &source[previous_end..token.span.start];
+                assert!(
+                    gap.chars().all(char_class::is_spl_whitespace),
+                    "{:?} before {:?} in {:?}",
+                    gap,
+                    token,
+                    source
+                );
+                previous_end = token.span.end;
+
+                match token.token_type {
+                    // The lexemes of these have their escape sequences decoded and their quotes
+                    // stripped, while the span covers them.
+                    TokenType::String => {
+                        assert!(
+                            text.starts_with('"') || text.starts_with("r\""),
+                            "{:?}",
+                            text
+                        );
+                        assert!(text.len() >= 2 && text.ends_with('"'), "{:?}", text);
+                    }
+                    TokenType::Char => {
+                        assert!(text.len() >= 3, "{:?}", text);
+                        assert!(text.starts_with('\'') && text.ends_with('\''), "{:?}", text);
+                    }
+                    TokenType::EndOfFile => assert_eq!(token.span.start, source.len()),
+                    _ => assert_eq!(text, token.lexeme, "{:?} in {:?}", token, source),
+                }
+            }
+
+            assert_eq!(previous_end, source.len());
+            assert_eq!(tokens_to_source(&file, &tokens), source.trim_start());
+        }
+    }
+
+    #[test]
+    fn test_shebang() {
+        let mut lex = Lexer::new("#!/usr/bin/env spl\nprint 1;");
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(
+            tokens[0],
+            Token {
+                token_type: TokenType::Print,
+                lexeme: "print".into(),
+                start: Position { line: 2, column: 1 },
+                end: Position { line: 2, column: 5 },
+                span: Span { start: 19, end: 24 },
+                symbol: None,
+                literal: None,
+            }
+        );
+
+        // Only a shebang
+        let mut lex = Lexer::new("#!/usr/bin/env spl");
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].token_type, TokenType::EndOfFile);
+
+        // Anywhere but at the very start, it is no shebang.
+        let mut lex = Lexer::new("print 1;\nprint 2;\n#!/usr/bin/env spl");
+        let errors = lex.tokenize().unwrap_err();
+        assert_eq!(
+            errors[0],
+            LexerError::UnexpectedChar {
+                position: Position { line: 3, column: 1 },
+                c: '#',
+                span: Span { start: 18, end: 19 },
+            }
+        );
+
+        let mut lex = Lexer::new(" #!/usr/bin/env spl");
+        assert!(lex.tokenize().is_err());
+
+        // In trivia mode, it is a comment.
+        let mut lex = Lexer::new("#!/usr/bin/env spl\nprint 1;").with_trivia(true);
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(tokens[0].token_type, TokenType::Comment);
+        assert_eq!(tokens[0].lexeme, "#!/usr/bin/env spl");
+        assert_eq!(tokens[0].span, Span { start: 0, end: 18 });
+        assert_eq!(tokens[1].token_type, TokenType::Print);
     }
 
     #[test]
@@ -1037,7 +3479,143 @@ mod tests {
         let _ = lex.tokenize().unwrap();
 
         assert_eq!(lex.line, 2);
-        assert_eq!(lex.column, 7);
+        // Column of the last character, the semicolon.
+        assert_eq!(lex.column, 6);
+    }
+
+    #[test]
+    fn test_line_endings() {
+        let program = "var x = 1; // one\nprint x;\n\n/* a\nb */ x = x + \"c\nd\";\n";
+
+        let lex = |source: &str| -> Vec<(TokenType, String, usize, usize)> {
+            Lexer::new(source)
+                .tokenize()
+                .unwrap()
+                .into_iter()
+                .map(|t| {
+                    (
+                        t.token_type,
+                        t.lexeme.into_owned(),
+                        t.start.line,
+                        t.start.column,
+                    )
+                })
+                .collect()
+        };
+
+        let unix = lex(program);
+        assert_eq!(unix.last().unwrap().2, 7);
+
+        for line_break in ["\r\n", "\r"] {
+            let tokens = lex(&program.replace('\n', line_break));
+            assert_eq!(tokens.len(), unix.len(), "{:?}", line_break);
+
+            for (token, expected) in tokens.iter().zip(&unix) {
+                assert_eq!(token.0, expected.0, "{:?}", line_break);
+                // String literals retain the line breaks of the source.
+                assert_eq!(token.1, expected.1.replace('\n', line_break));
+                assert_eq!((token.2, token.3), (expected.2, expected.3), "{:?}", token);
+            }
+        }
+
+        // Line comments end at either kind of line break.
+        for source in ["// one\r\nx", "// one\rx"] {
+            let tokens = Lexer::new(source).with_trivia(true).tokenize().unwrap();
+            assert_eq!(tokens[0].lexeme, "// one");
+            assert_eq!(tokens[1].lexeme, "x");
+            assert_eq!((tokens[1].start.line, tokens[1].start.column), (2, 1));
+        }
+    }
+
+    #[test]
+    fn test_error_positions() {
+        // Right after a newline
+        let mut lex = Lexer::new("a\n@b");
+        let errors = lex.tokenize().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![LexerError::UnexpectedChar {
+                position: Position { line: 2, column: 1 },
+                c: '@',
+                span: Span { start: 2, end: 3 },
+            }]
+        );
+
+        // Right at the end of the input
+        let mut lex = Lexer::new("x = @");
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        lex.tokenize_into(&mut tokens, &mut errors);
+        assert_eq!(
+            errors,
+            vec![LexerError::UnexpectedChar {
+                position: Position { line: 1, column: 5 },
+                c: '@',
+                span: Span { start: 4, end: 5 },
+            }]
+        );
+        assert_eq!(tokens.last().unwrap().start.column, 6);
+
+        // An unterminated string ends at the last character of the input, on whichever line that
+        // is.
+        let mut lex = Lexer::new("x\n\"ab\ncd");
+        let errors = lex.tokenize().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![LexerError::UnterminatedStringSequence {
+                starts_at: Position { line: 2, column: 1 },
+                ends_at: Position { line: 3, column: 2 },
+                span: Span { start: 2, end: 8 },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_unexpected_chars() {
+        let mut lex = Lexer::new("var x = @@@###@@@ 5;");
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        lex.tokenize_into(&mut tokens, &mut errors);
+        assert_eq!(
+            errors,
+            vec![LexerError::UnexpectedChars {
+                position: Position { line: 1, column: 9 },
+                text: "@@@###@@@".to_string(),
+                span: Span { start: 8, end: 17 },
+            }]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "Unexpected characters `@@@###@@@` found at line 1, column 9"
+        );
+
+        let lexemes: Vec<_> = tokens.iter().map(|t| t.lexeme.as_ref()).collect();
+        assert_eq!(lexemes, vec!["var", "x", "=", "5", ";", ""]);
+
+        // The run ends at the next token, even without whitespace in between.
+        let mut lex = Lexer::new("@#(a)");
+        lex.tokenize_into(&mut tokens, &mut errors);
+        assert_eq!(
+            errors,
+            vec![LexerError::UnexpectedChars {
+                position: Position { line: 1, column: 1 },
+                text: "@#".to_string(),
+                span: Span { start: 0, end: 2 },
+            }]
+        );
+        assert_eq!(tokens[0].token_type, TokenType::OpeningParentheses);
+
+        // A lone unexpected character is reported as such.
+        let mut lex = Lexer::new("a @ b");
+        let errors = lex.tokenize().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![LexerError::UnexpectedChar {
+                position: Position { line: 1, column: 3 },
+                c: '@',
+                span: Span { start: 2, end: 3 },
+            }]
+        );
     }
 
     #[test]
@@ -1045,51 +3623,779 @@ mod tests {
         let mut lex = Lexer::new("a");
         let tokens = lex.tokenize().unwrap();
 
+        // End of file is positioned one past the last character.
         assert_eq!(
             tokens[1],
             Token {
-                token_type: TokenType::EndOfile,
+                token_type: TokenType::EndOfFile,
                 lexeme: "".into(),
-                line: 1
+                start: Position { line: 1, column: 2 },
+                end: Position { line: 1, column: 2 },
+                span: Span { start: 1, end: 1 },
+                symbol: None,
+                literal: None,
             }
         );
     }
 
     #[test]
-    fn test_tokenize() {
-        let input = "
-var b = true ; // A boolean
-var i = 123; // A number
-var d = 12.3; // Another number
-var s = \"123 \"; // This is a string , not a number
-
-i + d; // 135.3
-1 == 2; // false
-!true; // false
-true or false; // true
-var average = (min + max ) / 2;
-
-{
-	print \"Hello , world !\";
-	print \"Hello , SPL Prime world !\";
-}
+    fn test_columns() {
+        let mut lex = Lexer::new("if (ab >= 12.5) {\n  print \"x y\";\n}");
+        let tokens = lex.tokenize().unwrap();
 
-if ( i == s ) {
-	print \"yes\";
-} else {
-	print \"no\";
-}
+        let positions: Vec<(&str, usize, usize)> = tokens
+            .iter()
+            .map(|t| (t.lexeme.as_ref(), t.start.line, t.start.column))
+            .collect();
 
-var a = 1;
-while (a < 10) {
-	print a;
-	a = a + 1;
-}
-";
+        assert_eq!(
+            positions,
+            vec![
+                ("if", 1, 1),
+                ("(", 1, 4),
+                ("ab", 1, 5),
+                (">=", 1, 8),
+                ("12.5", 1, 11),
+                (")", 1, 15),
+                ("{", 1, 17),
+                ("print", 2, 3),
+                ("x y", 2, 9),
+                (";", 2, 14),
+                ("}", 3, 1),
+                ("", 3, 2),
+            ]
+        );
 
-        let mut lex = Lexer::new(input);
+        assert_eq!(
+            tokens[3].to_string(),
+            "<GreaterOrEqual, >=> at line 1, column 8"
+        );
+        assert_eq!(tokens[3].end.line, tokens[3].start.line);
+        assert_eq!(tokens[3].end.column, tokens[3].start.column + 1);
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let mut lex = Lexer::new(PROGRAM);
         let tokens = lex.tokenize().unwrap();
 
         assert_eq!(tokens.len(), 94);
     }
+
+    #[test]
+    fn test_literals() {
+        let mut lex = Lexer::new(r#"12.5 0x1f true false nil "a\tb" r"a\tb" 'c' x"#);
+        let tokens = lex.tokenize().unwrap();
+        let literals: Vec<_> = tokens.iter().map(|t| t.literal.clone()).collect();
+        assert_eq!(
+            literals,
+            vec![
+                Some(Literal::Number(12.5)),
+                Some(Literal::Number(31.0)),
+                Some(Literal::Bool(true)),
+                Some(Literal::Bool(false)),
+                Some(Literal::None),
+                Some(Literal::Str("a\tb".to_string())),
+                Some(Literal::Str(r"a\tb".to_string())),
+                Some(Literal::Str("c".to_string())),
+                None,
+                None,
+            ]
+        );
+
+        // Case-insensitive keywords are literals too.
+        let options = LexerOptions {
+            case_insensitive_keywords: true,
+            ..LexerOptions::default()
+        };
+        let tokens = Lexer::with_options("TRUE", options).tokenize().unwrap();
+        assert_eq!(tokens[0].literal, Some(Literal::Bool(true)));
+    }
+
+    #[test]
+    fn test_number_out_of_range() {
+        let source = format!("x = 1{}; y = 2;", "0".repeat(400));
+        let mut lex = Lexer::new(&source);
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        lex.tokenize_into(&mut tokens, &mut errors);
+
+        assert_eq!(
+            errors,
+            vec![LexerError::NumberOutOfRange {
+                position: Position { line: 1, column: 5 },
+                lexeme: source[4..405].to_string(),
+                span: Span { start: 4, end: 405 },
+            }]
+        );
+        assert!(errors[0]
+            .to_string()
+            .ends_with("found at line 1, column 5 is out of range"));
+        // Lexing continues after it.
+        assert_eq!(tokens[5].literal, Some(Literal::Number(2.0)));
+
+        assert!(Lexer::new("1e308").tokenize().is_ok());
+        assert!(Lexer::new("1e309").tokenize().is_err());
+    }
+
+    #[test]
+    fn test_max_errors() {
+        let source = "@ ".repeat(150);
+        let errors = Lexer::new(&source).tokenize().unwrap_err();
+        assert_eq!(errors.len(), 101);
+        assert!(errors[..100]
+            .iter()
+            .all(|e| matches!(e, LexerError::UnexpectedChar { c: '@', .. })));
+        assert_eq!(
+            errors[100],
+            LexerError::TooManyErrors {
+                reported: 100,
+                position: Position {
+                    line: 1,
+                    column: 199
+                },
+                span: Span {
+                    start: 199,
+                    end: 199
+                },
+            }
+        );
+        assert_eq!(
+            errors[100].to_string(),
+            "Too many errors, stopped lexing at line 1, column 199 after 100 errors"
+        );
+
+        let options = LexerOptions {
+            max_errors: Some(3),
+            ..LexerOptions::default()
+        };
+        let mut lex = Lexer::with_options("a @ b @ c @ d @ e", options);
+        let items: Vec<_> = lex.by_ref().collect();
+        // No tokens follow the last error, not even the EOF token.
+        assert_eq!(items.len(), 7);
+        assert!(matches!(
+            items.last(),
+            Some(Err(LexerError::TooManyErrors { reported: 3, .. }))
+        ));
+        assert!(lex.next().is_none());
+
+        let options = LexerOptions {
+            max_errors: None,
+            ..LexerOptions::default()
+        };
+        let errors = Lexer::with_options(&source, options)
+            .tokenize()
+            .unwrap_err();
+        assert_eq!(errors.len(), 150);
+    }
+
+    #[test]
+    fn test_fail_fast() {
+        let options = LexerOptions {
+            fail_fast: true,
+            ..LexerOptions::default()
+        };
+
+        let source = "@ ".repeat(150);
+        let errors = Lexer::with_options(&source, options)
+            .tokenize()
+            .unwrap_err();
+        assert_eq!(
+            errors,
+            vec![LexerError::UnexpectedChar {
+                position: Position { line: 1, column: 1 },
+                c: '@',
+                span: Span { start: 0, end: 1 },
+            }]
+        );
+
+        // A literal containing an error is still completed, as the error is reported after it.
+        let mut lex = Lexer::with_options(r#"x "\q" y"#, options);
+        let items: Vec<_> = lex.by_ref().collect();
+        assert_eq!(items.len(), 3);
+        assert!(matches!(
+            items[2],
+            Err(LexerError::UnknownEscapeSequence { c: 'q', .. })
+        ));
+
+        // A clean program is unaffected by either mode.
+        let tokens = Lexer::with_options(PROGRAM, options).tokenize().unwrap();
+        assert_eq!(tokens, Lexer::new(PROGRAM).tokenize().unwrap());
+    }
+
+    #[test]
+    fn test_scan_kinds() {
+        fn kinds(source: &str) -> Vec<(TokenType, Span)> {
+            let tokens = Lexer::new(source).scan_kinds();
+            let expected: Vec<_> = Lexer::new(source)
+                .filter_map(|item| item.ok())
+                .map(|token| (token.token_type, token.span))
+                .collect();
+            assert_eq!(tokens, expected, "{:?}", source);
+
+            tokens
+        }
+
+        assert_eq!(kinds(PROGRAM).len(), 94);
+        assert_eq!(
+            kinds("x = \"a\\n\\q\" @"),
+            vec![
+                (TokenType::Identifier, Span { start: 0, end: 1 }),
+                (TokenType::Equals, Span { start: 2, end: 3 }),
+                (TokenType::String, Span { start: 4, end: 11 }),
+                (TokenType::EndOfFile, Span { start: 13, end: 13 }),
+            ]
+        );
+
+        let mut rng = Rng(0xfeed_f00d_dead_beef);
+        for _ in 0..1_000 {
+            kinds(&rng.ascii_string());
+        }
+
+        // Lexing as usual afterwards.
+        let mut lex = Lexer::new("\"\\n\" a");
+        lex.scan_kinds();
+        lex.reset("\"\\n\" a");
+        let tokens = lex.tokenize().unwrap();
+        assert_eq!(tokens[0].lexeme, "\n");
+        assert!(tokens[1].symbol.is_some());
+    }
+
+    #[test]
+    fn test_iterator() {
+        let input = "var a = 1;\nwhile (a < 10) { print \"a\"; }";
+
+        let collected: Result<Vec<Token>, LexerError> = Lexer::new(input).collect();
+        assert_eq!(collected.unwrap(), Lexer::new(input).tokenize().unwrap());
+
+        // Iteration ends after the EOF token.
+        let mut lex = Lexer::new("a");
+        assert_eq!(
+            lex.next().unwrap().unwrap().token_type,
+            TokenType::Identifier
+        );
+        assert_eq!(
+            lex.next().unwrap().unwrap().token_type,
+            TokenType::EndOfFile
+        );
+        assert!(lex.next().is_none());
+        assert!(lex.next().is_none());
+    }
+
+    #[test]
+    fn test_iterator_errors_in_stream() {
+        let items: Vec<Result<Token, LexerError>> = Lexer::new("a @ b").collect();
+
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[0].as_ref().unwrap().lexeme, "a");
+        assert_eq!(
+            items[1],
+            Err(LexerError::UnexpectedChar {
+                position: Position { line: 1, column: 3 },
+                c: '@',
+                span: Span { start: 2, end: 3 },
+            })
+        );
+        assert_eq!(items[2].as_ref().unwrap().lexeme, "b");
+        assert_eq!(items[3].as_ref().unwrap().token_type, TokenType::EndOfFile);
+    }
+
+    #[test]
+    fn test_tokenize_into_reuses_buffers() {
+        let first = "var a = 1 @ 2;\nprint a + a + a;";
+        let second = "b;";
+
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+
+        Lexer::new(first).tokenize_into(&mut tokens, &mut errors);
+        assert_eq!(tokens.len(), 14);
+        assert_eq!(errors.len(), 1);
+        let capacity = tokens.capacity();
+
+        // A different lexer may fill the same buffers, which contain nothing of the first run
+        // afterwards.
+        Lexer::new(second).tokenize_into(&mut tokens, &mut errors);
+        assert_eq!(tokens, Lexer::new(second).tokenize().unwrap());
+        assert!(errors.is_empty());
+
+        // The allocation was reused rather than replaced.
+        assert_eq!(tokens.capacity(), capacity);
+    }
+
+    /// Reader which returns at most one byte per read.
+    struct ByteReader<'a>(&'a [u8]);
+
+    impl Read for ByteReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match (self.0.split_first(), buf.first_mut()) {
+                (Some((byte, rest)), Some(out)) => {
+                    *out = *byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    /// Output of lexing `source` through a reader returning a byte at a time.
+    fn tokenize_bytewise(source: &str, options: LexerOptions) -> Tokenized {
+        let reader = BufReader::with_capacity(1, ByteReader(source.as_bytes()));
+        Lexer::tokenize_reader(reader, options).unwrap()
+    }
+
+    /// Assert that lexing `source` through a reader produces the same tokens, errors, warnings
+    /// and symbols as lexing it at once.
+    fn assert_tokenizes_bytewise(source: &str, options: LexerOptions) {
+        let tokenized = tokenize_bytewise(source, options);
+        let mut lexer = Lexer::with_options(source, options);
+        let result = lexer.tokenize();
+
+        assert_eq!(tokenized.result, result, "{:?}", source);
+        assert_eq!(tokenized.warnings, lexer.warnings(), "{:?}", source);
+        for token in tokenized.result.iter().flatten() {
+            if let Some(symbol) = token.symbol {
+                assert_eq!(
+                    tokenized.symbols.resolve(symbol),
+                    Some(token.lexeme.as_ref()),
+                    "{:?}",
+                    source
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_file() {
+        let source = "var größe = 1;\nif (größe >= 2) {\n  print \"big\";\n}\n";
+        let path = std::env::temp_dir().join(format!("spl-test-{}.spl", std::process::id()));
+        std::fs::write(&path, source).unwrap();
+
+        let from_file = Lexer::from_file(&path, LexerOptions::default()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(from_file.result, Lexer::new(source).tokenize());
+        assert_eq!(
+            from_file.symbols.get("größe"),
+            from_file.result.unwrap()[1].symbol
+        );
+
+        // The file is gone by now.
+        let error = Lexer::from_file(&path, LexerOptions::default()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_tokenize_reader() {
+        // Operators and multi-byte characters split across reads are still lexed as one.
+        let source = "var größe = 1;\nif (größe >= 2) {\n  print \"big\";\n}\n";
+        assert_tokenizes_bytewise(source, LexerOptions::default());
+        assert_tokenizes_bytewise(PROGRAM, LexerOptions::default());
+
+        // Invalid UTF-8
+        let reader = BufReader::with_capacity(1, ByteReader(&[b'a', 0xff]));
+        let result = Lexer::tokenize_reader(reader, LexerOptions::default());
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_tokenize_reader_across_lines() {
+        let sources = [
+            // Nothing at all, or only line breaks.
+            "",
+            "\n\n",
+            "a\r\nb\rc\n\r\nd",
+            // Comments and strings spanning lines, as well as identifiers seen again after them.
+            "var a = 1; /* first\nsecond\r\nthird */ print a;\n// line\na = 2;",
+            "/* outer /* nested\n*/ still a comment\n*/ a",
+            "print \"a\nb\";\nprint a;",
+            // Trailing dots and members at the end of a line.
+            "1.\n5;\n1.\nfoo",
+            // Errors on later lines, and ones running up to the end of the input.
+            "a;\nb @ c;\n\n0x;\n\"\\q\"\nclass",
+            "a;\n/* never\nclosed",
+            "a;\n\"never\nclosed",
+            // A last line without any tokens.
+            "a;\n// comment",
+        ];
+
+        for source in sources {
+            assert_tokenizes_bytewise(source, LexerOptions::default());
+        }
+    }
+
+    #[test]
+    fn test_tokenize_reader_options() {
+        // The error limit applies to the whole input, rather than to each line.
+        let source = "@ \n".repeat(150);
+        let errors = tokenize_bytewise(&source, LexerOptions::default())
+            .result
+            .unwrap_err();
+        assert_eq!(errors.len(), 101);
+        assert!(matches!(
+            errors[100],
+            LexerError::TooManyErrors { reported: 100, .. }
+        ));
+
+        let sources = [
+            source.as_str(),
+            "a;\nb @ c;\n\"\\q\"\n@",
+            "WHILE (a) {\n  1.;\n}\n/* a /* b */",
+        ];
+        let dialects = [
+            LexerOptions::default(),
+            LexerOptions {
+                max_errors: None,
+                ..LexerOptions::default()
+            },
+            LexerOptions {
+                max_errors: Some(2),
+                ..LexerOptions::default()
+            },
+            LexerOptions {
+                fail_fast: true,
+                ..LexerOptions::default()
+            },
+            LexerOptions {
+                case_insensitive_keywords: true,
+                nested_block_comments: false,
+                ..LexerOptions::default()
+            },
+        ];
+        for source in sources {
+            for options in dialects {
+                assert_tokenizes_bytewise(source, options);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tokenize_reader_warnings() {
+        let source = "print 1.;\nprint 2;\nprint 3.;";
+        let tokenized = tokenize_bytewise(source, LexerOptions::default());
+
+        assert_eq!(tokenized.warnings.len(), 2);
+        assert_eq!(tokenized.warnings[1].position.line, 3);
+        assert_tokenizes_bytewise(source, LexerOptions::default());
+    }
+
+    #[test]
+    fn test_token_memory() {
+        // The tokens of a million-token input fit into 128 MiB, see the size of `Token`.
+        const BUDGET: usize = 128 << 20;
+
+        let source = "a ".repeat(1_000_000);
+        let tokens = Lexer::new(&source).tokenize().unwrap();
+        assert_eq!(tokens.len(), 1_000_001);
+        assert!(tokens.capacity() * mem::size_of::<Token>() <= BUDGET);
+    }
+
+    #[test]
+    fn test_reset() {
+        let first = "var a = 1;\nprint a;";
+        let second = "while (b) {\n\tb = false;\n}\n\"c\"";
+
+        let mut lex = Lexer::new(first);
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+
+        lex.tokenize_into(&mut tokens, &mut errors);
+        assert!(errors.is_empty());
+        assert_eq!(tokens, Lexer::new(first).tokenize().unwrap());
+
+        lex.reset(second);
+        lex.tokenize_into(&mut tokens, &mut errors);
+        assert!(errors.is_empty());
+
+        let mut fresh = Lexer::new(second);
+        assert_eq!(tokens, fresh.tokenize().unwrap());
+        assert_eq!((lex.line, lex.column), (fresh.line, fresh.column));
+
+        // Errors must not leak into the next run either.
+        lex.reset("@");
+        lex.tokenize_into(&mut tokens, &mut errors);
+        assert_eq!(errors, Lexer::new("@").tokenize().unwrap_err());
+
+        lex.reset(first);
+        lex.tokenize_into(&mut tokens, &mut errors);
+        assert!(errors.is_empty());
+        assert_eq!(tokens, Lexer::new(first).tokenize().unwrap());
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_reset() {
+        use std::time::Instant;
+
+        let input = "var a = 1; while (a < 10) { print a; a = a + 1; }";
+        let iterations = 100_000;
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            let _ = Lexer::new(input).tokenize();
+        }
+        let fresh = start.elapsed();
+
+        let start = Instant::now();
+        let mut lex = Lexer::new(input);
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        for _ in 0..iterations {
+            lex.reset(input);
+            lex.tokenize_into(&mut tokens, &mut errors);
+        }
+        let reused = start.elapsed();
+
+        println!(
+            "Fresh lexers: {:?}, reused lexer and buffers: {:?}",
+            fresh, reused
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_scan_kinds() {
+        use std::time::Instant;
+
+        let input = format!("{}\nvar s = \"escaped \\\"quotes\\\"\";\n", PROGRAM).repeat(1_000);
+        let iterations = 20;
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            let _ = Lexer::new(&input).tokenize();
+        }
+        let tokenize = start.elapsed();
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            let _ = Lexer::new(&input).scan_kinds();
+        }
+        let scan_kinds = start.elapsed();
+
+        println!(
+            "tokenize: {:?}, scan_kinds: {:?}, speedup: {:.2}x",
+            tokenize,
+            scan_kinds,
+            tokenize.as_secs_f64() / scan_kinds.as_secs_f64()
+        );
+    }
+
+    #[test]
+    fn test_identifier_boundaries() {
+        // Identifiers as implied by the character class predicates: maximal runs starting with an
+        // identifier-start character, continued by identifier-continue characters.
+        fn expected_identifiers(input: &str) -> Vec<String> {
+            let mut out = Vec::new();
+            let mut current: Option<String> = None;
+
+            for c in input.chars() {
+                current = match current {
+                    Some(mut name) if char_class::is_ident_continue(c) => {
+                        name.push(c);
+                        Some(name)
+                    }
+                    Some(name) => {
+                        out.push(name);
+                        None
+                    }
+                    None => None,
+                };
+
+                if current.is_none() && char_class::is_ident_start(c) {
+                    current = Some(c.to_string());
+                }
+            }
+            out.extend(current);
+
+            out
+        }
+
+        let corpus = [
+            "foo bar",
+            "a1b2 c3",
+            "12abc",
+            "x+y*z",
+            "if32 while",
+            "straße λx",
+            "a٣b",
+            "snake_case",
+            "_ __ a_1",
+            "e\u{301}",
+            "a\u{a0}b",
+        ];
+
+        for input in corpus {
+            // Errors for characters which can't be lexed are irrelevant here, the tokens are
+            // collected either way.
+            let mut tokens = Vec::new();
+            let mut errors = Vec::new();
+            Lexer::new(input).tokenize_into(&mut tokens, &mut errors);
+
+            let identifiers: Vec<String> = tokens
+                .into_iter()
+                .filter(|t| t.lexeme.starts_with(char_class::is_ident_start))
+                .map(|t| t.lexeme.into_owned())
+                .collect();
+
+            assert_eq!(identifiers, expected_identifiers(input), "{:?}", input);
+        }
+    }
+
+    /// Minimal xorshift PRNG, so that the property tests below are reproducible without
+    /// depending on external crates.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        /// Random ASCII string, biased towards characters which are significant to the lexer.
+        fn ascii_string(&mut self) -> String {
+            const SIGNIFICANT: &[u8] = b"\"\\/*.0123456789_xbeE+-=!<>;(){}[],%#@ \t\r\n";
+
+            (0..self.below(64))
+                .map(|_| {
+                    if self.below(2) == 0 {
+                        SIGNIFICANT[self.below(SIGNIFICANT.len())] as char
+                    } else {
+                        self.below(128) as u8 as char
+                    }
+                })
+                .collect()
+        }
+
+        /// Random UTF-8 string, including multi-byte characters at the edges of the valid ranges.
+        fn utf8_string(&mut self) -> String {
+            const EDGES: &[char] = &[
+                '\u{0}',
+                '\u{7f}',
+                '\u{80}',
+                '\u{a0}',
+                '\u{301}',
+                '\u{7ff}',
+                '\u{800}',
+                '\u{d7ff}',
+                '\u{e000}',
+                '\u{feff}',
+                '\u{ffff}',
+                '\u{10000}',
+                '\u{10ffff}',
+            ];
+
+            (0..self.below(64))
+                .map(|_| match self.below(3) {
+                    0 => EDGES[self.below(EDGES.len())],
+                    1 => self.below(128) as u8 as char,
+                    // Surrogates are no valid chars, in which case we fall back to a replacement.
+                    _ => char::from_u32(self.below(0x11_0000) as u32).unwrap_or('\u{fffd}'),
+                })
+                .collect()
+        }
+    }
+
+    /// Lex `source`, asserting that the lexer terminates without panicking and that its output is
+    /// consistent with the input.
+    fn assert_lexes_consistently(source: &str) {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        // Without an error limit, so that the whole input is always scanned.
+        let options = LexerOptions {
+            max_errors: None,
+            ..LexerOptions::default()
+        };
+        Lexer::with_options(source, options).tokenize_into(&mut tokens, &mut errors);
+
+        // Exactly one EOF token, at the very end.
+        let eof = tokens.last().unwrap();
+        assert_eq!(eof.token_type, TokenType::EndOfFile, "{:?}", source);
+        assert_eq!(
+            eof.span,
+            Span {
+                start: source.len(),
+                end: source.len()
+            }
+        );
+        assert_eq!(
+            tokens
+                .iter()
+                .filter(|t| t.token_type == TokenType::EndOfFile)
+                .count(),
+            1
+        );
+
+        // Tokens cover disjoint, increasing slices of the input.
+        let mut previous_end = 0;
+        let mut lexeme_lengths = 0;
+        for token in &tokens {
+            assert!(
+                token.span.start >= previous_end,
+                "{:?}: {:?}",
+                source,
+                token
+            );
+            assert!(source.get(token.span.start..token.span.end).is_some());
+            previous_end = token.span.end;
+
+            // Other than strings and chars, whose quotes are dropped and escape sequences
+            // decoded - which only ever shortens them - lexemes are the slices of the input they
+            // cover.
+            if !matches!(token.token_type, TokenType::String | TokenType::Char) {
+                assert_eq!(token.lexeme, &source[token.span.start..token.span.end]);
+            }
+            lexeme_lengths += token.lexeme.len();
+        }
+        assert!(lexeme_lengths <= source.len(), "{:?}", source);
+
+        for error in &errors {
+            let span = error.span();
+            assert!(source.get(span.start..span.end).is_some(), "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_random_ascii_input() {
+        let mut rng = Rng(0x5eed_1234_abcd_ef01);
+
+        for _ in 0..5_000 {
+            assert_lexes_consistently(&rng.ascii_string());
+        }
+    }
+
+    #[test]
+    fn test_random_utf8_input() {
+        let mut rng = Rng(0x0123_4567_89ab_cdef);
+
+        for _ in 0..5_000 {
+            assert_lexes_consistently(&rng.utf8_string());
+        }
+    }
+
+    #[test]
+    fn test_pathological_input() {
+        let deep = 100_000;
+
+        for source in [
+            "/*".repeat(deep),
+            format!("{}{}", "/*".repeat(deep), "*/".repeat(deep)),
+            "9".repeat(deep),
+            format!("0x{}", "f".repeat(deep)),
+            "\"".repeat(deep + 1),
+            "\\".repeat(deep),
+            format!("\"{}", "\\".repeat(deep + 1)),
+            "a".repeat(deep),
+            "@".repeat(deep),
+            "\r\n\r".repeat(deep),
+        ] {
+            assert_lexes_consistently(&source);
+        }
+    }
 }
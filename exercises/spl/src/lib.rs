@@ -1,3 +1,21 @@
+//! Lexer, parser and interpreter of SPL, a small scripting language.
+//!
+//! The user-facing API is re-exported by `prelude`. Helpers which only the lexer, parser and
+//! interpreter use internally are private.
+
+pub mod ast;
+pub mod char_class;
+pub mod diagnostics;
+mod environment;
 pub mod error;
+#[cfg(test)]
+mod fixtures;
+mod interner;
+pub mod interpreter;
+pub mod json;
 pub mod lexer;
+pub mod parser;
+pub mod position;
+pub mod prelude;
 pub mod token;
+pub mod visitor;
@@ -0,0 +1,2827 @@
+//! Recursive-descent parser, turning tokens into an abstract syntax tree.
+//!
+//! A program is a sequence of statements:
+//!
+//! ```text
+//! program    → statement* EOF
+//! statement  → varDecl | funDecl | printStmt | ifStmt | whileStmt | forStmt | returnStmt
+//!              | breakStmt | continueStmt | block | exprStmt
+//! varDecl    → "var" IDENTIFIER ( "=" expression )? ";"
+//! funDecl    → "fun" IDENTIFIER "(" ( IDENTIFIER ( "," IDENTIFIER )* )? ")" block
+//! printStmt  → "print" expression ";"
+//! ifStmt     → "if" "(" expression ")" statement ( "else" statement )?
+//! whileStmt  → "while" "(" expression ")" statement
+//! forStmt    → "for" "(" ( varDecl | exprStmt | ";" ) expression? ";" expression? ")" statement
+//! returnStmt → "return" expression? ";"
+//! breakStmt  → "break" ";"
+//! continueStmt → "continue" ";"
+//! block      → "{" statement* "}"
+//! exprStmt   → expression ";"
+//! ```
+//!
+//! An `else` belongs to the nearest `if` it may belong to, so `if (a) if (b) x; else y;` is parsed
+//! as `if (a) { if (b) x; else y; }`.
+//!
+//! There is no syntax tree node for `for` loops, which are desugared into `while` loops instead:
+//! `for (init; condition; increment) body` is parsed as
+//! `{ init; while (condition) { body; increment; } }`, with a missing condition being `true`.
+//! So that `continue` doesn't skip the increment, a body which continues the loop is run by an
+//! inner loop instead, which it leaves by `break`, see `desugar_for`.
+//!
+//! A `return` must be within a function, and a `break` or `continue` within a loop - of the same
+//! function, if any.
+//!
+//! Expressions are parsed by precedence climbing, driven by the tables of operators
+//! `PREFIX_OPERATORS` and `INFIX_OPERATORS`. From loosest to tightest binding, the operators are:
+//!
+//! | Operators              | Associativity |
+//! |------------------------|---------------|
+//! | `=`                    | right         |
+//! | `or`                   | left          |
+//! | `and`                  | left          |
+//! | `==` `!=`              | left          |
+//! | `<` `<=` `>` `>=`      | left          |
+//! | `+` `-`                | left          |
+//! | `*` `/` `%`            | left          |
+//! | `!` `-` (prefix)       |               |
+//! | `(` `)` (call)         | left          |
+//!
+//! Operands are literals, variables, and parenthesized expressions. Calls bind tightest, so
+//! `-f(x)` negates the result of the call, and `f(x)(y)` calls the result of `f(x)`. The left-hand
+//! side of an assignment must be a variable.
+//!
+//! Functions take, and calls pass, at most `MAX_ARITY` parameters and arguments respectively.
+//!
+//! Groupings, operands of prefix operators, assigned values, blocks, and the branches and bodies of
+//! `if` and `while` are nested at most `DEFAULT_MAX_DEPTH` levels deep, see `with_max_depth`. So
+//! are chains of binary operators and calls, such as `1 + 2 + 3`, each operator or call of which is
+//! a level. Deeper input is rejected rather than exhausting the stack, with the too deeply nested
+//! construct being skipped.
+//!
+//! When a statement fails to parse, the error is recorded and the parser skips ahead to the next
+//! statement - past the next `;`, or up to the next token only a statement may start with - so
+//! that a single run reports as many errors as possible.
+
+use crate::{
+    ast::{Expr, Stmt},
+    error::{LexerError, ParserError},
+    position::{Position, Span},
+    token::{Literal, Token, TokenType},
+};
+
+/// Types of the tokens an expression may start with.
+const EXPRESSION_START: &[TokenType] = &[
+    TokenType::Number,
+    TokenType::String,
+    TokenType::Char,
+    TokenType::True,
+    TokenType::False,
+    TokenType::Nil,
+    TokenType::Identifier,
+    TokenType::OpeningParentheses,
+    TokenType::BooleanNot,
+    TokenType::Minus,
+];
+
+/// Types of the tokens a statement may start with, those of expressions included.
+const STATEMENT_START: &[TokenType] = &[
+    TokenType::Var,
+    TokenType::Fun,
+    TokenType::Print,
+    TokenType::If,
+    TokenType::While,
+    TokenType::For,
+    TokenType::Return,
+    TokenType::Break,
+    TokenType::Continue,
+    TokenType::OpeningBraces,
+    TokenType::Number,
+    TokenType::String,
+    TokenType::Char,
+    TokenType::True,
+    TokenType::False,
+    TokenType::Nil,
+    TokenType::Identifier,
+    TokenType::OpeningParentheses,
+    TokenType::BooleanNot,
+    TokenType::Minus,
+];
+
+/// How operators of the same precedence group, e.g. `a - b - c` as `(a - b) - c` if left-, or
+/// `a = b = c` as `a = (b = c)` if right-associative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Associativity {
+    Left,
+    Right,
+}
+
+// Precedences of operators, where higher ones bind tighter.
+const ASSIGNMENT: u8 = 1;
+const OR: u8 = 2;
+const AND: u8 = 3;
+const EQUALITY: u8 = 4;
+const COMPARISON: u8 = 5;
+const TERM: u8 = 6;
+const FACTOR: u8 = 7;
+const UNARY: u8 = 8;
+
+/// Binary operators, and assignment, with their precedence and associativity.
+const INFIX_OPERATORS: &[(TokenType, u8, Associativity)] = &[
+    (TokenType::Equals, ASSIGNMENT, Associativity::Right),
+    (TokenType::Or, OR, Associativity::Left),
+    (TokenType::And, AND, Associativity::Left),
+    (TokenType::DoubleEquals, EQUALITY, Associativity::Left),
+    (TokenType::NotEquals, EQUALITY, Associativity::Left),
+    (TokenType::Less, COMPARISON, Associativity::Left),
+    (TokenType::LessOrEqual, COMPARISON, Associativity::Left),
+    (TokenType::Greater, COMPARISON, Associativity::Left),
+    (TokenType::GreaterOrEqual, COMPARISON, Associativity::Left),
+    (TokenType::Plus, TERM, Associativity::Left),
+    (TokenType::Minus, TERM, Associativity::Left),
+    (TokenType::Times, FACTOR, Associativity::Left),
+    (TokenType::Divide, FACTOR, Associativity::Left),
+    (TokenType::Modulo, FACTOR, Associativity::Left),
+];
+
+/// Prefix operators, with the precedence their operand is parsed at.
+const PREFIX_OPERATORS: &[(TokenType, u8)] =
+    &[(TokenType::BooleanNot, UNARY), (TokenType::Minus, UNARY)];
+
+/// Types of the tokens the parser resumes at after an error, as they start a statement.
+const SYNCHRONIZATION_POINTS: &[TokenType] = &[
+    TokenType::Var,
+    TokenType::Fun,
+    TokenType::Print,
+    TokenType::If,
+    TokenType::While,
+    TokenType::For,
+    TokenType::Return,
+    TokenType::Break,
+    TokenType::Continue,
+    TokenType::OpeningBraces,
+];
+
+/// Maximum number of parameters of a function, and of arguments of a call.
+pub const MAX_ARITY: usize = 255;
+
+/// Default limit of how deeply constructs may be nested.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// What the statement being parsed is within, to validate `return`, `break` and `continue` with.
+#[derive(Debug, Clone, Copy, Default)]
+struct Context {
+    function: bool,
+    in_loop: bool,
+}
+
+/// Parenthesized clauses of a `for` loop, with an empty condition replaced by `true`.
+struct ForClauses {
+    initializer: Option<Box<Stmt>>,
+    condition: Box<Expr>,
+    increment: Option<Box<Expr>>,
+}
+
+/// Source of the tokens to parse, along with the errors of the lexer producing them.
+type TokenStream<'a> = Box<dyn Iterator<Item = Result<Token<'a>, LexerError>> + 'a>;
+
+pub struct Parser<'a> {
+    tokens: TokenStream<'a>,
+    // The next token, which is the only one read ahead. Once it is the EOF token, no further ones
+    // are read.
+    next: Token<'a>,
+    // Number of tokens consumed so far.
+    consumed: usize,
+    // Errors which were recorded without aborting the construct they occurred in, as well as
+    // those of statements which were skipped, and errors of the lexer.
+    errors: Vec<ParserError>,
+    // Number of constructs the parser currently is nested in, and the limit thereof.
+    depth: usize,
+    max_depth: usize,
+    context: Context,
+}
+
+impl<'a> Parser<'a> {
+    /// Create a parser for `tokens`, as returned by `Lexer::tokenize`.
+    ///
+    /// Comment tokens are skipped. If the tokens don't end with an EOF token, one is added.
+    pub fn new(tokens: Vec<Token<'a>>) -> Parser<'a> {
+        Parser::from_stream(tokens.into_iter().map(Ok))
+    }
+
+    /// Create a parser which reads tokens from `tokens` only as it needs them, such as from a
+    /// `Lexer`, without collecting them first.
+    ///
+    /// Errors of the lexer are recorded as `ParserError::Lexer`, with parsing continuing past the
+    /// input they refer to. Comment tokens are skipped. If the tokens don't end with an EOF token,
+    /// one is added.
+    pub fn from_stream<I>(tokens: I) -> Parser<'a>
+    where
+        I: IntoIterator<Item = Result<Token<'a>, LexerError>>,
+        I::IntoIter: 'a,
+    {
+        let mut parser = Parser {
+            tokens: Box::new(tokens.into_iter()),
+            // Placeholder, which an empty input's EOF token is placed at.
+            next: Token {
+                token_type: TokenType::EndOfFile,
+                lexeme: "".into(),
+                start: Position::default(),
+                end: Position::default(),
+                span: Span { start: 0, end: 0 },
+                symbol: None,
+                literal: None,
+            },
+            consumed: 0,
+            errors: Vec::new(),
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            context: Context::default(),
+        };
+        parser.next = parser.read();
+
+        parser
+    }
+
+    /// Configure how deeply constructs may be nested, such as `((1))` or `{ { } }` two levels
+    /// deep. Exceeding it fails with `ParserError::NestingTooDeep`.
+    ///
+    /// Each level takes up stack space, so the limit must be low enough for the stack the parser
+    /// runs on. The default fits into the 2 MiB of a spawned thread, even unoptimized.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Parser<'a> {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Parse all tokens as a program, that is a sequence of statements.
+    ///
+    /// Returns all errors encountered, see `parse_program_into`.
+    pub fn parse_program(&mut self) -> Result<Vec<Stmt>, Vec<ParserError>> {
+        let mut statements = Vec::new();
+        let mut errors = Vec::new();
+
+        self.parse_program_into(&mut statements, &mut errors);
+
+        if errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Parse the remaining tokens as a program into caller-provided buffers.
+    ///
+    /// Statements which fail to parse are skipped, with their errors added to `errors`. All other
+    /// statements are added to `statements`, so that they can be inspected even if parsing failed
+    /// - which is the case if `errors` is not empty afterwards. Both buffers are cleared first.
+    pub fn parse_program_into(
+        &mut self,
+        statements: &mut Vec<Stmt>,
+        errors: &mut Vec<ParserError>,
+    ) {
+        statements.clear();
+        errors.clear();
+
+        while !self.check(TokenType::EndOfFile) {
+            if let Some(statement) = self.statement_or_synchronize() {
+                statements.push(*statement);
+            }
+        }
+
+        errors.append(&mut self.errors);
+    }
+
+    /// Parse all tokens as a single expression.
+    pub fn parse_expression(&mut self) -> Result<Expr, ParserError> {
+        let result = self.expression().and_then(|expr| {
+            if self.check(TokenType::EndOfFile) {
+                Ok(expr)
+            } else {
+                Err(self.error(&[TokenType::EndOfFile]))
+            }
+        });
+
+        // Recorded errors occurred before the one which aborted parsing, if any.
+        if !self.errors.is_empty() {
+            return Err(self.errors.remove(0));
+        }
+
+        result
+    }
+
+    /// Parse a statement. If that fails, record the error and skip to the start of the next
+    /// statement, see `synchronize`.
+    fn statement_or_synchronize(&mut self) -> Option<Box<Stmt>> {
+        let start = self.consumed;
+        // Constructs restore the depth and context they change only once they are parsed, as an
+        // error aborts the whole statement anyway.
+        let (depth, context) = (self.depth, self.context);
+
+        match self.statement() {
+            Ok(statement) => Some(statement),
+            Err(e) => {
+                self.errors.push(e);
+                self.depth = depth;
+                self.context = context;
+
+                // A statement failing on its very first token would otherwise be retried forever. If
+                // that token is a stray `;`, the next statement starts right after it.
+                if self.consumed == start && self.advance().token_type == TokenType::Semicolon {
+                    return None;
+                }
+                self.synchronize();
+
+                None
+            }
+        }
+    }
+
+    /// Skip tokens up to the start of the next statement, that is past the next `;`, or up to the
+    /// next synchronization point, whichever comes first.
+    ///
+    /// Stops at a `}` as well, so that the enclosing block can be closed.
+    fn synchronize(&mut self) {
+        while !self.check(TokenType::EndOfFile) {
+            let token_type = self.peek().token_type;
+            if SYNCHRONIZATION_POINTS.contains(&token_type)
+                || token_type == TokenType::ClosingBraces
+            {
+                return;
+            }
+
+            if self.advance().token_type == TokenType::Semicolon {
+                return;
+            }
+        }
+    }
+
+    fn statement(&mut self) -> Result<Box<Stmt>, ParserError> {
+        match self.peek().token_type {
+            TokenType::Var => self.var_declaration(),
+            TokenType::Fun => self.function_declaration(),
+            TokenType::Print => self.print_statement(),
+            TokenType::If => self.if_statement(),
+            TokenType::While => self.while_statement(),
+            TokenType::For => self.for_statement(),
+            TokenType::Return => self.return_statement(),
+            TokenType::Break | TokenType::Continue => self.loop_control(),
+            TokenType::OpeningBraces => self.block(),
+            t if EXPRESSION_START.contains(&t) => self.expression_statement(),
+            _ => Err(self.error(STATEMENT_START)),
+        }
+    }
+
+    fn var_declaration(&mut self) -> Result<Box<Stmt>, ParserError> {
+        let keyword = self.advance();
+        let name = self.expect(TokenType::Identifier)?;
+
+        let initializer = match self.advance_if(|t| *t == TokenType::Equals) {
+            Some(_) => Some(self.expression()?),
+            None if !self.check(TokenType::Semicolon) => {
+                return Err(self.error(&[TokenType::Equals, TokenType::Semicolon]));
+            }
+            None => None,
+        };
+        let semicolon = self.expect(TokenType::Semicolon)?;
+
+        Ok(Box::new(Stmt::Var {
+            name,
+            initializer,
+            span: keyword.span.to(semicolon.span),
+        }))
+    }
+
+    fn function_declaration(&mut self) -> Result<Box<Stmt>, ParserError> {
+        let keyword = self.advance();
+        let name = self.expect(TokenType::Identifier)?;
+        let params = self.parameters()?;
+
+        if !self.check(TokenType::OpeningBraces) {
+            return Err(self.error(&[TokenType::OpeningBraces]));
+        }
+        // Loops around the declaration can't be broken out of from within the function.
+        let context = Context {
+            function: true,
+            ..Context::default()
+        };
+        let outer = std::mem::replace(&mut self.context, context);
+        let (body, body_span) = self.braced()?;
+        self.context = outer;
+
+        Ok(Box::new(Stmt::Function {
+            name,
+            params,
+            body,
+            span: keyword.span.to(body_span),
+        }))
+    }
+
+    /// Parse the parenthesized parameters of a function.
+    fn parameters(&mut self) -> Result<Vec<Token<'static>>, ParserError> {
+        self.expect(TokenType::OpeningParentheses)?;
+
+        let mut params = Vec::new();
+        if self
+            .advance_if(|t| *t == TokenType::ClosingParentheses)
+            .is_some()
+        {
+            return Ok(params);
+        }
+
+        loop {
+            let param = self.expect(TokenType::Identifier)?;
+            if params.len() == MAX_ARITY {
+                self.errors.push(ParserError::TooManyParameters {
+                    position: param.start,
+                    limit: MAX_ARITY,
+                    span: param.span,
+                });
+            }
+            params.push(param);
+
+            if self.advance_if(|t| *t == TokenType::Comma).is_none() {
+                break;
+            }
+        }
+        if self
+            .advance_if(|t| *t == TokenType::ClosingParentheses)
+            .is_none()
+        {
+            return Err(self.error(&[TokenType::Comma, TokenType::ClosingParentheses]));
+        }
+
+        Ok(params)
+    }
+
+    fn print_statement(&mut self) -> Result<Box<Stmt>, ParserError> {
+        let keyword = self.advance();
+        let expr = self.expression()?;
+        let semicolon = self.expect(TokenType::Semicolon)?;
+
+        Ok(Box::new(Stmt::Print {
+            span: keyword.span.to(semicolon.span),
+            keyword,
+            expr,
+        }))
+    }
+
+    fn if_statement(&mut self) -> Result<Box<Stmt>, ParserError> {
+        let keyword = self.advance();
+        let condition = self.condition()?;
+        self.descend(&keyword)?;
+        let then_branch = self.statement()?;
+        self.ascend();
+
+        // Binding the `else` right away, to the innermost `if`, resolves the dangling else.
+        let else_branch = match self.advance_if(|t| *t == TokenType::Else) {
+            Some(else_keyword) => {
+                self.descend(&else_keyword)?;
+                let else_branch = self.statement()?;
+                self.ascend();
+                Some(else_branch)
+            }
+            None => None,
+        };
+
+        // Covers the else branch, if any.
+        let end = else_branch.as_ref().unwrap_or(&then_branch).span();
+
+        Ok(Box::new(Stmt::If {
+            span: keyword.span.to(end),
+            keyword,
+            condition: *condition,
+            then_branch,
+            else_branch,
+        }))
+    }
+
+    fn while_statement(&mut self) -> Result<Box<Stmt>, ParserError> {
+        let keyword = self.advance();
+        let condition = self.condition()?;
+        let body = self.loop_body(&keyword)?;
+
+        Ok(Box::new(Stmt::While {
+            span: keyword.span.to(body.span()),
+            keyword,
+            condition: *condition,
+            body,
+        }))
+    }
+
+    /// Parse a `for` loop, desugared into a `while` loop, see the module documentation.
+    fn for_statement(&mut self) -> Result<Box<Stmt>, ParserError> {
+        let keyword = self.advance();
+        let clauses = self.for_clauses()?;
+        let body = self.loop_body(&keyword)?;
+
+        Ok(desugar_for(keyword, clauses, body))
+    }
+
+    /// Parse the parenthesized clauses of a `for` loop.
+    fn for_clauses(&mut self) -> Result<ForClauses, ParserError> {
+        self.expect(TokenType::OpeningParentheses)?;
+
+        let initializer = match self.peek().token_type {
+            TokenType::Semicolon => {
+                self.advance();
+                None
+            }
+            TokenType::Var => Some(self.var_declaration()?),
+            _ => Some(self.expression_statement()?),
+        };
+
+        let condition = self.for_condition()?;
+
+        let increment = if self.check(TokenType::ClosingParentheses) {
+            None
+        } else {
+            Some(self.expression_with_precedence(ASSIGNMENT)?)
+        };
+        self.expect(TokenType::ClosingParentheses)?;
+
+        Ok(ForClauses {
+            initializer,
+            condition,
+            increment,
+        })
+    }
+
+    /// Parse the condition of a `for` loop, up to and including the `;` following it.
+    fn for_condition(&mut self) -> Result<Box<Expr>, ParserError> {
+        let Some(semicolon) = self.advance_if(|t| *t == TokenType::Semicolon) else {
+            let condition = self.expression_with_precedence(ASSIGNMENT)?;
+            self.expect(TokenType::Semicolon)?;
+            return Ok(condition);
+        };
+
+        // An empty condition at the `;`, which the `true` replacing it is placed at.
+        let span = Span {
+            start: semicolon.span.start,
+            end: semicolon.span.start,
+        };
+
+        Ok(Box::new(Expr::Literal {
+            token: Token {
+                token_type: TokenType::True,
+                lexeme: "true".into(),
+                span,
+                symbol: None,
+                literal: Some(Literal::Bool(true)),
+                ..semicolon
+            },
+            span,
+        }))
+    }
+
+    /// Parse the body of the loop starting at `keyword`, which is nested one level deeper.
+    fn loop_body(&mut self, keyword: &Token<'a>) -> Result<Box<Stmt>, ParserError> {
+        let context = Context {
+            in_loop: true,
+            ..self.context
+        };
+        let outer = std::mem::replace(&mut self.context, context);
+        self.descend(keyword)?;
+        let body = self.statement()?;
+        self.ascend();
+        self.context = outer;
+
+        Ok(body)
+    }
+
+    fn return_statement(&mut self) -> Result<Box<Stmt>, ParserError> {
+        let keyword = self.advance();
+        if !self.context.function {
+            self.errors.push(ParserError::ReturnOutsideFunction {
+                position: keyword.start,
+                span: keyword.span,
+            });
+        }
+
+        let value = if self.check(TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        let semicolon = self.expect(TokenType::Semicolon)?;
+
+        Ok(Box::new(Stmt::Return {
+            span: keyword.span.to(semicolon.span),
+            keyword,
+            value,
+        }))
+    }
+
+    /// Parse a `break` or `continue`.
+    fn loop_control(&mut self) -> Result<Box<Stmt>, ParserError> {
+        let keyword = self.advance();
+        if !self.context.in_loop {
+            let (position, span) = (keyword.start, keyword.span);
+            self.errors.push(match keyword.token_type {
+                TokenType::Break => ParserError::BreakOutsideLoop { position, span },
+                _ => ParserError::ContinueOutsideLoop { position, span },
+            });
+        }
+        let semicolon = self.expect(TokenType::Semicolon)?;
+        let span = keyword.span.to(semicolon.span);
+
+        Ok(Box::new(match keyword.token_type {
+            TokenType::Break => Stmt::Break { keyword, span },
+            _ => Stmt::Continue { keyword, span },
+        }))
+    }
+
+    /// Parenthesized condition of an `if` or `while`.
+    fn condition(&mut self) -> Result<Box<Expr>, ParserError> {
+        let opening = self.expect(TokenType::OpeningParentheses)?;
+        let condition = self.expression_with_precedence(ASSIGNMENT)?;
+
+        // Pointing at the `(` rather than at whatever followed the condition makes it easier to
+        // find the mismatch.
+        if !self.check(TokenType::ClosingParentheses) {
+            return Err(ParserError::UnclosedDelimiter {
+                opening: Box::new(opening),
+            });
+        }
+        self.advance();
+
+        Ok(condition)
+    }
+
+    fn block(&mut self) -> Result<Box<Stmt>, ParserError> {
+        let (statements, span) = self.braced()?;
+
+        Ok(Box::new(Stmt::Block { statements, span }))
+    }
+
+    /// Statements enclosed in braces, which the next token must open, along with the span of the
+    /// braces.
+    fn braced(&mut self) -> Result<(Vec<Stmt>, Span), ParserError> {
+        let opening = self.advance();
+
+        self.descend(&opening)?;
+        let mut statements = Vec::new();
+        while !self.check(TokenType::ClosingBraces) && !self.check(TokenType::EndOfFile) {
+            if let Some(statement) = self.statement_or_synchronize() {
+                statements.push(*statement);
+            }
+        }
+        self.ascend();
+        let closing = self.expect(TokenType::ClosingBraces)?;
+
+        Ok((statements, opening.span.to(closing.span)))
+    }
+
+    fn expression_statement(&mut self) -> Result<Box<Stmt>, ParserError> {
+        let expr = self.expression()?;
+        let semicolon = self.expect(TokenType::Semicolon)?;
+
+        Ok(Box::new(Stmt::Expression {
+            span: expr.span().to(semicolon.span),
+            expr,
+        }))
+    }
+
+    fn expression(&mut self) -> Result<Expr, ParserError> {
+        Ok(*self.expression_with_precedence(ASSIGNMENT)?)
+    }
+
+    /// Parse an expression whose operators - outside of parentheses - have a precedence of at
+    /// least `min_precedence`.
+    ///
+    /// Each infix operator nests the expression so far one level deeper, up to the end of the
+    /// expression.
+    fn expression_with_precedence(&mut self, min_precedence: u8) -> Result<Box<Expr>, ParserError> {
+        let depth = self.depth;
+        let mut left = match PREFIX_OPERATORS
+            .iter()
+            .find(|(token_type, _)| self.check(*token_type))
+        {
+            Some(&(_, precedence)) => self.unary(precedence)?,
+            None => self.call()?,
+        };
+
+        while let Some(&(_, precedence, associativity)) = INFIX_OPERATORS
+            .iter()
+            .find(|(token_type, _, _)| self.check(*token_type))
+        {
+            if precedence < min_precedence {
+                break;
+            }
+
+            // The right operand of a left-associative operator must not contain operators of the
+            // same precedence, which instead take the whole expression so far as left operand.
+            left = match associativity {
+                Associativity::Left => self.infix(left, precedence + 1)?,
+                Associativity::Right => self.infix(left, precedence)?,
+            };
+        }
+        self.depth = depth;
+
+        Ok(left)
+    }
+
+    /// Parse the infix operator following `left`, and its right operand, whose operators have a
+    /// precedence of at least `min_precedence`.
+    fn infix(&mut self, left: Box<Expr>, min_precedence: u8) -> Result<Box<Expr>, ParserError> {
+        let operator = self.advance();
+        if operator.token_type == TokenType::Equals {
+            return self.assignment(&left, operator, min_precedence);
+        }
+
+        self.descend(&operator)?;
+        let right = self.expression_with_precedence(min_precedence)?;
+
+        Ok(Box::new(Expr::Binary {
+            span: left.span().to(right.span()),
+            left,
+            operator,
+            right,
+        }))
+    }
+
+    /// Parse the value assigned to `target`, whose `=` was consumed already.
+    fn assignment(
+        &mut self,
+        target: &Expr,
+        equals: Token<'static>,
+        min_precedence: u8,
+    ) -> Result<Box<Expr>, ParserError> {
+        // Only a bare identifier may be assigned to, which rules out e.g. `(a) = 1`. The error is
+        // recorded before parsing the right-hand side, which is parsed anyway, so that errors in it
+        // are reported too - and in order.
+        let name = match target {
+            Expr::Variable { name, .. } => Some(name.clone()),
+            _ => {
+                self.errors.push(ParserError::InvalidAssignmentTarget {
+                    position: equals.start,
+                    span: equals.span,
+                });
+                None
+            }
+        };
+        self.descend(&equals)?;
+        let value = self.expression_with_precedence(min_precedence)?;
+        self.ascend();
+
+        Ok(match name {
+            Some(name) => Box::new(Expr::Assign {
+                span: name.span.to(value.span()),
+                name,
+                value,
+            }),
+            None => value,
+        })
+    }
+
+    /// Parse a prefix operator applied to its operand, which is parsed at `precedence`.
+    fn unary(&mut self, precedence: u8) -> Result<Box<Expr>, ParserError> {
+        let operator = self.advance();
+        self.descend(&operator)?;
+        let operand = self.expression_with_precedence(precedence)?;
+        self.ascend();
+
+        Ok(Box::new(Expr::Unary {
+            span: operator.span.to(operand.span()),
+            operator,
+            operand,
+        }))
+    }
+
+    /// Parse an operand, that is a literal, variable or grouping, followed by any number of calls
+    /// of it.
+    ///
+    /// Each call nests the expression so far one level deeper, up to the end of the operand.
+    fn call(&mut self) -> Result<Box<Expr>, ParserError> {
+        let depth = self.depth;
+        let mut expr = match self.peek().token_type {
+            TokenType::Number
+            | TokenType::String
+            | TokenType::Char
+            | TokenType::True
+            | TokenType::False
+            | TokenType::Nil => self.literal()?,
+            TokenType::Identifier => self.variable(),
+            TokenType::OpeningParentheses => self.grouping()?,
+            _ => return Err(self.error(EXPRESSION_START)),
+        };
+
+        while self.check(TokenType::OpeningParentheses) {
+            expr = self.arguments(expr)?;
+        }
+        self.depth = depth;
+
+        Ok(expr)
+    }
+
+    /// Parse the parenthesized arguments `callee` is called with.
+    fn arguments(&mut self, callee: Box<Expr>) -> Result<Box<Expr>, ParserError> {
+        let opening = self.advance();
+        self.descend(&opening)?;
+
+        let mut args = Vec::new();
+        if !self.check(TokenType::ClosingParentheses) {
+            loop {
+                if args.len() == MAX_ARITY {
+                    self.errors.push(ParserError::TooManyArguments {
+                        position: self.peek().start,
+                        limit: MAX_ARITY,
+                        span: self.peek().span,
+                    });
+                }
+                args.push(*self.expression_with_precedence(ASSIGNMENT)?);
+
+                if self.advance_if(|t| *t == TokenType::Comma).is_none() {
+                    break;
+                }
+            }
+            if !self.check(TokenType::ClosingParentheses) {
+                return Err(self.error(&[TokenType::Comma, TokenType::ClosingParentheses]));
+            }
+        }
+        let closing_paren = self.advance();
+
+        Ok(Box::new(Expr::Call {
+            span: callee.span().to(closing_paren.span),
+            callee,
+            args,
+            closing_paren,
+        }))
+    }
+
+    fn literal(&mut self) -> Result<Box<Expr>, ParserError> {
+        let token = self.peek();
+
+        // Tokens which weren't produced by the lexer may lack their value.
+        let value = token
+            .literal
+            .clone()
+            .or_else(|| Literal::from_lexeme(token.token_type, &token.lexeme))
+            .ok_or_else(|| self.error(EXPRESSION_START))?;
+
+        let token = Token {
+            literal: Some(value),
+            ..self.advance()
+        };
+
+        Ok(Box::new(Expr::Literal {
+            span: token.span,
+            token,
+        }))
+    }
+
+    fn variable(&mut self) -> Box<Expr> {
+        let name = self.advance();
+
+        Box::new(Expr::Variable {
+            span: name.span,
+            name,
+        })
+    }
+
+    /// Parse an expression in parentheses, which is nested one level deeper.
+    fn grouping(&mut self) -> Result<Box<Expr>, ParserError> {
+        let opening = self.advance();
+        self.descend(&opening)?;
+        let expr = self.expression_with_precedence(ASSIGNMENT)?;
+        self.ascend();
+        let closing = self.expect(TokenType::ClosingParentheses)?;
+
+        Ok(Box::new(Expr::Grouping {
+            expr,
+            span: opening.span.to(closing.span),
+        }))
+    }
+
+    /// Go one level deeper, into the construct starting at `token`, until the matching `ascend`.
+    /// If that exceeds the limit, the rest of the construct is skipped, so that the limit is
+    /// reported once rather than for each of the tokens nested too deeply.
+    ///
+    /// Constructs are parsed by plain recursion, without closures, to keep the stack each level
+    /// takes up small.
+    fn descend(&mut self, token: &Token<'a>) -> Result<(), ParserError> {
+        if self.depth >= self.max_depth {
+            self.skip_nested(token);
+            return Err(ParserError::NestingTooDeep {
+                position: token.start,
+                limit: self.max_depth,
+                span: token.span,
+            });
+        }
+        self.depth += 1;
+
+        Ok(())
+    }
+
+    /// Go back up the level the last `descend` went down.
+    fn ascend(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Skip the construct starting at `token`, which was consumed already.
+    ///
+    /// If `token` opens parentheses or braces, that is up to and including the matching closing
+    /// one. Otherwise, it is up to the end of the statement, or a closing `)` or `}` of an
+    /// enclosing construct, with anything in balanced parentheses and braces being skipped too.
+    fn skip_nested(&mut self, token: &Token<'a>) {
+        let opening = matches!(
+            token.token_type,
+            TokenType::OpeningParentheses | TokenType::OpeningBraces
+        );
+        let mut open = usize::from(opening);
+
+        while !self.check(TokenType::EndOfFile) {
+            match self.peek().token_type {
+                TokenType::OpeningParentheses | TokenType::OpeningBraces => open += 1,
+                TokenType::ClosingParentheses | TokenType::ClosingBraces if open == 0 => return,
+                TokenType::ClosingParentheses | TokenType::ClosingBraces => {
+                    open -= 1;
+                    let closing = self.advance();
+                    if open == 0 && (opening || closing.token_type == TokenType::ClosingBraces) {
+                        return;
+                    }
+                    continue;
+                }
+                TokenType::Semicolon if open == 0 => return,
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    /// The next token, without consuming it.
+    fn peek(&self) -> &Token<'a> {
+        &self.next
+    }
+
+    fn check(&self, token_type: TokenType) -> bool {
+        self.peek().token_type == token_type
+    }
+
+    /// Consume the next token, returning an owned copy of it for the syntax tree.
+    ///
+    /// The EOF token is never consumed, but returned again and again.
+    fn advance(&mut self) -> Token<'static> {
+        if self.next.token_type == TokenType::EndOfFile {
+            return self.next.clone().into_owned();
+        }
+
+        let next = self.read();
+        self.consumed += 1;
+
+        std::mem::replace(&mut self.next, next).into_owned()
+    }
+
+    /// Read the token following `self.next` from the stream, recording errors of the lexer on the
+    /// way.
+    fn read(&mut self) -> Token<'a> {
+        loop {
+            match self.tokens.next() {
+                Some(Ok(token)) if token.token_type == TokenType::Comment => continue,
+                Some(Ok(token)) => return token,
+                Some(Err(e)) => self.errors.push(ParserError::Lexer(e)),
+                // The stream lacks an EOF token, which is placed at the end of the last token.
+                None => {
+                    return Token {
+                        token_type: TokenType::EndOfFile,
+                        lexeme: "".into(),
+                        start: self.next.end,
+                        end: self.next.end,
+                        span: Span {
+                            start: self.next.span.end,
+                            end: self.next.span.end,
+                        },
+                        symbol: None,
+                        literal: None,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Consume the next token if `f` holds for its type.
+    fn advance_if(&mut self, f: fn(&TokenType) -> bool) -> Option<Token<'static>> {
+        if f(&self.peek().token_type) {
+            Some(self.advance())
+        } else {
+            None
+        }
+    }
+
+    /// Consume the next token if it is of type `token_type`, or return an error saying that it
+    /// was expected otherwise.
+    fn expect(&mut self, token_type: TokenType) -> Result<Token<'static>, ParserError> {
+        if self.check(token_type) {
+            Ok(self.advance())
+        } else {
+            Err(self.error(&[token_type]))
+        }
+    }
+
+    /// Error for the next token, which isn't any of the `expected` ones.
+    fn error(&self, expected: &[TokenType]) -> ParserError {
+        let token = self.peek();
+        let expected = expected.to_vec();
+
+        if token.token_type == TokenType::EndOfFile {
+            ParserError::UnexpectedEndOfInput {
+                expected,
+                position: token.start,
+                span: token.span,
+            }
+        } else {
+            ParserError::UnexpectedToken {
+                expected,
+                found: Box::new(token.clone().into_owned()),
+            }
+        }
+    }
+}
+
+/// Name of the variable which a desugared `for` loop records a `break` of its body in, see
+/// `desugar_for`. Not being an identifier, it can't clash with the variables of the program.
+const BROKE_OUT: &str = "for-break";
+
+/// Assemble the `while` loop a `for` loop is desugared into, see the module documentation.
+///
+/// The increment runs after the body, outside of its scope, so that it updates the loop's
+/// variable even if the body declares one of the same name. If the body continues the loop, it is
+/// run by an inner loop, which its `continue` statements leave by `break` to reach the increment:
+///
+/// ```text
+/// {
+///     init;
+///     while (condition) {
+///         var for-break = false;
+///         while (true) { body; break; }
+///         if (for-break) break;
+///         increment;
+///     }
+/// }
+/// ```
+///
+/// A `break` of the body then records in `for-break` that it breaks out of the outer loop too.
+fn desugar_for(keyword: Token<'static>, clauses: ForClauses, mut body: Box<Stmt>) -> Box<Stmt> {
+    let ForClauses {
+        initializer,
+        condition,
+        increment,
+    } = clauses;
+    let span = keyword.span.to(body.span());
+
+    if let Some(increment) = increment {
+        let span = increment.span().to(body.span());
+        let increment = Stmt::Expression {
+            span: increment.span(),
+            expr: *increment,
+        };
+
+        let statements = if continues(&body) {
+            redirect_loop_control(&mut body);
+            let body_span = body.span();
+
+            vec![
+                Stmt::Var {
+                    name: synthetic_token(TokenType::Identifier, BROKE_OUT, &keyword),
+                    initializer: Some(synthetic_literal(TokenType::False, "false", &keyword)),
+                    span: keyword.span,
+                },
+                Stmt::While {
+                    keyword: keyword.clone(),
+                    condition: synthetic_literal(TokenType::True, "true", &keyword),
+                    body: Box::new(Stmt::Block {
+                        statements: vec![*body, synthetic_break(&keyword)],
+                        span: body_span,
+                    }),
+                    span: body_span,
+                },
+                Stmt::If {
+                    keyword: synthetic_token(TokenType::If, "if", &keyword),
+                    condition: Expr::Variable {
+                        name: synthetic_token(TokenType::Identifier, BROKE_OUT, &keyword),
+                        span: keyword.span,
+                    },
+                    then_branch: Box::new(synthetic_break(&keyword)),
+                    else_branch: None,
+                    span: keyword.span,
+                },
+                increment,
+            ]
+        } else if matches!(*body, Stmt::Var { .. } | Stmt::Function { .. }) {
+            // A declaration, which gets a scope of its own.
+            let body_span = body.span();
+            vec![
+                Stmt::Block {
+                    statements: vec![*body],
+                    span: body_span,
+                },
+                increment,
+            ]
+        } else {
+            vec![*body, increment]
+        };
+
+        body = Box::new(Stmt::Block { statements, span });
+    }
+
+    let statement = Stmt::While {
+        keyword,
+        condition: *condition,
+        body,
+        span,
+    };
+
+    Box::new(match initializer {
+        // Scopes the initializer's variable to the loop.
+        Some(initializer) => Stmt::Block {
+            statements: vec![*initializer, statement],
+            span,
+        },
+        None => statement,
+    })
+}
+
+/// Whether `statement` contains a `continue` of the loop it is the body of, rather than of a loop
+/// nested in it.
+fn continues(statement: &Stmt) -> bool {
+    match statement {
+        Stmt::Continue { .. } => true,
+        Stmt::If {
+            then_branch,
+            else_branch,
+            ..
+        } => continues(then_branch) || else_branch.as_deref().is_some_and(continues),
+        Stmt::Block { statements, .. } => statements.iter().any(continues),
+        // Loop control doesn't reach out of nested loops and functions.
+        _ => false,
+    }
+}
+
+/// Make the `break` and `continue` statements of the loop `statement` is the body of leave the
+/// inner loop of a desugared `for` loop instead, see `desugar_for`.
+fn redirect_loop_control(statement: &mut Stmt) {
+    match statement {
+        Stmt::Continue { keyword, .. } => *statement = synthetic_break(keyword),
+        Stmt::Break { keyword, span } => {
+            let record = Expr::Assign {
+                name: synthetic_token(TokenType::Identifier, BROKE_OUT, keyword),
+                value: Box::new(synthetic_literal(TokenType::True, "true", keyword)),
+                span: *span,
+            };
+            *statement = Stmt::Block {
+                statements: vec![
+                    Stmt::Expression {
+                        expr: record,
+                        span: *span,
+                    },
+                    synthetic_break(keyword),
+                ],
+                span: *span,
+            };
+        }
+        Stmt::If {
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            redirect_loop_control(then_branch);
+            if let Some(else_branch) = else_branch {
+                redirect_loop_control(else_branch);
+            }
+        }
+        Stmt::Block { statements, .. } => statements.iter_mut().for_each(redirect_loop_control),
+        _ => {}
+    }
+}
+
+/// Token of the given type, which isn't part of the source, but is placed at `at` for the nodes a
+/// `for` loop is desugared into.
+fn synthetic_token(
+    token_type: TokenType,
+    lexeme: &'static str,
+    at: &Token<'static>,
+) -> Token<'static> {
+    Token {
+        token_type,
+        lexeme: lexeme.into(),
+        symbol: None,
+        literal: Literal::from_lexeme(token_type, lexeme),
+        ..at.clone()
+    }
+}
+
+/// Literal which isn't part of the source, see `synthetic_token`.
+fn synthetic_literal(token_type: TokenType, lexeme: &'static str, at: &Token<'static>) -> Expr {
+    Expr::Literal {
+        token: synthetic_token(token_type, lexeme, at),
+        span: at.span,
+    }
+}
+
+/// `break` which isn't part of the source, see `synthetic_token`.
+fn synthetic_break(at: &Token<'static>) -> Stmt {
+    Stmt::Break {
+        keyword: synthetic_token(TokenType::Break, "break", at),
+        span: at.span,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::AstPrinter, fixtures::PROGRAM, lexer::Lexer};
+
+    /// Description of `EXPRESSION_START` in error messages.
+    const EXPRESSION_ALTERNATIVES: &str =
+        "number, string, char, 'true', 'false', 'nil', identifier, '(', '!' or '-'";
+
+    /// Description of `STATEMENT_START` in error messages.
+    const STATEMENT_ALTERNATIVES: &str = "'var', 'fun', 'print', 'if', 'while', 'for', \
+        'return', 'break', 'continue', '{', number, string, char, 'true', 'false', 'nil', identifier, '(', '!' or '-'";
+
+    fn parse(source: &str) -> Result<Expr, ParserError> {
+        Parser::new(Lexer::new(source).tokenize().unwrap()).parse_expression()
+    }
+
+    fn parse_program(source: &str) -> Result<Vec<Stmt>, Vec<ParserError>> {
+        Parser::new(Lexer::new(source).tokenize().unwrap()).parse_program()
+    }
+
+    /// The single error reported for `source`.
+    fn program_error(source: &str) -> ParserError {
+        let mut errors = parse_program(source).unwrap_err();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        errors.remove(0)
+    }
+
+    /// Fully parenthesized form of `expr`, to assert its structure with.
+    fn sexpr(expr: &Expr) -> String {
+        match expr {
+            Expr::Literal { token, .. } => token.lexeme.to_string(),
+            Expr::Variable { name, .. } => name.lexeme.to_string(),
+            Expr::Assign { name, value, .. } => format!("(= {} {})", name.lexeme, sexpr(value)),
+            // Transparent, as the output is fully parenthesized anyway.
+            Expr::Grouping { expr, .. } => sexpr(expr),
+            Expr::Unary {
+                operator, operand, ..
+            } => {
+                format!("({} {})", operator.lexeme, sexpr(operand))
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => format!("({} {} {})", operator.lexeme, sexpr(left), sexpr(right)),
+            Expr::Call { callee, args, .. } => {
+                let mut out = format!("(call {}", sexpr(callee));
+                for arg in args {
+                    out.push(' ');
+                    out.push_str(&sexpr(arg));
+                }
+                out.push(')');
+                out
+            }
+        }
+    }
+
+    fn assert_parses_as(source: &str, expected: &str) {
+        assert_eq!(sexpr(&parse(source).unwrap()), expected, "{:?}", source);
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_parses_as("1 + 2 * 3", "(+ 1 (* 2 3))");
+        assert_parses_as("1 * 2 + 3", "(+ (* 1 2) 3)");
+        assert_parses_as("1 + 2 % 3 - 4 / 5", "(- (+ 1 (% 2 3)) (/ 4 5))");
+        assert_parses_as("a < b == c >= d", "(== (< a b) (>= c d))");
+        assert_parses_as("a or b and c", "(or a (and b c))");
+        assert_parses_as("a == b and c != d or e", "(or (and (== a b) (!= c d)) e)");
+        assert_parses_as("!a == -b", "(== (! a) (- b))");
+        assert_parses_as("-a * b", "(* (- a) b)");
+    }
+
+    #[test]
+    fn test_unary() {
+        assert_parses_as("-5", "(- 5)");
+        assert_parses_as("!done", "(! done)");
+        assert_parses_as("-a * b", "(* (- a) b)");
+        assert_parses_as("a * -b", "(* a (- b))");
+        assert_parses_as("-a - -b", "(- (- a) (- b))");
+        assert_parses_as("!a == b", "(== (! a) b)");
+        assert_parses_as("!(a == b)", "(! (== a b))");
+        assert_parses_as("!a and !b", "(and (! a) (! b))");
+        assert_parses_as("-a < b", "(< (- a) b)");
+
+        // Nested, with or without whitespace in between.
+        assert_parses_as("--a", "(- (- a))");
+        assert_parses_as("- -1", "(- (- 1))");
+        assert_parses_as("!!!a", "(! (! (! a)))");
+        assert_parses_as("-!a", "(- (! a))");
+
+        match &parse("- -1").unwrap() {
+            Expr::Unary {
+                operator, operand, ..
+            } => {
+                assert_eq!(operator.token_type, TokenType::Minus);
+                assert_eq!(operator.start, Position { line: 1, column: 1 });
+                assert!(matches!(
+                    **operand,
+                    Expr::Unary { ref operator, .. }
+                        if operator.start == Position { line: 1, column: 3 }
+                ));
+            }
+            expr => panic!("Expected unary expression, got {:?}", expr),
+        }
+
+        // An operand is required.
+        assert_eq!(
+            parse("-").unwrap_err().to_string(),
+            format!(
+                "expected {} but found end of input at line 1, column 2",
+                EXPRESSION_ALTERNATIVES
+            )
+        );
+    }
+
+    #[test]
+    fn test_associativity() {
+        assert_parses_as("1 - 2 - 3", "(- (- 1 2) 3)");
+        assert_parses_as("1 / 2 * 3", "(* (/ 1 2) 3)");
+        assert_parses_as("a or b or c", "(or (or a b) c)");
+        assert_parses_as("a == b == c", "(== (== a b) c)");
+        assert_parses_as("!!a", "(! (! a))");
+    }
+
+    #[test]
+    fn test_parentheses() {
+        assert_parses_as("(1 + 2) * 3", "(* (+ 1 2) 3)");
+        assert_parses_as("((((a))))", "a");
+        assert_parses_as("(a - (b - (c - d)))", "(- a (- b (- c d)))");
+        assert_parses_as("-(1 + 2)", "(- (+ 1 2))");
+    }
+
+    #[test]
+    fn test_primary() {
+        let expr = parse("12.5").unwrap();
+        assert!(matches!(
+            expr,
+            Expr::Literal { ref token, .. } if token.literal == Some(Literal::Number(12.5))
+        ));
+
+        let expr = parse(r#""a\tb""#).unwrap();
+        assert!(matches!(
+            expr,
+            Expr::Literal { ref token, .. } if token.literal == Some(Literal::Str("a\tb".into()))
+        ));
+
+        for (source, value) in [
+            ("true", Literal::Bool(true)),
+            ("false", Literal::Bool(false)),
+            ("nil", Literal::None),
+        ] {
+            match &parse(source).unwrap() {
+                Expr::Literal { token, .. } => {
+                    assert_eq!(token.literal, Some(value));
+                    assert_eq!(token.lexeme, source);
+                }
+                expr => panic!("Expected literal, got {:?}", expr),
+            }
+        }
+
+        match &parse("foo").unwrap() {
+            Expr::Variable { name, .. } => {
+                assert_eq!(name.lexeme, "foo");
+                assert_eq!(name.start, Position { line: 1, column: 1 });
+            }
+            expr => panic!("Expected variable, got {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn test_literal_without_value() {
+        // Tokens which weren't produced by the lexer get their value from the lexeme.
+        let token = Token {
+            token_type: TokenType::Number,
+            lexeme: "0x1f".into(),
+            start: Position { line: 1, column: 1 },
+            end: Position { line: 1, column: 4 },
+            span: Span { start: 0, end: 4 },
+            symbol: None,
+            literal: None,
+        };
+        let expr = Parser::new(vec![token]).parse_expression().unwrap();
+        assert!(matches!(
+            expr,
+            Expr::Literal { ref token, .. } if token.literal == Some(Literal::Number(31.0))
+        ));
+    }
+
+    #[test]
+    fn test_tokens_on_nodes() {
+        match &parse("a +\n  b").unwrap() {
+            Expr::Binary { operator, .. } => {
+                assert_eq!(operator.token_type, TokenType::Plus);
+                assert_eq!(operator.start, Position { line: 1, column: 3 });
+            }
+            expr => panic!("Expected binary expression, got {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn test_dangling_operator() {
+        assert_eq!(
+            parse("1 +"),
+            Err(ParserError::UnexpectedEndOfInput {
+                expected: EXPRESSION_START.to_vec(),
+                position: Position { line: 1, column: 4 },
+                span: Span { start: 3, end: 3 },
+            })
+        );
+        assert_eq!(
+            parse("1 +").unwrap_err().to_string(),
+            "expected number, string, char, 'true', 'false', 'nil', identifier, '(', '!' or '-' but found end of input at line 1, column 4"
+        );
+
+        let error = parse("1 + * 2").unwrap_err();
+        assert!(matches!(
+            error,
+            ParserError::UnexpectedToken {
+                ref expected,
+                ref found,
+            } if expected == EXPRESSION_START && found.token_type == TokenType::Times
+        ));
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "expected {} but found '*' at line 1, column 5",
+                EXPRESSION_ALTERNATIVES
+            )
+        );
+    }
+
+    #[test]
+    fn test_unclosed_parenthesis() {
+        assert_eq!(
+            parse("(1 + 2"),
+            Err(ParserError::UnexpectedEndOfInput {
+                expected: vec![TokenType::ClosingParentheses],
+                position: Position { line: 1, column: 7 },
+                span: Span { start: 6, end: 6 },
+            })
+        );
+
+        let error = parse("(1 + 2 3").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "expected ')' but found number '3' at line 1, column 8"
+        );
+
+        assert_eq!(
+            parse("1 )").unwrap_err().to_string(),
+            "expected end of input but found ')' at line 1, column 3"
+        );
+    }
+
+    #[test]
+    fn test_without_eof_token() {
+        let mut tokens = Lexer::new("1 + 2").tokenize().unwrap();
+        tokens.pop();
+        let expr = Parser::new(tokens).parse_expression().unwrap();
+        assert_eq!(sexpr(&expr), "(+ 1 2)");
+
+        assert_eq!(
+            Parser::new(vec![]).parse_expression(),
+            Err(ParserError::UnexpectedEndOfInput {
+                expected: EXPRESSION_START.to_vec(),
+                position: Position { line: 0, column: 0 },
+                span: Span { start: 0, end: 0 },
+            })
+        );
+    }
+
+    #[test]
+    fn test_comments_skipped() {
+        let tokens = Lexer::new("1 + /* two */ 2 // done")
+            .with_trivia(true)
+            .tokenize()
+            .unwrap();
+        let expr = Parser::new(tokens).parse_expression().unwrap();
+        assert_eq!(sexpr(&expr), "(+ 1 2)");
+    }
+
+    #[test]
+    fn test_statements() {
+        let statements = parse_program("var a = 1; var b;\nprint a + b;\na;").unwrap();
+        assert_eq!(statements.len(), 4);
+
+        match &statements[0] {
+            Stmt::Var {
+                name,
+                initializer: Some(initializer),
+                ..
+            } => {
+                assert_eq!(name.lexeme, "a");
+                assert_eq!(sexpr(initializer), "1");
+            }
+            stmt => panic!("Expected variable declaration, got {:?}", stmt),
+        }
+        assert!(matches!(
+            &statements[1],
+            Stmt::Var { name, initializer: None, .. } if name.lexeme == "b"
+        ));
+        match &statements[2] {
+            Stmt::Print { keyword, expr, .. } => {
+                assert_eq!(keyword.start, Position { line: 2, column: 1 });
+                assert_eq!(sexpr(expr), "(+ a b)");
+            }
+            stmt => panic!("Expected print statement, got {:?}", stmt),
+        }
+        assert!(matches!(
+            &statements[3],
+            Stmt::Expression { expr, .. } if sexpr(expr) == "a"
+        ));
+
+        assert_eq!(parse_program(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_fixture_program() {
+        let statements = parse_program(PROGRAM).unwrap();
+
+        assert_eq!(statements.len(), 13);
+        assert!(matches!(
+            &statements[8],
+            Stmt::Var { name, initializer: Some(expr), .. }
+                if name.lexeme == "average" && sexpr(expr) == "(/ (+ min max) 2)"
+        ));
+        assert!(matches!(
+            &statements[9],
+            Stmt::Block { statements, .. } if statements.len() == 2
+        ));
+        match &statements[10] {
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch: Some(else_branch),
+                ..
+            } => {
+                assert_eq!(sexpr(condition), "(== i s)");
+                assert!(matches!(**then_branch, Stmt::Block { .. }));
+                assert!(matches!(**else_branch, Stmt::Block { .. }));
+            }
+            stmt => panic!("Expected if statement, got {:?}", stmt),
+        }
+        match &statements[12] {
+            Stmt::While {
+                condition, body, ..
+            } => {
+                assert_eq!(sexpr(condition), "(< a 10)");
+                assert!(matches!(
+                    &**body,
+                    Stmt::Block { statements, .. } if matches!(
+                        &statements[1],
+                        Stmt::Expression { expr, .. } if sexpr(expr) == "(= a (+ a 1))"
+                    )
+                ));
+            }
+            stmt => panic!("Expected while statement, got {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn test_blocks() {
+        assert_eq!(
+            parse_program("{}"),
+            Ok(vec![Stmt::Block {
+                statements: vec![],
+                span: Span { start: 0, end: 2 },
+            }])
+        );
+
+        let statements = parse_program("{ { { print 1; } } var a; }").unwrap();
+        let Stmt::Block {
+            statements: outer, ..
+        } = &statements[0]
+        else {
+            panic!("Expected block, got {:?}", statements[0]);
+        };
+        assert_eq!(outer.len(), 2);
+        let Stmt::Block {
+            statements: middle, ..
+        } = &outer[0]
+        else {
+            panic!("Expected block, got {:?}", outer[0]);
+        };
+        let Stmt::Block {
+            statements: inner, ..
+        } = &middle[0]
+        else {
+            panic!("Expected block, got {:?}", middle[0]);
+        };
+        assert!(matches!(inner[..], [Stmt::Print { .. }]));
+
+        assert_eq!(
+            program_error("{ print 1;"),
+            ParserError::UnexpectedEndOfInput {
+                expected: vec![TokenType::ClosingBraces],
+                position: Position {
+                    line: 1,
+                    column: 11
+                },
+                span: Span { start: 10, end: 10 },
+            }
+        );
+        assert_eq!(
+            program_error("}").to_string(),
+            format!(
+                "expected {} but found '}}' at line 1, column 1",
+                STATEMENT_ALTERNATIVES
+            )
+        );
+    }
+
+    #[test]
+    fn test_missing_semicolon() {
+        assert_eq!(
+            program_error("print 1\nprint 2;").to_string(),
+            "expected ';' but found keyword 'print' at line 2, column 1"
+        );
+        assert_eq!(
+            program_error("var a = 1"),
+            ParserError::UnexpectedEndOfInput {
+                expected: vec![TokenType::Semicolon],
+                position: Position {
+                    line: 1,
+                    column: 10
+                },
+                span: Span { start: 9, end: 9 },
+            }
+        );
+        assert_eq!(
+            program_error("{ a + b }").to_string(),
+            "expected ';' but found '}' at line 1, column 9"
+        );
+    }
+
+    #[test]
+    fn test_missing_variable_name() {
+        let error = program_error("var = 1;");
+        assert_eq!(error.position(), Position { line: 1, column: 5 });
+        assert_eq!(
+            error.to_string(),
+            "expected identifier but found '=' at line 1, column 5"
+        );
+
+        assert_eq!(
+            program_error("var 1 = 1;").to_string(),
+            "expected identifier but found number '1' at line 1, column 5"
+        );
+    }
+
+    #[test]
+    fn test_if() {
+        let statements =
+            parse_program("if (a) print 1;\nif (b) { print 2; } else print 3;").unwrap();
+
+        match &statements[0] {
+            Stmt::If {
+                keyword,
+                condition,
+                then_branch,
+                else_branch: None,
+                ..
+            } => {
+                assert_eq!(keyword.start, Position { line: 1, column: 1 });
+                assert_eq!(sexpr(condition), "a");
+                assert!(matches!(**then_branch, Stmt::Print { .. }));
+            }
+            stmt => panic!("Expected if statement without else, got {:?}", stmt),
+        }
+        assert!(matches!(
+            &statements[1],
+            Stmt::If { then_branch, else_branch: Some(else_branch), .. }
+                if matches!(**then_branch, Stmt::Block { .. })
+                    && matches!(**else_branch, Stmt::Print { .. })
+        ));
+    }
+
+    #[test]
+    fn test_dangling_else() {
+        let statements = parse_program("if (a) if (b) print 1; else print 2;").unwrap();
+        assert_eq!(statements.len(), 1);
+
+        // The else belongs to the inner if.
+        let Stmt::If {
+            then_branch,
+            else_branch: None,
+            ..
+        } = &statements[0]
+        else {
+            panic!("Expected outer if without else, got {:?}", statements[0]);
+        };
+        assert!(matches!(
+            &**then_branch,
+            Stmt::If { condition, else_branch: Some(_), .. } if sexpr(condition) == "b"
+        ));
+    }
+
+    #[test]
+    fn test_while() {
+        let source = "while (a < 10) {\n    if (a == 5) print a; else { print 0; }\n}";
+        let statements = parse_program(source).unwrap();
+
+        let Stmt::While {
+            keyword,
+            condition,
+            body,
+            ..
+        } = &statements[0]
+        else {
+            panic!("Expected while statement, got {:?}", statements[0]);
+        };
+        assert_eq!(keyword.lexeme, "while");
+        assert_eq!(sexpr(condition), "(< a 10)");
+        let Stmt::Block {
+            statements: body, ..
+        } = &**body
+        else {
+            panic!("Expected block, got {:?}", body);
+        };
+        assert!(matches!(
+            &body[..],
+            [Stmt::If { condition, else_branch: Some(_), .. }] if sexpr(condition) == "(== a 5)"
+        ));
+
+        assert!(matches!(
+            parse_program("while (true) print 1;").unwrap()[..],
+            [Stmt::While { .. }]
+        ));
+    }
+
+    #[test]
+    fn test_for() {
+        let desugared = parse_program("for (var i = 0; i < 3; i = i + 1) { print i; }").unwrap();
+        let equivalent =
+            parse_program("{ var i = 0; while (i < 3) { { print i; } i = i + 1; } }").unwrap();
+        assert_eq!(
+            AstPrinter.print_program(&desugared),
+            AstPrinter.print_program(&equivalent)
+        );
+        assert_eq!(
+            AstPrinter.print_program(&desugared),
+            "(block (var i 0) (while (< i 3) (block (block (print i)) (expr (= i (+ i 1))))))"
+        );
+
+        // A declaration as the body is scoped to it, rather than to the increment.
+        assert_eq!(
+            AstPrinter.print_program(&parse_program("for (; a; a = a - 1) var a = 1;").unwrap()),
+            "(while a (block (block (var a 1)) (expr (= a (- a 1)))))"
+        );
+
+        // Without a declaration, there is no block scoping it.
+        assert_eq!(
+            AstPrinter.print_program(&parse_program("for (i = 0; i < 3;) i = i + 1;").unwrap()),
+            "(block (expr (= i 0)) (while (< i 3) (expr (= i (+ i 1)))))"
+        );
+        assert_eq!(
+            AstPrinter.print_program(&parse_program("for (; a;) print a;").unwrap()),
+            "(while a (print a))"
+        );
+    }
+
+    #[test]
+    fn test_infinite_for() {
+        let statements = parse_program("for (;;) print 1;").unwrap();
+        assert_eq!(
+            AstPrinter.print_program(&statements),
+            "(while true (print 1))"
+        );
+
+        // Spans the whole loop, with the condition at the second `;`.
+        let Stmt::While {
+            keyword,
+            condition,
+            span,
+            ..
+        } = &statements[0]
+        else {
+            panic!("Expected while statement, got {:?}", statements[0]);
+        };
+        assert_eq!(keyword.token_type, TokenType::For);
+        assert_eq!(*span, Span { start: 0, end: 17 });
+        assert_eq!(condition.span(), Span { start: 6, end: 6 });
+    }
+
+    #[test]
+    fn test_malformed_for() {
+        let error = program_error("for (var i = 0; i < 3; i = i + 1 print i;\nprint 2;");
+        assert_eq!(
+            error.to_string(),
+            "expected ')' but found keyword 'print' at line 1, column 34"
+        );
+
+        // The parser resumes at the declaration, which the rest of the loop doesn't follow.
+        let errors = parse_program("for var i = 0; i < 3; i = i + 1) print i;").unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            "expected '(' but found keyword 'var' at line 1, column 5"
+        );
+
+        let errors = parse_program("for (print 1;;) print 2;").unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            format!(
+                "expected {} but found keyword 'print' at line 1, column 6",
+                EXPRESSION_ALTERNATIVES
+            )
+        );
+    }
+
+    #[test]
+    fn test_calls() {
+        assert_parses_as("f()", "(call f)");
+        assert_parses_as("f(a, 1 + 2)", "(call f a (+ 1 2))");
+        assert_parses_as("f(g(x), h())", "(call f (call g x) (call h))");
+        assert_parses_as("f(x)(y)", "(call (call f x) y)");
+        assert_parses_as("(f)(x)", "(call f x)");
+
+        // Calls bind tighter than any operator.
+        assert_parses_as("-f(x)", "(- (call f x))");
+        assert_parses_as("!f()()", "(! (call (call f)))");
+        assert_parses_as("a + f(b) * c", "(+ a (* (call f b) c))");
+        assert_parses_as("a = f(b = c)", "(= a (call f (= b c)))");
+
+        let Expr::Call {
+            closing_paren,
+            span,
+            ..
+        } = &parse("f(a)\n(b)").unwrap()
+        else {
+            panic!("Expected call");
+        };
+        assert_eq!(closing_paren.start, Position { line: 2, column: 3 });
+        assert_eq!(*span, Span { start: 0, end: 8 });
+    }
+
+    #[test]
+    fn test_malformed_calls() {
+        // A trailing comma must be followed by another argument.
+        assert_eq!(
+            parse("f(a, )").unwrap_err().to_string(),
+            format!(
+                "expected {} but found ')' at line 1, column 6",
+                EXPRESSION_ALTERNATIVES
+            )
+        );
+        assert_eq!(
+            parse("f(a b)").unwrap_err().to_string(),
+            "expected ',' or ')' but found identifier 'b' at line 1, column 5"
+        );
+        assert_eq!(
+            parse("f(a, g(b)").unwrap_err().to_string(),
+            "expected ',' or ')' but found end of input at line 1, column 10"
+        );
+        assert_eq!(
+            program_error("print f(1;\nprint 2;").to_string(),
+            "expected ',' or ')' but found ';' at line 1, column 10"
+        );
+
+        // Neither is a call a valid assignment target.
+        assert_eq!(
+            parse("f() = 1"),
+            Err(ParserError::InvalidAssignmentTarget {
+                position: Position { line: 1, column: 5 },
+                span: Span { start: 4, end: 5 },
+            })
+        );
+    }
+
+    #[test]
+    fn test_functions() {
+        let statements = parse_program("fun add(a, b) {\n    print a + b;\n}\nadd(1, 2);").unwrap();
+        assert_eq!(
+            AstPrinter.print_program(&statements),
+            "(fun add (a b) (print (+ a b)))\n(expr (call add 1 2))"
+        );
+
+        let Stmt::Function {
+            name, params, span, ..
+        } = &statements[0]
+        else {
+            panic!("Expected function, got {:?}", statements[0]);
+        };
+        assert_eq!(name.lexeme, "add");
+        assert_eq!(
+            params[1].start,
+            Position {
+                line: 1,
+                column: 12
+            }
+        );
+        assert_eq!(*span, Span { start: 0, end: 34 });
+
+        // Without parameters, and nested.
+        assert_eq!(
+            AstPrinter.print_program(&parse_program("fun f() { fun g() {} h(g); }").unwrap()),
+            "(fun f () (fun g ()) (expr (call h g)))"
+        );
+    }
+
+    #[test]
+    fn test_malformed_functions() {
+        let cases = [
+            (
+                "fun (a) {}",
+                "expected identifier but found '(' at line 1, column 5",
+            ),
+            ("fun f {}", "expected '(' but found '{' at line 1, column 7"),
+            (
+                "fun f(a,) {}",
+                "expected identifier but found ')' at line 1, column 9",
+            ),
+            (
+                "fun f(a b) {}",
+                "expected ',' or ')' but found identifier 'b' at line 1, column 9",
+            ),
+            (
+                "fun f(1) {}",
+                "expected identifier but found number '1' at line 1, column 7",
+            ),
+            (
+                "fun f(a) print a;",
+                "expected '{' but found keyword 'print' at line 1, column 10",
+            ),
+        ];
+
+        for (source, expected) in cases {
+            let errors = parse_program(source).unwrap_err();
+            assert_eq!(errors[0].to_string(), expected, "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_arity_limit() {
+        let names = |count| {
+            (0..count)
+                .map(|i| format!("a{}", i))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let source = format!("fun f({}) {{}}\nf({});", names(MAX_ARITY), names(MAX_ARITY));
+        assert!(parse_program(&source).is_ok());
+
+        // Reported at the first one too many, without aborting the declaration or call.
+        let source = format!(
+            "fun f({}) {{}}\nf({});",
+            names(MAX_ARITY + 2),
+            names(MAX_ARITY + 1)
+        );
+        let errors = parse_program(&source).unwrap_err();
+        // Byte offsets of the parameter and argument `a255`.
+        let param = 6 + names(MAX_ARITY).len() + 2;
+        let arg = source.find('\n').unwrap() + 1 + 2 + names(MAX_ARITY).len() + 2;
+        assert_eq!(
+            errors,
+            vec![
+                ParserError::TooManyParameters {
+                    position: Position {
+                        line: 1,
+                        column: 7 + names(MAX_ARITY).len() + 2,
+                    },
+                    limit: MAX_ARITY,
+                    span: Span {
+                        start: param,
+                        end: param + 4,
+                    },
+                },
+                ParserError::TooManyArguments {
+                    position: Position {
+                        line: 2,
+                        column: 3 + names(MAX_ARITY).len() + 2,
+                    },
+                    limit: MAX_ARITY,
+                    span: Span {
+                        start: arg,
+                        end: arg + 4,
+                    },
+                },
+            ]
+        );
+        assert_eq!(
+            errors[1].to_string(),
+            format!(
+                "more than 255 arguments passed at line 2, column {}",
+                errors[1].position().column
+            )
+        );
+    }
+
+    #[test]
+    fn test_return() {
+        let statements = parse_program("fun f(a) { if (a) return; return nil; }").unwrap();
+        let Stmt::Function { body, .. } = &statements[0] else {
+            panic!("Expected function, got {:?}", statements[0]);
+        };
+
+        // `return;` and `return nil;` differ.
+        let Stmt::If { then_branch, .. } = &body[0] else {
+            panic!("Expected if statement, got {:?}", body[0]);
+        };
+        assert!(matches!(**then_branch, Stmt::Return { value: None, .. }));
+        assert!(matches!(
+            &body[1],
+            Stmt::Return {
+                value: Some(Expr::Literal {
+                    token: Token {
+                        literal: Some(Literal::None),
+                        ..
+                    },
+                    ..
+                }),
+                span: Span { start: 26, end: 37 },
+                ..
+            }
+        ));
+
+        assert_eq!(
+            AstPrinter.print_program(
+                &parse_program("fun f() { while (true) { return f() + 1; } }").unwrap()
+            ),
+            "(fun f () (while true (block (return (+ (call f) 1)))))"
+        );
+    }
+
+    #[test]
+    fn test_break_continue() {
+        let source = "while (a) {\n    if (b) break;\n    { continue; }\n}";
+        assert_eq!(
+            AstPrinter.print_program(&parse_program(source).unwrap()),
+            "(while a (block (if b (break)) (block (continue))))"
+        );
+
+        let source = "fun f() { for (;;) { while (a) break; continue; } }";
+        assert_eq!(
+            AstPrinter.print_program(&parse_program(source).unwrap()),
+            "(fun f () (while true (block (while a (break)) (continue))))"
+        );
+
+        assert_eq!(
+            program_error("while (a) break").to_string(),
+            "expected ';' but found end of input at line 1, column 16"
+        );
+    }
+
+    #[test]
+    fn test_for_continue() {
+        // The body is run by an inner loop, which a `continue` leaves for the increment, unless
+        // it is of a nested loop. A `break` leaves the outer loop as well.
+        let source = "for (var i = 0; i < 3; i = i + 1) { if (i == 1) continue; else break; while (a) continue; }";
+        assert_eq!(
+            AstPrinter.print_program(&parse_program(source).unwrap()),
+            concat!(
+                "(block (var i 0) (while (< i 3) (block ",
+                "(var for-break false) ",
+                "(while true (block (block ",
+                "(if (== i 1) (break) (block (expr (= for-break true)) (break))) ",
+                "(while a (continue))) ",
+                "(break))) ",
+                "(if for-break (break)) ",
+                "(expr (= i (+ i 1))))))",
+            )
+        );
+
+        // Without a `continue` of the loop itself, the body is run directly.
+        let source = "for (var i = 0; i < 3; i = i + 1) { while (a) continue; break; }";
+        assert_eq!(
+            AstPrinter.print_program(&parse_program(source).unwrap()),
+            concat!(
+                "(block (var i 0) (while (< i 3) (block ",
+                "(block (while a (continue)) (break)) ",
+                "(expr (= i (+ i 1))))))",
+            )
+        );
+
+        let source = "for (var i = 0; i < 3;) continue;";
+        assert_eq!(
+            AstPrinter.print_program(&parse_program(source).unwrap()),
+            "(block (var i 0) (while (< i 3) (continue)))"
+        );
+    }
+
+    #[test]
+    fn test_misplaced_control_flow() {
+        let position = |line, column| Position { line, column };
+        let cases = [
+            (
+                "return 1;",
+                ParserError::ReturnOutsideFunction {
+                    position: position(1, 1),
+                    span: Span { start: 0, end: 6 },
+                },
+            ),
+            (
+                "while (a) { fun f() { break; } }",
+                ParserError::BreakOutsideLoop {
+                    position: position(1, 23),
+                    span: Span { start: 22, end: 27 },
+                },
+            ),
+            (
+                "if (a) {\n    continue;\n}",
+                ParserError::ContinueOutsideLoop {
+                    position: position(2, 5),
+                    span: Span { start: 13, end: 21 },
+                },
+            ),
+            (
+                "fun f() {}\nreturn;",
+                ParserError::ReturnOutsideFunction {
+                    position: position(2, 1),
+                    span: Span { start: 11, end: 17 },
+                },
+            ),
+            (
+                "while (a) {}\nbreak;",
+                ParserError::BreakOutsideLoop {
+                    position: position(2, 1),
+                    span: Span { start: 13, end: 18 },
+                },
+            ),
+        ];
+
+        for (source, expected) in cases {
+            assert_eq!(program_error(source), expected, "{:?}", source);
+        }
+
+        // Reported without skipping the statement, so that errors after it are reported as well.
+        let errors = parse_program("break;\ncontinue 1;").unwrap_err();
+        assert_eq!(
+            errors
+                .iter()
+                .map(ParserError::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "'break' outside of a loop at line 1, column 1",
+                "'continue' outside of a loop at line 2, column 1",
+                "expected ';' but found number '1' at line 2, column 10",
+            ]
+        );
+        assert_eq!(
+            program_error("{ return; }").to_string(),
+            "'return' outside of a function at line 1, column 3"
+        );
+    }
+
+    #[test]
+    fn test_unclosed_condition() {
+        let error = program_error("var a = 1;\nwhile (a < 10 {\n    print a;\n}");
+        assert_eq!(error.position(), Position { line: 2, column: 7 });
+        assert_eq!(error.to_string(), "unclosed '(' at line 2, column 7");
+
+        assert_eq!(
+            program_error("if (a print a;").to_string(),
+            "unclosed '(' at line 1, column 4"
+        );
+    }
+
+    #[test]
+    fn test_condition_without_parentheses() {
+        assert_eq!(
+            program_error("while a < 10 { }").to_string(),
+            "expected '(' but found identifier 'a' at line 1, column 7"
+        );
+        assert_eq!(
+            program_error("if a print a;").to_string(),
+            "expected '(' but found identifier 'a' at line 1, column 4"
+        );
+    }
+
+    #[test]
+    fn test_else_without_if() {
+        assert_eq!(
+            program_error("print 1;\nelse print 2;").to_string(),
+            format!(
+                "expected {} but found keyword 'else' at line 2, column 1",
+                STATEMENT_ALTERNATIVES
+            )
+        );
+        assert_eq!(
+            program_error("if (a) print 1; print 2; else print 3;").to_string(),
+            format!(
+                "expected {} but found keyword 'else' at line 1, column 26",
+                STATEMENT_ALTERNATIVES
+            )
+        );
+    }
+
+    #[test]
+    fn test_expected_alternatives() {
+        // Everything a statement may start with is listed.
+        match program_error("print 1; ;") {
+            ParserError::UnexpectedToken { expected, found } => {
+                assert_eq!(expected, STATEMENT_START);
+                for token_type in [TokenType::Var, TokenType::While, TokenType::Minus] {
+                    assert!(expected.contains(&token_type));
+                }
+                assert_eq!(found.token_type, TokenType::Semicolon);
+            }
+            error => panic!("Expected unexpected token, got {:?}", error),
+        }
+        assert_eq!(
+            program_error("print 1; ;").to_string(),
+            "expected 'var', 'fun', 'print', 'if', 'while', 'for', 'return', 'break', 'continue', \
+             '{', number, string, char, 'true', 'false', 'nil', identifier, '(', '!' or '-' but found ';' at line 1, column 10"
+        );
+
+        // A declaration may continue with an initializer or end.
+        assert_eq!(
+            program_error("var a b;").to_string(),
+            "expected '=' or ';' but found identifier 'b' at line 1, column 7"
+        );
+        assert_eq!(
+            program_error("var a"),
+            ParserError::UnexpectedEndOfInput {
+                expected: vec![TokenType::Equals, TokenType::Semicolon],
+                position: Position { line: 1, column: 6 },
+                span: Span { start: 5, end: 5 },
+            }
+        );
+    }
+
+    #[test]
+    fn test_error_display() {
+        let found = |source| {
+            Lexer::new(source)
+                .tokenize()
+                .unwrap()
+                .remove(0)
+                .into_owned()
+        };
+        let position = Position {
+            line: 3,
+            column: 12,
+        };
+
+        let error = ParserError::UnexpectedToken {
+            expected: vec![TokenType::Semicolon, TokenType::ClosingParentheses],
+            found: Box::new(Token {
+                start: position,
+                ..found("foo")
+            }),
+        };
+        assert_eq!(
+            error.to_string(),
+            "expected ';' or ')' but found identifier 'foo' at line 3, column 12"
+        );
+
+        let error = ParserError::UnexpectedToken {
+            expected: vec![TokenType::Identifier],
+            found: Box::new(found("\"a b\"")),
+        };
+        assert_eq!(
+            error.to_string(),
+            "expected identifier but found string 'a b' at line 1, column 1"
+        );
+
+        let span = Span { start: 40, end: 41 };
+        let error = ParserError::UnexpectedEndOfInput {
+            expected: vec![TokenType::ClosingBraces],
+            position,
+            span,
+        };
+        assert_eq!(
+            error.to_string(),
+            "expected '}' but found end of input at line 3, column 12"
+        );
+
+        let error = ParserError::InvalidAssignmentTarget { position, span };
+        assert_eq!(error.position(), position);
+        assert_eq!(error.span(), span);
+        assert_eq!(
+            error.to_string(),
+            "invalid assignment target at line 3, column 12"
+        );
+
+        let error = ParserError::NestingTooDeep {
+            position,
+            limit: 2,
+            span,
+        };
+        assert_eq!(error.position(), position);
+        assert_eq!(
+            error.to_string(),
+            "nesting exceeds the limit of 2 levels at line 3, column 12"
+        );
+    }
+
+    #[test]
+    fn test_recovery() {
+        let source = "var a = 1;
+print a +;
+var b = 2;
+var = 3;
+print a + b;
+if (a) { print a a; print b; }
+print b;";
+        let mut parser = Parser::new(Lexer::new(source).tokenize().unwrap());
+        let mut statements = Vec::new();
+        let mut errors = Vec::new();
+        parser.parse_program_into(&mut statements, &mut errors);
+
+        let messages: Vec<String> = errors.iter().map(ParserError::to_string).collect();
+        assert_eq!(
+            messages,
+            vec![
+                format!(
+                    "expected {} but found ';' at line 2, column 10",
+                    EXPRESSION_ALTERNATIVES
+                ),
+                "expected identifier but found '=' at line 4, column 5".to_string(),
+                "expected ';' but found identifier 'a' at line 6, column 18".to_string(),
+            ]
+        );
+
+        // The valid statements in between are kept, those within blocks included.
+        let names: Vec<String> = statements
+            .iter()
+            .map(|statement| match statement {
+                Stmt::Var { name, .. } => format!("var {}", name.lexeme),
+                Stmt::Print { expr, .. } => format!("print {}", sexpr(expr)),
+                Stmt::If { then_branch, .. } => match &**then_branch {
+                    Stmt::Block { statements, .. } => format!("if with {}", statements.len()),
+                    stmt => panic!("Expected block, got {:?}", stmt),
+                },
+                stmt => panic!("Unexpected statement {:?}", stmt),
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec!["var a", "var b", "print (+ a b)", "if with 1", "print b"]
+        );
+
+        assert_eq!(parse_program(source), Err(errors));
+    }
+
+    #[test]
+    fn test_recovery_progress() {
+        // Errors on the first token of a statement, which is skipped.
+        let errors = parse_program("} ; print 1; ) print 2;").unwrap_err();
+        assert_eq!(errors.len(), 2);
+
+        let mut parser = Parser::new(
+            Lexer::new("; print 1; ) print 2; print 3;")
+                .tokenize()
+                .unwrap(),
+        );
+        let mut statements = Vec::new();
+        let mut errors = Vec::new();
+        parser.parse_program_into(&mut statements, &mut errors);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(statements.len(), 3);
+
+        // Synchronization points are not skipped, even if they caused the error.
+        let errors = parse_program("print 1\nprint 2\nprint 3;").unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[1].position(), Position { line: 3, column: 1 });
+    }
+
+    #[test]
+    fn test_assignment() {
+        assert_parses_as("a = 1 + 2", "(= a (+ 1 2))");
+        assert_parses_as("a = b = 3", "(= a (= b 3))");
+        assert_parses_as("a = b or c", "(= a (or b c))");
+
+        match &parse("a = b = 3").unwrap() {
+            Expr::Assign { name, .. } => {
+                assert_eq!(name.lexeme, "a");
+                assert_eq!(name.start, Position { line: 1, column: 1 });
+            }
+            expr => panic!("Expected assignment, got {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn test_invalid_assignment_target() {
+        for (source, column) in [
+            ("1 = 2", 3),
+            ("(a) = 3", 5),
+            ("a + b = c", 7),
+            ("-a = 1", 4),
+        ] {
+            assert_eq!(
+                parse(source),
+                Err(ParserError::InvalidAssignmentTarget {
+                    position: Position { line: 1, column },
+                    span: Span {
+                        start: column - 1,
+                        end: column,
+                    },
+                }),
+                "{:?}",
+                source
+            );
+        }
+        assert_eq!(
+            parse("a + b = c").unwrap_err().to_string(),
+            "invalid assignment target at line 1, column 7"
+        );
+
+        // The right-hand side is still parsed, and errors in it reported as well.
+        let errors = parse_program("1 = 2 +;\na = 1;\n(b) = c = 3;").unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                ParserError::InvalidAssignmentTarget {
+                    position: Position { line: 1, column: 3 },
+                    span: Span { start: 2, end: 3 },
+                },
+                ParserError::UnexpectedToken {
+                    expected: EXPRESSION_START.to_vec(),
+                    found: Box::new(Token {
+                        token_type: TokenType::Semicolon,
+                        lexeme: ";".into(),
+                        start: Position { line: 1, column: 8 },
+                        end: Position { line: 1, column: 8 },
+                        span: Span { start: 7, end: 8 },
+                        symbol: None,
+                        literal: None,
+                    }),
+                },
+                ParserError::InvalidAssignmentTarget {
+                    position: Position { line: 3, column: 5 },
+                    span: Span { start: 20, end: 21 },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_assignment_in_while_condition() {
+        let statements = parse_program("while ((a = a - 1) > 0) print a;").unwrap();
+        assert!(matches!(
+            &statements[0],
+            Stmt::While { condition, .. } if sexpr(condition) == "(> (= a (- a 1)) 0)"
+        ));
+
+        let statements = parse_program("while (line = next) { print line; }").unwrap();
+        assert!(matches!(
+            &statements[0],
+            Stmt::While { condition, .. } if sexpr(condition) == "(= line next)"
+        ));
+    }
+
+    #[test]
+    fn test_streaming() {
+        // A large program, with distinct names in each copy of the fixture.
+        let source: String = (0..500)
+            .map(|i| PROGRAM.replace("average", &format!("average{}", i)))
+            .collect();
+
+        let streamed = Parser::from_stream(Lexer::new(&source))
+            .parse_program()
+            .unwrap();
+        let collected = parse_program(&source).unwrap();
+
+        assert_eq!(streamed.len(), 500 * 13);
+        assert_eq!(
+            AstPrinter.print_program(&streamed),
+            AstPrinter.print_program(&collected)
+        );
+        assert_eq!(streamed, collected);
+    }
+
+    #[test]
+    fn test_streaming_lexer_errors() {
+        let source = "var a = 1;\nvar b = @ 2;\nprint a;\n";
+        let mut parser = Parser::from_stream(Lexer::new(source));
+        let mut statements = Vec::new();
+        let mut errors = Vec::new();
+        parser.parse_program_into(&mut statements, &mut errors);
+
+        // The erroneous input is skipped.
+        assert_eq!(
+            AstPrinter.print_program(&statements),
+            "(var a 1)\n(var b 2)\n(print a)"
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ParserError::Lexer(LexerError::UnexpectedChar { c: '@', .. })
+        ));
+        assert_eq!(errors[0].position(), Position { line: 2, column: 9 });
+        assert_eq!(
+            errors[0].to_string(),
+            "Unexpected char `@` (unicode \\u{40}) found at line 2, column 9"
+        );
+
+        // Errors of the lexer and the parser are reported alike, in order.
+        let errors = Parser::from_stream(Lexer::new("print 1 $;\nvar = 2;\nprint \"abc"))
+            .parse_program()
+            .unwrap_err();
+        let positions: Vec<Position> = errors.iter().map(ParserError::position).collect();
+        assert_eq!(
+            positions,
+            vec![
+                Position { line: 1, column: 9 },
+                Position { line: 2, column: 5 },
+                Position { line: 3, column: 7 },
+                Position {
+                    line: 3,
+                    column: 11
+                },
+            ]
+        );
+        assert!(matches!(errors[2], ParserError::Lexer(_)));
+        assert!(matches!(
+            errors[3],
+            ParserError::UnexpectedEndOfInput { .. }
+        ));
+    }
+
+    #[test]
+    fn test_streaming_without_eof_token() {
+        let tokens: Vec<_> = Lexer::new("print 1 +\n2").collect();
+        let tokens = tokens
+            .into_iter()
+            .filter(|item| !matches!(item, Ok(token) if token.token_type == TokenType::EndOfFile));
+
+        assert_eq!(
+            Parser::from_stream(tokens).parse_program(),
+            Err(vec![ParserError::UnexpectedEndOfInput {
+                expected: vec![TokenType::Semicolon],
+                position: Position { line: 2, column: 1 },
+                span: Span { start: 11, end: 11 },
+            }])
+        );
+    }
+
+    #[test]
+    fn test_expression_spans() {
+        let source = "a = -(1 + b) * 2;";
+        let statements = parse_program(source).unwrap();
+        let text = |span: Span| &source[span.start..span.end];
+
+        let Stmt::Expression { expr, span } = &statements[0] else {
+            panic!("Expected expression statement, got {:?}", statements[0]);
+        };
+        assert_eq!(*span, Span { start: 0, end: 17 });
+        assert_eq!(statements[0].span(), *span);
+        assert_eq!(expr.span(), Span { start: 0, end: 16 });
+
+        let Expr::Assign { value, .. } = expr else {
+            panic!("Expected assignment, got {:?}", expr);
+        };
+        assert_eq!(text(value.span()), "-(1 + b) * 2");
+
+        let Expr::Binary { left, right, .. } = &**value else {
+            panic!("Expected binary expression, got {:?}", value);
+        };
+        assert_eq!(text(left.span()), "-(1 + b)");
+        assert_eq!(text(right.span()), "2");
+
+        // Groupings include their parentheses, their contents don't.
+        let Expr::Unary { operand, .. } = &**left else {
+            panic!("Expected unary expression, got {:?}", left);
+        };
+        assert_eq!(operand.span(), Span { start: 5, end: 12 });
+        let Expr::Grouping { expr, .. } = &**operand else {
+            panic!("Expected grouping, got {:?}", operand);
+        };
+        assert_eq!(expr.span(), Span { start: 6, end: 11 });
+    }
+
+    #[test]
+    fn test_statement_spans() {
+        let statements = parse_program(PROGRAM).unwrap();
+        let text = |span: Span| &PROGRAM[span.start..span.end];
+
+        // Covers the else branch.
+        let start = PROGRAM.find("if (").unwrap();
+        let source = "if ( i == s ) {\n\tprint \"yes\";\n} else {\n\tprint \"no\";\n}";
+        assert_eq!(
+            statements[10].span(),
+            Span {
+                start,
+                end: start + source.len(),
+            }
+        );
+        assert_eq!(text(statements[10].span()), source);
+
+        let Stmt::If {
+            condition,
+            then_branch,
+            else_branch: Some(else_branch),
+            ..
+        } = &statements[10]
+        else {
+            panic!("Expected if statement, got {:?}", statements[10]);
+        };
+        assert_eq!(text(condition.span()), "i == s");
+        assert_eq!(text(then_branch.span()), "{\n\tprint \"yes\";\n}");
+        assert_eq!(text(else_branch.span()), "{\n\tprint \"no\";\n}");
+
+        assert_eq!(text(statements[0].span()), "var b = true ;");
+        assert_eq!(text(statements[4].span()), "i + d;");
+        assert!(text(statements[12].span()).starts_with("while (a < 10) {"));
+        assert!(text(statements[12].span()).ends_with("a = a + 1;\n}"));
+
+        // Without else, up to the end of the then branch.
+        let source = "if (a)\n  print 1;\nprint 2;";
+        let statements = parse_program(source).unwrap();
+        assert_eq!(statements[0].span(), Span { start: 0, end: 17 });
+        assert_eq!(statements[1].span(), Span { start: 18, end: 26 });
+    }
+
+    #[test]
+    fn test_operator_tables() {
+        // Every operator has an entry, and no other token type.
+        for token_type in TokenType::ALL {
+            let is_operator =
+                token_type.is_operator() || matches!(token_type, TokenType::And | TokenType::Or);
+            let infix = INFIX_OPERATORS.iter().filter(|(t, _, _)| t == token_type);
+            let prefix = PREFIX_OPERATORS.iter().filter(|(t, _)| t == token_type);
+
+            assert!(infix.clone().count() <= 1, "{}", token_type);
+            assert!(prefix.clone().count() <= 1, "{}", token_type);
+            assert_eq!(
+                infix.count() + prefix.count() > 0,
+                is_operator,
+                "{}",
+                token_type
+            );
+        }
+
+        // Consistent with the groups of token types.
+        for (token_type, precedence, associativity) in INFIX_OPERATORS {
+            let expected = match token_type {
+                t if t.is_equality() => EQUALITY,
+                t if t.is_comparison() => COMPARISON,
+                t if t.is_term() => TERM,
+                t if t.is_factor() => FACTOR,
+                _ => continue,
+            };
+            assert_eq!(*precedence, expected, "{}", token_type);
+            assert_eq!(*associativity, Associativity::Left, "{}", token_type);
+        }
+        for (token_type, precedence) in PREFIX_OPERATORS {
+            assert!(token_type.is_unary());
+            assert!(INFIX_OPERATORS.iter().all(|(_, p, _)| p < precedence));
+        }
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested = |depth| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+
+        assert!(parse(&nested(DEFAULT_MAX_DEPTH)).is_ok());
+        assert_eq!(
+            parse(&nested(DEFAULT_MAX_DEPTH + 1)),
+            Err(ParserError::NestingTooDeep {
+                position: Position {
+                    line: 1,
+                    column: DEFAULT_MAX_DEPTH + 1,
+                },
+                limit: DEFAULT_MAX_DEPTH,
+                span: Span {
+                    start: DEFAULT_MAX_DEPTH,
+                    end: DEFAULT_MAX_DEPTH + 1,
+                },
+            })
+        );
+
+        // Every kind of nesting parses up to the limit on the stack of a test thread.
+        let calls = format!(
+            "{}1{}",
+            "f(".repeat(DEFAULT_MAX_DEPTH - 1),
+            ")".repeat(DEFAULT_MAX_DEPTH - 1)
+        );
+        assert!(parse(&calls).is_ok());
+        assert!(parse(&format!("{}1", "-".repeat(DEFAULT_MAX_DEPTH))).is_ok());
+        assert!(parse(&format!("{}1", "a = ".repeat(DEFAULT_MAX_DEPTH))).is_ok());
+        assert!(parse(&"(".repeat(100_000)).is_err());
+
+        // Fails at the limit instead of recursing any further, and skips the rest of the statement.
+        let source = format!("print {};\nprint 2;", nested(100_000));
+        let mut errors = Parser::from_stream(Lexer::new(&source))
+            .parse_program()
+            .unwrap_err();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(matches!(
+            errors.remove(0),
+            ParserError::NestingTooDeep { limit, .. } if limit == DEFAULT_MAX_DEPTH
+        ));
+
+        // Prefix operators and assignments nest as well.
+        let source = format!("{}1", "-".repeat(100_000));
+        assert!(matches!(
+            parse(&source),
+            Err(ParserError::NestingTooDeep { .. })
+        ));
+        let source = format!("{}1", "a = ".repeat(100_000));
+        assert!(matches!(
+            parse(&source),
+            Err(ParserError::NestingTooDeep { .. })
+        ));
+
+        // So do chains of binary operators and calls, whose syntax trees are just as deep.
+        let terms = |n| vec!["1"; n].join(" + ");
+        assert!(parse(&terms(DEFAULT_MAX_DEPTH + 1)).is_ok());
+        assert_eq!(
+            parse(&terms(DEFAULT_MAX_DEPTH + 2)),
+            Err(ParserError::NestingTooDeep {
+                position: Position {
+                    line: 1,
+                    column: 4 * DEFAULT_MAX_DEPTH + 3,
+                },
+                limit: DEFAULT_MAX_DEPTH,
+                span: Span {
+                    start: 4 * DEFAULT_MAX_DEPTH + 2,
+                    end: 4 * DEFAULT_MAX_DEPTH + 3,
+                },
+            })
+        );
+        let source = format!(
+            "print {};\nprint f{};",
+            terms(100_000),
+            "()".repeat(100_000)
+        );
+        let errors = parse_program(&source).unwrap_err();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors
+            .iter()
+            .all(|e| matches!(e, ParserError::NestingTooDeep { .. })));
+        assert_eq!(errors[1].position().line, 2);
+    }
+
+    #[test]
+    fn test_nesting_limit_statements() {
+        let blocks = |depth| format!("{}{}", "{".repeat(depth), "}".repeat(depth));
+
+        assert!(parse_program(&blocks(DEFAULT_MAX_DEPTH)).is_ok());
+        let errors = parse_program(&blocks(DEFAULT_MAX_DEPTH + 1)).unwrap_err();
+        assert_eq!(
+            errors[0],
+            ParserError::NestingTooDeep {
+                position: Position {
+                    line: 1,
+                    column: DEFAULT_MAX_DEPTH + 1,
+                },
+                limit: DEFAULT_MAX_DEPTH,
+                span: Span {
+                    start: DEFAULT_MAX_DEPTH,
+                    end: DEFAULT_MAX_DEPTH + 1,
+                },
+            }
+        );
+
+        // Every kind of nesting parses up to the limit on the stack of a test thread.
+        for (open, close) in [
+            ("if (a) ", ""),
+            ("while (a) ", ""),
+            ("for (;;) ", ""),
+            ("fun f() { ", "}"),
+        ] {
+            let source = format!(
+                "{}print 1;{}",
+                open.repeat(DEFAULT_MAX_DEPTH),
+                close.repeat(DEFAULT_MAX_DEPTH)
+            );
+            assert!(parse_program(&source).is_ok(), "{}", open);
+        }
+
+        let source = format!("{}print 1;", "while (a) if (b) ".repeat(100_000));
+        let errors = parse_program(&source).unwrap_err();
+        assert!(matches!(errors[..], [ParserError::NestingTooDeep { .. }]));
+
+        // The limit is reported once, with parsing resuming after the skipped construct.
+        let source = format!("print 1;\n{}\nprint (2;", blocks(100_000));
+        let errors = parse_program(&source).unwrap_err();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(matches!(errors[0], ParserError::NestingTooDeep { .. }));
+        assert_eq!(errors[1].position().line, 3);
+        let source = format!("while (a) {{ {} }}\nprint (2;", blocks(100_000));
+        let errors = parse_program(&source).unwrap_err();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let parse_with_limit = |source: &str, max_depth| {
+            Parser::new(Lexer::new(source).tokenize().unwrap())
+                .with_max_depth(max_depth)
+                .parse_program()
+        };
+
+        // Each grouping, operand of a prefix operator, binary operator, call, block, and branch is
+        // a level.
+        assert!(parse_with_limit("{ print -(1); }", 3).is_ok());
+        assert!(parse_with_limit("{ print -(1); }", 2).is_err());
+        assert!(parse_with_limit("if (a) { b = 1; }", 3).is_ok());
+        assert!(parse_with_limit("if (a) { b = 1; }", 2).is_err());
+        assert!(parse_with_limit("print 1 + 2 * 3;", 2).is_ok());
+        assert!(parse_with_limit("print 1 + 2 * 3;", 1).is_err());
+        assert!(parse_with_limit("print 1 * 2 + 3;", 1).is_err());
+        assert!(parse_with_limit("f(1)(2);", 2).is_ok());
+        assert!(parse_with_limit("f(1)(2);", 1).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+//! Locations within the source, shared by tokens, errors, and diagnostics.
+
+use std::fmt::Display;
+
+use crate::token::Token;
+
+/// Position within an input file
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Range of byte offsets within an input file. `start` is inclusive, `end` exclusive.
+///
+/// As these are byte offsets, they can be used to slice the input safely, even if it contains
+/// multi-byte characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Smallest span covering both this one and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+/// Text of an input file, which tokens and errors point into by their spans.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    text: String,
+}
+
+impl SourceFile {
+    pub fn new(text: impl Into<String>) -> Self {
+        SourceFile { text: text.into() }
+    }
+
+    /// Complete text of the file.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Original text covered by `span`.
+    ///
+    /// Unlike the lexeme of a token, this is the text exactly as it appears in the source, e.g.
+    /// including the quotes and escape sequences of strings.
+    ///
+    /// # Panics
+    ///
+    /// If the span does not lie within the file, or does not fall onto character boundaries. Spans
+    /// the lexer produced for this file always do.
+    pub fn slice(&self, span: Span) -> &str {
+        &self.text[span.start..span.end]
+    }
+}
+
+/// Original text of a contiguous range of tokens, from the start of the first to the end of the
+/// last.
+///
+/// Whitespace and comments between the tokens are retained as they appear in the source. An empty
+/// range yields an empty string.
+///
+/// ```
+/// use spl::prelude::*;
+///
+/// let source = SourceFile::new(r#"print "a\tb" + 1; // done"#);
+/// let tokens = Lexer::new(source.text()).tokenize().unwrap();
+///
+/// assert_eq!(tokens[1].lexeme, "a\tb");
+/// assert_eq!(tokens_to_source(&source, &tokens[1..4]), r#""a\tb" + 1"#);
+/// ```
+pub fn tokens_to_source(source: &SourceFile, tokens: &[Token]) -> String {
+    match (tokens.first(), tokens.last()) {
+        (Some(first), Some(last)) => source.slice(first.span.to(last.span)).to_string(),
+        _ => String::new(),
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
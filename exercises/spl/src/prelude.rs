@@ -0,0 +1,33 @@
+//! Curated re-exports of the user-facing parts of the crate.
+//!
+//! Code which only imports from here should keep compiling as modules get reorganized.
+//!
+//! ```
+//! use spl::prelude::*;
+//!
+//! let mut lexer = Lexer::new("var a = 1;");
+//! let tokens: Vec<Token> = lexer.tokenize().unwrap();
+//!
+//! assert_eq!(tokens[0].token_type, TokenType::Var);
+//! assert_eq!(tokens.last().unwrap().token_type, TokenType::EndOfFile);
+//!
+//! let errors: Vec<LexerError> = Lexer::new("@").tokenize().unwrap_err();
+//! assert_eq!(
+//!     errors[0],
+//!     LexerError::UnexpectedChar {
+//!         position: Position { line: 1, column: 1 },
+//!         c: '@',
+//!         span: Span { start: 0, end: 1 },
+//!     }
+//! );
+//! ```
+
+pub use crate::ast::{AstPrinter, Expr, SourcePrinter, Stmt};
+pub use crate::error::{Diagnostic, LexerError, ParserError, RuntimeError, Severity, SplError};
+pub use crate::interner::{StringPool, Symbol};
+pub use crate::interpreter::{Interpreter, Value};
+pub use crate::lexer::{Lexer, LexerOptions, Tokenized};
+pub use crate::parser::Parser;
+pub use crate::position::{tokens_to_source, Position, SourceFile, Span};
+pub use crate::token::{Literal, Token, TokenType};
+pub use crate::visitor::{ExprVisitor, StmtVisitor};
@@ -1,29 +1,245 @@
-use std::fmt::Display;
+use std::{borrow::Cow, fmt::Display};
 
-#[derive(Debug, Eq, PartialEq)]
-pub struct Token {
+use crate::{
+    char_class,
+    interner::Symbol,
+    position::{Position, Span},
+};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Token<'a> {
     pub token_type: TokenType,
-    pub lexeme: String,
-    pub line: usize,
+    /// Text of the token. Borrowed from the source, except for string literals whose escape
+    /// sequences had to be decoded.
+    pub lexeme: Cow<'a, str>,
+    /// Position of the first character of the token.
+    pub start: Position,
+    /// Position of the last character of the token. Tokens such as strings may span multiple
+    /// lines, in which case it is on a later line than `start`. For the end-of-file token, which
+    /// covers no characters, it is the same as `start`.
+    pub end: Position,
+    /// Byte offsets of the token within the source, including e.g. the quotes of strings.
+    pub span: Span,
+    /// Interned name of identifiers, see `Lexer::symbols`. None for all other tokens.
+    pub symbol: Option<Symbol>,
+    /// Value of literals, including the keywords `true`, `false` and `nil`. None for all other
+    /// tokens.
+    pub literal: Option<Literal>,
+}
+
+// Pins the size of tokens, of which there are as many as there are lexemes in the input, so that
+// growing it is a deliberate decision. On 64-bit targets, the lexeme and the literal take up 24
+// bytes each, and each of the positions and the span 16.
+#[cfg(target_pointer_width = "64")]
+const _: () = assert!(std::mem::size_of::<Token>() <= 112);
+
+impl Token<'_> {
+    /// Convert into a token which owns its lexeme, and as such no longer borrows from the source.
+    pub fn into_owned(self) -> Token<'static> {
+        Token {
+            token_type: self.token_type,
+            lexeme: Cow::Owned(self.lexeme.into_owned()),
+            start: self.start,
+            end: self.end,
+            span: self.span,
+            symbol: self.symbol,
+            literal: self.literal,
+        }
+    }
+
+    /// Whether the token is of type `token_type`.
+    pub fn is(&self, token_type: TokenType) -> bool {
+        self.token_type == token_type
+    }
+
+    /// Whether the token is the identifier `name`.
+    pub fn is_identifier(&self, name: &str) -> bool {
+        self.is(TokenType::Identifier) && self.lexeme == name
+    }
+
+    /// Whether the token is a keyword, see `TokenType::is_keyword`.
+    pub fn is_keyword(&self) -> bool {
+        self.token_type.is_keyword()
+    }
+
+    /// Whether the token is an operator, see `TokenType::is_operator`.
+    pub fn is_operator(&self) -> bool {
+        self.token_type.is_operator()
+    }
 }
 
-impl Display for Token {
+impl Display for Token<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "<{}, {}> Line: {}",
-            self.token_type, self.lexeme, self.line
+            "<{}, {}> at {}",
+            self.token_type, self.lexeme, self.start
         )
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+/// Value of a literal, as determined by the lexer when scanning it.
+#[derive(Debug, Clone)]
+pub enum Literal {
+    Number(f64),
+    /// Text of a string or char literal, with its escape sequences decoded.
+    Str(String),
+    Bool(bool),
+    /// The value of `nil`.
+    None,
+}
+
+impl Literal {
+    /// Value of a token with the given type and lexeme, if it is a literal.
+    ///
+    /// Returns None for numbers which are out of range, see `Literal::parse_number`.
+    pub fn from_lexeme(token_type: TokenType, lexeme: &str) -> Option<Literal> {
+        match token_type {
+            TokenType::Number => Literal::parse_number(lexeme).map(Literal::Number),
+            TokenType::String | TokenType::Char => Some(Literal::Str(lexeme.to_string())),
+            TokenType::True => Some(Literal::Bool(true)),
+            TokenType::False => Some(Literal::Bool(false)),
+            TokenType::Nil => Some(Literal::None),
+            _ => None,
+        }
+    }
+
+    /// Value of the number literal `lexeme`, in any of the notations the lexer accepts.
+    ///
+    /// Returns None if it is too large to be represented, or not a well-formed number literal.
+    pub(crate) fn parse_number(lexeme: &str) -> Option<f64> {
+        let lexeme = lexeme.replace('_', "");
+
+        let value = if let Some(digits) = lexeme.strip_prefix("0x") {
+            parse_radix(digits, 16)?
+        } else if let Some(digits) = lexeme.strip_prefix("0b") {
+            parse_radix(digits, 2)?
+        } else {
+            lexeme.parse().ok()?
+        };
+
+        value.is_finite().then_some(value)
+    }
+}
+
+/// Value of the digits of a hexadecimal or binary number literal.
+///
+/// Accumulated as a float rather than an integer, so that overly large literals lose precision
+/// like decimal ones do, rather than overflowing.
+fn parse_radix(digits: &str, radix: u32) -> Option<f64> {
+    if digits.is_empty() {
+        return None;
+    }
+
+    digits.chars().try_fold(0.0, |value, c| {
+        let digit = char_class::digit_value(c, radix)?;
+        Some(value * radix as f64 + digit as f64)
+    })
+}
+
+// Numbers are compared by their bits rather than by value, so that equality is reflexive - as
+// required by `Eq` - even for NaN. The lexer never produces one, but literals may be constructed
+// elsewhere.
+impl PartialEq for Literal {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Literal::Number(a), Literal::Number(b)) => a.to_bits() == b.to_bits(),
+            (Literal::Str(a), Literal::Str(b)) => a == b,
+            (Literal::Bool(a), Literal::Bool(b)) => a == b,
+            (Literal::None, Literal::None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Literal {}
+
+/// Lexemes longer than this many characters are truncated in token tables.
+const TABLE_LEXEME_WIDTH: usize = 24;
+
+/// Render `tokens` as a table with aligned columns, one row per token.
+///
+/// Lexemes are shown with line breaks and tabs escaped, and truncated with an ellipsis if they are
+/// overly long.
+pub fn format_token_table(tokens: &[Token]) -> String {
+    let header = ["#", "Line", "Column", "Type", "Lexeme"];
+    let rows: Vec<[String; 5]> = tokens
+        .iter()
+        .enumerate()
+        .map(|(i, token)| {
+            [
+                i.to_string(),
+                token.start.line.to_string(),
+                token.start.column.to_string(),
+                token.token_type.to_string(),
+                table_lexeme(&token.lexeme),
+            ]
+        })
+        .collect();
+
+    // Widths are in characters rather than bytes, so that multi-byte lexemes are aligned too.
+    let mut widths = header.map(|cell| cell.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut out = String::new();
+    let mut push_row = |row: [&str; 5]| {
+        let line = format!(
+            "{:>w0$}  {:>w1$}  {:>w2$}  {:<w3$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            row[4],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+        );
+        out.push_str(line.trim_end());
+        out.push('\n');
+    };
+
+    push_row(header);
+    for row in &rows {
+        push_row([&row[0], &row[1], &row[2], &row[3], &row[4]]);
+    }
+
+    out
+}
+
+/// Lexeme as shown in a token table, see `format_token_table`.
+fn table_lexeme(lexeme: &str) -> String {
+    let escaped: Vec<char> = lexeme
+        .chars()
+        .flat_map(|c| match c {
+            '\n' => vec!['\\', 'n'],
+            '\r' => vec!['\\', 'r'],
+            '\t' => vec!['\\', 't'],
+            c => vec![c],
+        })
+        .collect();
+
+    if escaped.len() > TABLE_LEXEME_WIDTH {
+        let mut truncated: String = escaped[..TABLE_LEXEME_WIDTH - 1].iter().collect();
+        truncated.push('…');
+        truncated
+    } else {
+        escaped.into_iter().collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub enum TokenType {
     // Operators
     Plus,
     Minus,
     Times,
     Divide,
+    Modulo,
     Equals,
     DoubleEquals,
     NotEquals,
@@ -35,10 +251,16 @@ pub enum TokenType {
 
     // Special characters
     Semicolon,
+    Comma,
+    Dot,
     OpeningParentheses,
     ClosingParentheses,
     OpeningBraces,
     ClosingBraces,
+    OpeningBracket,
+    ClosingBracket,
+    Question,
+    Colon,
 
     // Keywords
     True,
@@ -50,16 +272,279 @@ pub enum TokenType {
     If,
     Else,
     While,
+    Fun,
+    Return,
+    For,
+    Break,
+    Continue,
+    Nil,
 
     // Literals
     Number,
     String,
+    Char,
 
     // Variables
     Identifier,
 
+    // Only emitted if the lexer is configured to preserve comments.
+    Comment,
+
     // Returned once when whole input file is tokenized.
-    EndOfile,
+    EndOfFile,
+}
+
+/// Keywords of the language, along with their token types.
+///
+/// This is the single source of truth for keywords, which both directions of the lookup - see
+/// `keyword_type` and `TokenType::as_keyword_str` - are based on.
+pub const KEYWORDS: &[(&str, TokenType)] = &[
+    ("true", TokenType::True),
+    ("false", TokenType::False),
+    ("and", TokenType::And),
+    ("or", TokenType::Or),
+    ("var", TokenType::Var),
+    ("print", TokenType::Print),
+    ("if", TokenType::If),
+    ("else", TokenType::Else),
+    ("while", TokenType::While),
+    ("fun", TokenType::Fun),
+    ("return", TokenType::Return),
+    ("for", TokenType::For),
+    ("break", TokenType::Break),
+    ("continue", TokenType::Continue),
+    ("nil", TokenType::Nil),
+];
+
+/// Token type of the keyword `name`, if it is one.
+pub fn keyword_type(name: &str) -> Option<TokenType> {
+    KEYWORDS
+        .iter()
+        .find(|(keyword, _)| *keyword == name)
+        .map(|(_, token_type)| *token_type)
+}
+
+/// Words which are reserved for future use.
+///
+/// They are not keywords yet, but can't be used as identifiers either, so that programs don't
+/// break once they become keywords.
+pub const RESERVED_WORDS: &[&str] = &["class", "const", "import", "match"];
+
+/// Whether `name` is reserved for future use, see `RESERVED_WORDS`.
+///
+/// Tooling can tell reserved words and keywords apart with this and `keyword_type`:
+///
+/// ```
+/// use spl::token::{is_reserved_word, keyword_type, TokenType};
+///
+/// assert_eq!(keyword_type("while"), Some(TokenType::While));
+/// assert!(is_reserved_word("class") && keyword_type("class").is_none());
+/// ```
+pub fn is_reserved_word(name: &str) -> bool {
+    RESERVED_WORDS.contains(&name)
+}
+
+impl TokenType {
+    /// All token types, in order of declaration.
+    pub const ALL: &'static [TokenType] = &[
+        TokenType::Plus,
+        TokenType::Minus,
+        TokenType::Times,
+        TokenType::Divide,
+        TokenType::Modulo,
+        TokenType::Equals,
+        TokenType::DoubleEquals,
+        TokenType::NotEquals,
+        TokenType::Greater,
+        TokenType::Less,
+        TokenType::GreaterOrEqual,
+        TokenType::LessOrEqual,
+        TokenType::BooleanNot,
+        TokenType::Semicolon,
+        TokenType::Comma,
+        TokenType::Dot,
+        TokenType::OpeningParentheses,
+        TokenType::ClosingParentheses,
+        TokenType::OpeningBraces,
+        TokenType::ClosingBraces,
+        TokenType::OpeningBracket,
+        TokenType::ClosingBracket,
+        TokenType::Question,
+        TokenType::Colon,
+        TokenType::True,
+        TokenType::False,
+        TokenType::And,
+        TokenType::Or,
+        TokenType::Var,
+        TokenType::Print,
+        TokenType::If,
+        TokenType::Else,
+        TokenType::While,
+        TokenType::Fun,
+        TokenType::Return,
+        TokenType::For,
+        TokenType::Break,
+        TokenType::Continue,
+        TokenType::Nil,
+        TokenType::Number,
+        TokenType::String,
+        TokenType::Char,
+        TokenType::Identifier,
+        TokenType::Comment,
+        TokenType::EndOfFile,
+    ];
+
+    /// Token type whose name - as returned by its `Display` implementation - is `name`.
+    pub fn from_name(name: &str) -> Option<TokenType> {
+        TokenType::ALL
+            .iter()
+            .find(|token_type| token_type.to_string() == name)
+            .copied()
+    }
+
+    /// Whether this is the token type of a keyword, see `KEYWORDS`.
+    ///
+    /// This includes the logical operators `and` and `or`, as well as the literals `true`,
+    /// `false` and `nil`.
+    pub fn is_keyword(&self) -> bool {
+        self.as_keyword_str().is_some()
+    }
+
+    /// Whether this is the token type of a symbolic operator, such as `+` or `<=`.
+    ///
+    /// The logical operators `and` and `or` are keywords instead. `=` is included, even though
+    /// assignment isn't an expression.
+    pub fn is_operator(&self) -> bool {
+        matches!(
+            self,
+            TokenType::Plus
+                | TokenType::Minus
+                | TokenType::Times
+                | TokenType::Divide
+                | TokenType::Modulo
+                | TokenType::Equals
+                | TokenType::DoubleEquals
+                | TokenType::NotEquals
+                | TokenType::Greater
+                | TokenType::Less
+                | TokenType::GreaterOrEqual
+                | TokenType::LessOrEqual
+                | TokenType::BooleanNot
+        )
+    }
+
+    /// Whether this is the token type of an equality operator, `==` or `!=`.
+    pub fn is_equality(&self) -> bool {
+        matches!(self, TokenType::DoubleEquals | TokenType::NotEquals)
+    }
+
+    /// Whether this is the token type of an ordering comparison, such as `<`.
+    ///
+    /// Equality operators are not included, as they bind less tightly, see `is_equality`.
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            TokenType::Greater
+                | TokenType::Less
+                | TokenType::GreaterOrEqual
+                | TokenType::LessOrEqual
+        )
+    }
+
+    /// Whether this is the token type of an additive binary operator, `+` or `-`.
+    pub fn is_term(&self) -> bool {
+        matches!(self, TokenType::Plus | TokenType::Minus)
+    }
+
+    /// Whether this is the token type of a multiplicative binary operator, such as `*`.
+    pub fn is_factor(&self) -> bool {
+        matches!(
+            self,
+            TokenType::Times | TokenType::Divide | TokenType::Modulo
+        )
+    }
+
+    /// Whether this is the token type of a unary operator, `!` or `-`.
+    pub fn is_unary(&self) -> bool {
+        matches!(self, TokenType::BooleanNot | TokenType::Minus)
+    }
+
+    /// Whether this is the token type of a literal with a lexeme of its own, such as a number.
+    ///
+    /// The keyword literals `true`, `false` and `nil` are not included.
+    pub fn is_literal(&self) -> bool {
+        matches!(
+            self,
+            TokenType::Number | TokenType::String | TokenType::Char
+        )
+    }
+
+    /// The keyword this token type stands for, if it is the one of a keyword.
+    pub fn as_keyword_str(&self) -> Option<&'static str> {
+        KEYWORDS
+            .iter()
+            .find(|(_, token_type)| token_type == self)
+            .map(|(keyword, _)| *keyword)
+    }
+
+    /// The text of every token of this type, if it is fixed. That is the case for keywords,
+    /// operators and special characters.
+    pub fn as_fixed_str(&self) -> Option<&'static str> {
+        let symbol = match self {
+            TokenType::Plus => "+",
+            TokenType::Minus => "-",
+            TokenType::Times => "*",
+            TokenType::Divide => "/",
+            TokenType::Modulo => "%",
+            TokenType::Equals => "=",
+            TokenType::DoubleEquals => "==",
+            TokenType::NotEquals => "!=",
+            TokenType::Greater => ">",
+            TokenType::Less => "<",
+            TokenType::GreaterOrEqual => ">=",
+            TokenType::LessOrEqual => "<=",
+            TokenType::BooleanNot => "!",
+            TokenType::Semicolon => ";",
+            TokenType::Comma => ",",
+            TokenType::Dot => ".",
+            TokenType::OpeningParentheses => "(",
+            TokenType::ClosingParentheses => ")",
+            TokenType::OpeningBraces => "{",
+            TokenType::ClosingBraces => "}",
+            TokenType::OpeningBracket => "[",
+            TokenType::ClosingBracket => "]",
+            TokenType::Question => "?",
+            TokenType::Colon => ":",
+            _ => return self.as_keyword_str(),
+        };
+
+        Some(symbol)
+    }
+
+    /// Description of this token type for messages, such as `';'` or `identifier`.
+    pub fn describe(&self) -> String {
+        if let Some(text) = self.as_fixed_str() {
+            return format!("'{}'", text);
+        }
+
+        match self {
+            TokenType::Number => "number",
+            TokenType::String => "string",
+            TokenType::Char => "char",
+            TokenType::Identifier => "identifier",
+            TokenType::Comment => "comment",
+            TokenType::EndOfFile => "end of input",
+            _ => unreachable!("{} has a fixed text", self),
+        }
+        .to_string()
+    }
+}
+
+#[allow(non_upper_case_globals)]
+impl TokenType {
+    /// Old, misspelt, name of `TokenType::EndOfFile`.
+    #[deprecated(note = "renamed to `TokenType::EndOfFile`")]
+    pub const EndOfile: TokenType = TokenType::EndOfFile;
 }
 
 impl Display for TokenType {
@@ -69,3 +554,263 @@ impl Display for TokenType {
         write!(f, "{:?}", self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_end_of_file() {
+        assert_eq!(TokenType::EndOfile, TokenType::EndOfFile);
+
+        // Must still be usable as a pattern, as old code matches on it.
+        assert!(matches!(TokenType::EndOfFile, TokenType::EndOfile));
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(TokenType::Semicolon.describe(), "';'");
+        assert_eq!(TokenType::LessOrEqual.describe(), "'<='");
+        assert_eq!(TokenType::While.describe(), "'while'");
+        assert_eq!(TokenType::Identifier.describe(), "identifier");
+        assert_eq!(TokenType::EndOfFile.describe(), "end of input");
+
+        // Consistent with the lexer.
+        for token_type in TokenType::ALL {
+            if let Some(text) = token_type.as_fixed_str() {
+                let tokens = crate::lexer::Lexer::new(text).tokenize().unwrap();
+                assert_eq!(tokens[0].token_type, *token_type, "{}", text);
+            }
+        }
+    }
+
+    #[test]
+    fn test_keywords_and_reserved_words_disjoint() {
+        for word in RESERVED_WORDS {
+            assert!(keyword_type(word).is_none(), "{}", word);
+            assert!(is_reserved_word(word));
+        }
+
+        for (word, _) in KEYWORDS {
+            assert!(!is_reserved_word(word));
+        }
+    }
+
+    #[test]
+    fn test_format_token_table() {
+        let token = |token_type, lexeme: &'static str, line, column| Token {
+            token_type,
+            lexeme: lexeme.into(),
+            start: Position { line, column },
+            end: Position { line, column },
+            span: Span { start: 0, end: 0 },
+            symbol: None,
+            literal: None,
+        };
+
+        let tokens = vec![
+            token(TokenType::Var, "var", 1, 1),
+            token(TokenType::Identifier, "größe", 1, 5),
+            token(TokenType::Equals, "=", 1, 11),
+            token(TokenType::String, "line\nbreak", 1, 13),
+            token(TokenType::Semicolon, ";", 2, 7),
+            token(
+                TokenType::String,
+                "a very long string literal, indeed",
+                3,
+                1,
+            ),
+            token(TokenType::EndOfFile, "", 12, 1),
+        ];
+
+        assert_eq!(
+            format_token_table(&tokens),
+            concat!(
+                "#  Line  Column  Type        Lexeme\n",
+                "0     1       1  Var         var\n",
+                "1     1       5  Identifier  größe\n",
+                "2     1      11  Equals      =\n",
+                "3     1      13  String      line\\nbreak\n",
+                "4     2       7  Semicolon   ;\n",
+                "5     3       1  String      a very long string lite…\n",
+                "6    12       1  EndOfFile\n",
+            )
+        );
+
+        assert_eq!(format_token_table(&[]), "#  Line  Column  Type  Lexeme\n");
+    }
+
+    #[test]
+    fn test_from_name() {
+        for token_type in TokenType::ALL {
+            assert_eq!(
+                TokenType::from_name(&token_type.to_string()),
+                Some(*token_type)
+            );
+        }
+
+        assert_eq!(TokenType::from_name("Plus"), Some(TokenType::Plus));
+        assert_eq!(TokenType::from_name("plus"), None);
+        assert_eq!(TokenType::from_name(""), None);
+    }
+
+    #[test]
+    fn test_keyword_type() {
+        for (word, token_type) in KEYWORDS {
+            assert_eq!(keyword_type(word), Some(*token_type));
+            assert_eq!(token_type.as_keyword_str(), Some(*word));
+            // The name of the token type is the keyword itself.
+            assert_eq!(token_type.to_string().to_lowercase(), *word);
+        }
+
+        assert_eq!(keyword_type("while"), Some(TokenType::While));
+        assert_eq!(keyword_type("nil"), Some(TokenType::Nil));
+        assert_eq!(keyword_type("class"), None);
+        assert_eq!(keyword_type("While"), None);
+
+        assert_eq!(TokenType::Identifier.as_keyword_str(), None);
+        assert_eq!(TokenType::Plus.as_keyword_str(), None);
+    }
+
+    /// Token types of `TokenType::ALL` which satisfy `f`.
+    fn token_types_where(f: fn(&TokenType) -> bool) -> Vec<TokenType> {
+        TokenType::ALL.iter().copied().filter(f).collect()
+    }
+
+    #[test]
+    fn test_token_type_groups() {
+        use TokenType::*;
+
+        assert_eq!(
+            token_types_where(TokenType::is_keyword),
+            vec![
+                True, False, And, Or, Var, Print, If, Else, While, Fun, Return, For, Break,
+                Continue, Nil
+            ]
+        );
+        assert_eq!(
+            token_types_where(TokenType::is_operator),
+            vec![
+                Plus,
+                Minus,
+                Times,
+                Divide,
+                Modulo,
+                Equals,
+                DoubleEquals,
+                NotEquals,
+                Greater,
+                Less,
+                GreaterOrEqual,
+                LessOrEqual,
+                BooleanNot
+            ]
+        );
+        assert_eq!(
+            token_types_where(TokenType::is_equality),
+            vec![DoubleEquals, NotEquals]
+        );
+        assert_eq!(
+            token_types_where(TokenType::is_comparison),
+            vec![Greater, Less, GreaterOrEqual, LessOrEqual]
+        );
+        assert_eq!(token_types_where(TokenType::is_term), vec![Plus, Minus]);
+        assert_eq!(
+            token_types_where(TokenType::is_factor),
+            vec![Times, Divide, Modulo]
+        );
+        assert_eq!(
+            token_types_where(TokenType::is_unary),
+            vec![Minus, BooleanNot]
+        );
+        assert_eq!(
+            token_types_where(TokenType::is_literal),
+            vec![Number, String, Char]
+        );
+    }
+
+    #[test]
+    fn test_parse_number() {
+        for (lexeme, value) in [
+            ("0", 0.0),
+            ("12.5", 12.5),
+            ("1_000", 1000.0),
+            ("1.5e3", 1500.0),
+            ("25E-1", 2.5),
+            ("0x1f", 31.0),
+            ("0xFF_FF", 65535.0),
+            ("0b1010", 10.0),
+        ] {
+            assert_eq!(Literal::parse_number(lexeme), Some(value), "{}", lexeme);
+        }
+
+        for lexeme in ["1e999", "0x", "0b2", "abc", ""] {
+            assert_eq!(Literal::parse_number(lexeme), None, "{}", lexeme);
+        }
+
+        // Too large for an integer, but not for a float.
+        assert_eq!(
+            Literal::parse_number("0x1_0000_0000_0000_0000"),
+            Some(2f64.powi(64))
+        );
+    }
+
+    #[test]
+    fn test_literal_from_lexeme() {
+        assert_eq!(
+            Literal::from_lexeme(TokenType::Number, "12.5"),
+            Some(Literal::Number(12.5))
+        );
+        assert_eq!(
+            Literal::from_lexeme(TokenType::String, "a\nb"),
+            Some(Literal::Str("a\nb".to_string()))
+        );
+        assert_eq!(
+            Literal::from_lexeme(TokenType::True, "true"),
+            Some(Literal::Bool(true))
+        );
+        assert_eq!(
+            Literal::from_lexeme(TokenType::False, "FALSE"),
+            Some(Literal::Bool(false))
+        );
+        assert_eq!(
+            Literal::from_lexeme(TokenType::Nil, "nil"),
+            Some(Literal::None)
+        );
+        assert_eq!(Literal::from_lexeme(TokenType::Identifier, "x"), None);
+        assert_eq!(Literal::from_lexeme(TokenType::Number, "1e999"), None);
+
+        assert_eq!(Literal::Number(f64::NAN), Literal::Number(f64::NAN));
+        assert_ne!(Literal::Number(1.0), Literal::Bool(true));
+    }
+
+    #[test]
+    fn test_token_helpers() {
+        let token = |token_type, lexeme: &'static str| Token {
+            token_type,
+            lexeme: lexeme.into(),
+            start: Position { line: 1, column: 1 },
+            end: Position { line: 1, column: 1 },
+            span: Span { start: 0, end: 0 },
+            symbol: None,
+            literal: None,
+        };
+
+        let foo = token(TokenType::Identifier, "foo");
+        assert!(foo.is(TokenType::Identifier));
+        assert!(!foo.is(TokenType::String));
+        assert!(foo.is_identifier("foo"));
+        assert!(!foo.is_identifier("bar"));
+        assert!(!token(TokenType::String, "foo").is_identifier("foo"));
+        assert!(!foo.is_keyword() && !foo.is_operator());
+
+        assert!(token(TokenType::While, "while").is_keyword());
+        assert!(token(TokenType::LessOrEqual, "<=").is_operator());
+
+        // Token types can be used as keys, and tokens be copied.
+        let token_types: std::collections::HashSet<_> = TokenType::ALL.iter().copied().collect();
+        assert_eq!(token_types.len(), TokenType::ALL.len());
+        assert_eq!(foo.clone(), foo);
+    }
+}
@@ -0,0 +1,298 @@
+//! Visitors of syntax trees, so that passes over them - such as printing, analysis or evaluation -
+//! don't need to match on the nodes themselves.
+//!
+//! Each visitor has one method per kind of node, which receives the parts of that node. Visitors
+//! decide themselves whether and in which order to visit the children, by calling `accept` on
+//! them. The return type is up to the visitor, e.g. `String` for a printer or `()` for a pass
+//! which only collects information.
+
+use crate::{
+    ast::{Expr, Stmt},
+    token::{Literal, Token},
+};
+
+pub trait ExprVisitor<R> {
+    fn visit_literal(&mut self, value: &Literal, token: &Token<'static>) -> R;
+    fn visit_variable(&mut self, name: &Token<'static>) -> R;
+    fn visit_assign(&mut self, name: &Token<'static>, value: &Expr) -> R;
+    fn visit_grouping(&mut self, expr: &Expr) -> R;
+    fn visit_unary(&mut self, operator: &Token<'static>, operand: &Expr) -> R;
+    fn visit_binary(&mut self, left: &Expr, operator: &Token<'static>, right: &Expr) -> R;
+    fn visit_call(&mut self, callee: &Expr, args: &[Expr], closing_paren: &Token<'static>) -> R;
+}
+
+pub trait StmtVisitor<R> {
+    fn visit_expression_stmt(&mut self, expr: &Expr) -> R;
+    fn visit_print(&mut self, keyword: &Token<'static>, expr: &Expr) -> R;
+    fn visit_var(&mut self, name: &Token<'static>, initializer: Option<&Expr>) -> R;
+    fn visit_if(
+        &mut self,
+        keyword: &Token<'static>,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> R;
+    fn visit_while(&mut self, keyword: &Token<'static>, condition: &Expr, body: &Stmt) -> R;
+    fn visit_block(&mut self, statements: &[Stmt]) -> R;
+    fn visit_function(
+        &mut self,
+        name: &Token<'static>,
+        params: &[Token<'static>],
+        body: &[Stmt],
+    ) -> R;
+    fn visit_return(&mut self, keyword: &Token<'static>, value: Option<&Expr>) -> R;
+    fn visit_break(&mut self, keyword: &Token<'static>) -> R;
+    fn visit_continue(&mut self, keyword: &Token<'static>) -> R;
+}
+
+impl Expr {
+    /// Have `visitor` visit this expression, returning its result.
+    pub fn accept<R, V: ExprVisitor<R>>(&self, visitor: &mut V) -> R {
+        match self {
+            Expr::Literal { token, .. } => {
+                let value = token
+                    .literal
+                    .as_ref()
+                    .expect("literal token without a value");
+                visitor.visit_literal(value, token)
+            }
+            Expr::Variable { name, .. } => visitor.visit_variable(name),
+            Expr::Assign { name, value, .. } => visitor.visit_assign(name, value),
+            Expr::Grouping { expr, .. } => visitor.visit_grouping(expr),
+            Expr::Unary {
+                operator, operand, ..
+            } => visitor.visit_unary(operator, operand),
+            Expr::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => visitor.visit_binary(left, operator, right),
+            Expr::Call {
+                callee,
+                args,
+                closing_paren,
+                ..
+            } => visitor.visit_call(callee, args, closing_paren),
+        }
+    }
+}
+
+impl Stmt {
+    /// Have `visitor` visit this statement, returning its result.
+    pub fn accept<R, V: StmtVisitor<R>>(&self, visitor: &mut V) -> R {
+        match self {
+            Stmt::Expression { expr, .. } => visitor.visit_expression_stmt(expr),
+            Stmt::Print { keyword, expr, .. } => visitor.visit_print(keyword, expr),
+            Stmt::Var {
+                name, initializer, ..
+            } => visitor.visit_var(name, initializer.as_ref()),
+            Stmt::If {
+                keyword,
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => visitor.visit_if(keyword, condition, then_branch, else_branch.as_deref()),
+            Stmt::While {
+                keyword,
+                condition,
+                body,
+                ..
+            } => visitor.visit_while(keyword, condition, body),
+            Stmt::Block { statements, .. } => visitor.visit_block(statements),
+            Stmt::Function {
+                name, params, body, ..
+            } => visitor.visit_function(name, params, body),
+            Stmt::Return { keyword, value, .. } => visitor.visit_return(keyword, value.as_ref()),
+            Stmt::Break { keyword, .. } => visitor.visit_break(keyword),
+            Stmt::Continue { keyword, .. } => visitor.visit_continue(keyword),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::PROGRAM, lexer::Lexer, parser::Parser};
+
+    /// Counts the literals of a program.
+    struct LiteralCounter;
+
+    impl ExprVisitor<usize> for LiteralCounter {
+        fn visit_literal(&mut self, _value: &Literal, _token: &Token<'static>) -> usize {
+            1
+        }
+
+        fn visit_variable(&mut self, _name: &Token<'static>) -> usize {
+            0
+        }
+
+        fn visit_assign(&mut self, _name: &Token<'static>, value: &Expr) -> usize {
+            value.accept(self)
+        }
+
+        fn visit_grouping(&mut self, expr: &Expr) -> usize {
+            expr.accept(self)
+        }
+
+        fn visit_unary(&mut self, _operator: &Token<'static>, operand: &Expr) -> usize {
+            operand.accept(self)
+        }
+
+        fn visit_binary(&mut self, left: &Expr, _operator: &Token<'static>, right: &Expr) -> usize {
+            left.accept(self) + right.accept(self)
+        }
+
+        fn visit_call(
+            &mut self,
+            callee: &Expr,
+            args: &[Expr],
+            _closing_paren: &Token<'static>,
+        ) -> usize {
+            callee.accept(self) + args.iter().map(|arg| arg.accept(self)).sum::<usize>()
+        }
+    }
+
+    impl StmtVisitor<usize> for LiteralCounter {
+        fn visit_expression_stmt(&mut self, expr: &Expr) -> usize {
+            expr.accept(self)
+        }
+
+        fn visit_print(&mut self, _keyword: &Token<'static>, expr: &Expr) -> usize {
+            expr.accept(self)
+        }
+
+        fn visit_var(&mut self, _name: &Token<'static>, initializer: Option<&Expr>) -> usize {
+            initializer.map_or(0, |expr| expr.accept(self))
+        }
+
+        fn visit_if(
+            &mut self,
+            _keyword: &Token<'static>,
+            condition: &Expr,
+            then_branch: &Stmt,
+            else_branch: Option<&Stmt>,
+        ) -> usize {
+            condition.accept(self)
+                + then_branch.accept(self)
+                + else_branch.map_or(0, |stmt| stmt.accept(self))
+        }
+
+        fn visit_while(
+            &mut self,
+            _keyword: &Token<'static>,
+            condition: &Expr,
+            body: &Stmt,
+        ) -> usize {
+            condition.accept(self) + body.accept(self)
+        }
+
+        fn visit_block(&mut self, statements: &[Stmt]) -> usize {
+            statements.iter().map(|stmt| stmt.accept(self)).sum()
+        }
+
+        fn visit_function(
+            &mut self,
+            _name: &Token<'static>,
+            _params: &[Token<'static>],
+            body: &[Stmt],
+        ) -> usize {
+            self.visit_block(body)
+        }
+
+        fn visit_return(&mut self, _keyword: &Token<'static>, value: Option<&Expr>) -> usize {
+            value.map_or(0, |expr| expr.accept(self))
+        }
+
+        fn visit_break(&mut self, _keyword: &Token<'static>) -> usize {
+            0
+        }
+
+        fn visit_continue(&mut self, _keyword: &Token<'static>) -> usize {
+            0
+        }
+    }
+
+    fn count_literals(source: &str) -> usize {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+
+        program
+            .iter()
+            .map(|stmt| stmt.accept(&mut LiteralCounter))
+            .sum()
+    }
+
+    #[test]
+    fn test_count_literals() {
+        assert_eq!(count_literals(""), 0);
+        assert_eq!(count_literals("a = b;"), 0);
+        assert_eq!(count_literals("print -1 + 2 * \"x\";"), 3);
+        assert_eq!(
+            count_literals("var a; if (true) { a = nil; } else print 'c';"),
+            3
+        );
+        assert_eq!(count_literals("while (false) {{ print 1; }}"), 2);
+        assert_eq!(count_literals("fun f(a) { print a(1)(2, 3); }"), 3);
+        assert_eq!(
+            count_literals("fun f() { while (1) { break; } return 2; }"),
+            2
+        );
+
+        // `true`, 123, 12.3, "123 ", 1, 2, `true`, `true`, `false`, 2, two strings in the block,
+        // "yes", "no", 1, 10, and 1.
+        assert_eq!(count_literals(PROGRAM), 17);
+    }
+
+    #[test]
+    fn test_accept_order() {
+        /// Collects the names of variables in the order they are visited.
+        struct Names(Vec<String>);
+
+        impl ExprVisitor<()> for Names {
+            fn visit_literal(&mut self, _value: &Literal, _token: &Token<'static>) {}
+
+            fn visit_variable(&mut self, name: &Token<'static>) {
+                self.0.push(name.lexeme.to_string());
+            }
+
+            fn visit_assign(&mut self, name: &Token<'static>, value: &Expr) {
+                self.0.push(name.lexeme.to_string());
+                value.accept(self);
+            }
+
+            fn visit_grouping(&mut self, expr: &Expr) {
+                expr.accept(self);
+            }
+
+            fn visit_unary(&mut self, _operator: &Token<'static>, operand: &Expr) {
+                operand.accept(self);
+            }
+
+            fn visit_binary(&mut self, left: &Expr, _operator: &Token<'static>, right: &Expr) {
+                left.accept(self);
+                right.accept(self);
+            }
+
+            fn visit_call(
+                &mut self,
+                callee: &Expr,
+                args: &[Expr],
+                _closing_paren: &Token<'static>,
+            ) {
+                callee.accept(self);
+                for arg in args {
+                    arg.accept(self);
+                }
+            }
+        }
+
+        let tokens = Lexer::new("a = b * -(c + d) + e(f, g)").tokenize().unwrap();
+        let expr = Parser::new(tokens).parse_expression().unwrap();
+        let mut names = Names(Vec::new());
+        expr.accept(&mut names);
+
+        assert_eq!(names.0, vec!["a", "b", "c", "d", "e", "f", "g"]);
+    }
+}
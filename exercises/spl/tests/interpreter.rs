@@ -0,0 +1,87 @@
+//! Runs programs through the `interpreter` binary, from source to output.
+
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+/// Run `source` through the interpreter, passing it on stdin.
+fn run(source: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_interpreter"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(source.as_bytes())
+        .unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_fixture_program() {
+    // The fixture averages two variables which it never declares.
+    let source = format!(
+        "var min = 1;\nvar max = 9;\n{}",
+        include_str!("fixture.spl")
+    );
+    let output = run(&source);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Hello , world !\nHello , SPL Prime world !\nno\n1\n2\n3\n4\n5\n6\n7\n8\n9\n"
+    );
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_fixture_program_undeclared_variables() {
+    let output = run(include_str!("fixture.spl"));
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("undefined variable 'min' at line 11, column 16"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_errors() {
+    let output = run("print 1;\nprint 1 + true;\nprint 2;");
+    assert!(!output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "1\n");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(
+        stderr,
+        concat!(
+            "error: cannot add Number and Bool at line 2, column 9\n",
+            "  |\n",
+            "2 | print 1 + true;\n",
+            "  |         ^\n",
+        )
+    );
+
+    // Nothing is executed unless the whole program parses.
+    let output = run("print 1;\nprint (2;");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(
+        stderr,
+        concat!(
+            "error: expected ')' but found ';' at line 2, column 9\n",
+            "  |\n",
+            "2 | print (2;\n",
+            "  |         ^\n",
+        )
+    );
+}